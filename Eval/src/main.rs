@@ -0,0 +1,1237 @@
+// 离线评估工具
+// 用与解锁时相同的 检测 → 对齐 → 提取特征 → 活体检测 → 比对 流程，跑一遍已标注的图片目录
+// 不依赖 Windows API，可以在 Linux 上运行
+// 与界面共用 utils 下的代码，这里用不到的函数不报警告
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/active_liveness.rs"]
+mod active_liveness;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/attempt_log.rs"]
+mod attempt_log;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/camera_chain.rs"]
+mod camera_chain;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/camera_lease.rs"]
+mod camera_lease;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/camera_warmup.rs"]
+mod camera_warmup;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/capture_profile.rs"]
+mod capture_profile;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/face_conflicts.rs"]
+mod face_conflicts;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/face_finder.rs"]
+mod face_finder;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/face_pipeline.rs"]
+mod face_pipeline;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/face_policy.rs"]
+mod face_policy;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/face_quality.rs"]
+mod face_quality;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/face_tracker.rs"]
+mod face_tracker;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/frame_normalize.rs"]
+mod frame_normalize;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/frame_source.rs"]
+mod frame_source;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/liveness.rs"]
+mod liveness;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/metrics.rs"]
+mod metrics;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/model_manifest.rs"]
+mod model_manifest;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/preprocess.rs"]
+mod preprocess;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/replay.rs"]
+mod replay;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/spoof_heuristics.rs"]
+mod spoof_heuristics;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/template_gallery.rs"]
+mod template_gallery;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/unlock_attempt.rs"]
+mod unlock_attempt;
+#[allow(dead_code)]
+#[path = "../../UI/src-tauri/src/utils/video_enroll.rs"]
+mod video_enroll;
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::Instant,
+};
+
+use camera_chain::{CameraChain, ChainConfig};
+use face_finder::{FaceFinder, FinderConfig};
+use face_pipeline::{
+    create_detector, create_liveness, create_recognizer, detect_faces, detect_faces_scaled,
+    extract_feature, face_values, largest_face, match_score, FACE_SCORE_INDEX,
+};
+use face_quality::QualityConfig;
+use frame_normalize::{modality_compatible, normalize_frame, read_normalized, Modality};
+use frame_source::open_source_uri;
+use liveness::{LivenessAggregation, LivenessVotingConfig};
+use metrics::{calibrate, CalibrationReport, ScoreSample};
+use model_manifest::{sha256_file, ModelManifest, MANIFEST_FILE};
+use opencv::{core::Mat, prelude::*};
+use face_policy::{MultiFaceConfig, MultiFacePolicy};
+use preprocess::{preprocess_frame, Equalization, PreprocessConfig};
+use replay::{FrameOutputs, ReplayReader, ReplayRecord};
+use serde::Serialize;
+use serde_json::json;
+use spoof_heuristics::{fuse, spoof_scores, FusionConfig, FusionRule, SpoofScores};
+use template_gallery::{AdaptiveConfig, TemplateGallery};
+use unlock_attempt::{load_primary_template, AttemptConfig, AttemptModels, EnrolledFace, UnlockAttempt};
+
+const USAGE: &str = "用法: face_eval --data <图片目录> [选项]
+
+目录结构:
+  <图片目录>/<人名>/*.jpg          本人的真实图片
+  <图片目录>/<人名>/attack/*.jpg   针对此人的攻击图片（照片、屏幕翻拍等）
+  单通道、16 位 PNG 等红外图片按红外处理，只与同为红外的录入图片比对
+
+选项:
+  --models <目录>                  模型所在目录，默认 ./resources
+  --out <前缀>                     输出文件前缀，默认 ./eval，会生成 .json / _images.csv / _curve.csv
+  --enroll <数量>                  每人用于录入的图片数量，默认 1
+  --threshold <0~100>              比对阈值，与面容的 threshold 一致，默认 40
+  --face-detection-threshold <0~1> 人脸检测置信度，默认 0.9
+  --liveness-threshold <0~1>       活体检测阈值，默认 0.5
+  --no-liveness                    不做活体检测
+  --liveness-frames <数量>         --replay 时多帧活体投票的窗口帧数，默认 5
+  --liveness-method <mean|median|min>
+                                   --replay 时活体分数的聚合方式，默认 mean
+  --liveness-pass-ratio <0~1>      --replay 时窗口内至少多少比例的帧单独通过，默认 0.6
+  --target-far <0~1>               推荐阈值时的目标误识率，默认 0.001
+  --modality <rgb|ir>              强制指定全部图片的成像方式，默认按图片自动判断
+  --working-size <像素>            检测前把图片长边缩小到此尺寸，默认 0 不缩小
+  --bench <次数>                   只测检测耗时：每张图片按 整帧 / 缩小 / 区域跟踪 各跑指定次数
+  --spoof-heuristics               计算摩尔纹、高光、边框、色域四项翻拍/打印检查，并与活体分数融合
+  --fusion <weighted|veto|min>     融合规则，默认 veto
+  --veto-threshold <0~1>           veto 规则下单项检查的否决阈值，默认 0.8
+  --preprocess <clahe|hist|none>   检测和提取特征前做直方图均衡，默认不做任何预处理
+  --clahe-clip <数值>              CLAHE 的对比度限制，默认 2.0
+  --gamma <数值>                   gamma 校正，小于 1 提亮，默认 1 不处理
+  --white-balance                  灰度世界白平衡
+  --source <地址>                  不读标注目录，逐帧处理画面来源，地址与 open_camera 的 source 相同
+  --frames <数量>                  --source 处理的帧数，默认 100
+                                   --source 可以指定多次，按顺序作为摄像头链，当前来源读不到画面或拍不到人脸时换下一个
+  --no-face-frames <数量>          摄像头链连续多少帧没有人脸后切换，默认 15
+  --replay <文件>                  用当前的流程重新处理解锁尝试的回放文件（.fwreplay），与录制时的结果对比
+  --faces <目录>                   --replay 时比对的面容，为程序目录下的 faces，读取每个 .faceimg 和对应的 .gallery
+                                   比对阈值使用 --threshold
+  --adaptive-templates             --replay 解锁成功时按自适应学习的默认设置判断是否会学习新模板，只记录决定，不修改模板库
+  --multi-face <largest|allEnrolled|rejectUnknown>
+                                   --replay 时画面中有多张人脸的解锁策略，与设置中的多人脸策略相同，默认 largest
+  --multi-face-min-size <像素>     rejectUnknown 时参与判断的人脸框短边最小像素，默认 60
+
+其他用法:
+  face_eval --write-manifest [--models <目录>]   计算模型的 SHA-256 并写回模型目录下的 models.json";
+
+const IMAGE_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "bmp"];
+
+struct Args {
+    data_dir: PathBuf,
+    resource_dir: PathBuf,
+    out_prefix: PathBuf,
+    enroll_count: usize,
+    threshold: f64,
+    face_detection_threshold: f32,
+    liveness_threshold: f32,
+    liveness_enabled: bool,
+    // 多帧活体投票，阈值与 liveness_threshold 一致
+    voting: LivenessVotingConfig,
+    target_far: f64,
+    modality: Option<Modality>,
+    working_size: i32,
+    bench_rounds: usize,
+    write_manifest: bool,
+    fusion: FusionConfig,
+    preprocess: PreprocessConfig,
+    sources: Vec<String>,
+    frames: usize,
+    chain: ChainConfig,
+    replay: Option<PathBuf>,
+    faces_dir: Option<PathBuf>,
+    adaptive: AdaptiveConfig,
+    multi_face: MultiFaceConfig,
+}
+
+// 一张图片的处理结果
+#[derive(Serialize)]
+struct ImageResult {
+    path: String,
+    label: String,
+    // 是否为攻击图片
+    attack: bool,
+    // enroll 用于录入，probe 用于测试
+    role: &'static str,
+    modality: Option<Modality>,
+    face_count: i32,
+    face_score: Option<f32>,
+    liveness_score: Option<f32>,
+    liveness_passed: Option<bool>,
+    // 四项翻拍/打印检查的攻击分数
+    spoof: Option<SpoofScores>,
+    // 与活体分数融合后的分数和结果，未开启时为空
+    fused_score: Option<f32>,
+    fused_passed: Option<bool>,
+    // 与本人模板的相似度
+    genuine_score: Option<f64>,
+    // 相似度最高的人和分数
+    best_label: Option<String>,
+    best_score: Option<f64>,
+    // 整个流程是否会解锁到本人
+    accepted: bool,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    people: usize,
+    enrolled_images: usize,
+    probe_images: usize,
+    attack_images: usize,
+    // 未检测到人脸的比例
+    failure_to_acquire_rate: f64,
+    // 最相似的人是本人的比例
+    rank1_accuracy: f64,
+    // 真实图片被接受的比例
+    genuine_accept_rate: f64,
+    // 攻击图片被接受的比例
+    attack_accept_rate: f64,
+    // 攻击图片通过活体检测的比例
+    apcer: Option<f64>,
+    // 真实图片未通过活体检测的比例
+    bpcer: Option<f64>,
+    // 按融合结果计算的 APCER / BPCER
+    fused_apcer: Option<f64>,
+    fused_bpcer: Option<f64>,
+    // 按图片类别统计的翻拍/打印检查：genuine 为真实的测试图片，攻击图片按文件名第一个 _ 之前的部分分类
+    spoof_groups: BTreeMap<String, SpoofGroup>,
+}
+
+// 一类图片四项检查的平均攻击分数和融合后的通过率
+#[derive(Serialize)]
+struct SpoofGroup {
+    images: usize,
+    moire: f64,
+    specular: f64,
+    bezel: f64,
+    gamut: f64,
+    // 攻击图片即这一类的 APCER，真实图片即 1 - BPCER
+    fused_pass_rate: f64,
+}
+
+struct Models {
+    detector: opencv::core::Ptr<opencv::objdetect::FaceDetectorYN>,
+    recognizer: opencv::core::Ptr<opencv::objdetect::FaceRecognizerSF>,
+    liveness: Option<opencv::dnn::Net>,
+}
+
+// 单张图片过完流水线后的中间结果
+struct Processed {
+    modality: Modality,
+    face_count: i32,
+    face_score: Option<f32>,
+    liveness_score: Option<f32>,
+    spoof: Option<SpoofScores>,
+    feature: Option<Mat>,
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(&args) {
+        eprintln!("评估失败: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        data_dir: PathBuf::new(),
+        resource_dir: PathBuf::from("resources"),
+        out_prefix: PathBuf::from("eval"),
+        enroll_count: 1,
+        threshold: 0.40,
+        face_detection_threshold: 0.9,
+        liveness_threshold: 0.5,
+        liveness_enabled: true,
+        voting: LivenessVotingConfig::default(),
+        target_far: 0.001,
+        modality: None,
+        working_size: 0,
+        bench_rounds: 0,
+        write_manifest: false,
+        fusion: FusionConfig::default(),
+        // 默认不做任何处理，指定了参数才会实际处理
+        preprocess: PreprocessConfig {
+            enabled: true,
+            equalization: Equalization::None,
+            ..PreprocessConfig::default()
+        },
+        sources: Vec::new(),
+        frames: 100,
+        chain: ChainConfig::default(),
+        replay: None,
+        faces_dir: None,
+        adaptive: AdaptiveConfig::default(),
+        multi_face: MultiFaceConfig::default(),
+    };
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or_else(|| format!("参数 {} 缺少值", arg));
+        match arg.as_str() {
+            "--data" => args.data_dir = PathBuf::from(value()?),
+            "--models" => args.resource_dir = PathBuf::from(value()?),
+            "--out" => args.out_prefix = PathBuf::from(value()?),
+            "--enroll" => args.enroll_count = parse_number(&value()?)?,
+            "--threshold" => args.threshold = parse_number::<f64>(&value()?)? / 100.0,
+            "--face-detection-threshold" => args.face_detection_threshold = parse_number(&value()?)?,
+            "--liveness-threshold" => args.liveness_threshold = parse_number(&value()?)?,
+            "--no-liveness" => args.liveness_enabled = false,
+            "--liveness-frames" => args.voting.window_size = parse_number::<usize>(&value()?)?.max(1),
+            "--liveness-method" => {
+                args.voting.aggregation = match value()?.as_str() {
+                    "mean" => LivenessAggregation::Mean,
+                    "median" => LivenessAggregation::Median,
+                    "min" => LivenessAggregation::Min,
+                    other => return Err(format!("未知的聚合方式 {}", other)),
+                }
+            }
+            "--liveness-pass-ratio" => args.voting.min_pass_ratio = parse_number(&value()?)?,
+            "--target-far" => args.target_far = parse_number(&value()?)?,
+            "--modality" => {
+                let modality = value()?;
+                args.modality = Some(
+                    Modality::from_options(Some(modality.as_str()))
+                        .ok_or_else(|| format!("未知的成像方式 {}", modality))?,
+                );
+            }
+            "--working-size" => args.working_size = parse_number(&value()?)?,
+            "--bench" => args.bench_rounds = parse_number(&value()?)?,
+            "--write-manifest" => args.write_manifest = true,
+            "--spoof-heuristics" => args.fusion.enabled = true,
+            "--fusion" => {
+                args.fusion.rule = match value()?.as_str() {
+                    "weighted" => FusionRule::Weighted,
+                    "veto" => FusionRule::Veto,
+                    "min" => FusionRule::Min,
+                    other => return Err(format!("未知的融合规则 {}", other)),
+                }
+            }
+            "--veto-threshold" => args.fusion.veto_threshold = parse_number(&value()?)?,
+            "--preprocess" => {
+                args.preprocess.equalization = match value()?.as_str() {
+                    "clahe" => Equalization::Clahe,
+                    "hist" => Equalization::Histogram,
+                    "none" => Equalization::None,
+                    other => return Err(format!("未知的预处理方式 {}", other)),
+                }
+            }
+            "--clahe-clip" => args.preprocess.clahe_clip_limit = parse_number(&value()?)?,
+            "--gamma" => args.preprocess.gamma = parse_number(&value()?)?,
+            "--white-balance" => args.preprocess.white_balance = true,
+            "--source" => args.sources.push(value()?),
+            "--frames" => args.frames = parse_number(&value()?)?,
+            "--no-face-frames" => args.chain.no_face_frames = parse_number(&value()?)?,
+            "--replay" => args.replay = Some(PathBuf::from(value()?)),
+            "--faces" => args.faces_dir = Some(PathBuf::from(value()?)),
+            "--adaptive-templates" => args.adaptive.enabled = true,
+            "--multi-face" => {
+                args.multi_face.policy = match value()?.as_str() {
+                    "largest" => MultiFacePolicy::Largest,
+                    "allEnrolled" => MultiFacePolicy::AllEnrolled,
+                    "rejectUnknown" => MultiFacePolicy::RejectUnknown,
+                    other => return Err(format!("未知的多人脸策略 {}", other)),
+                }
+            }
+            "--multi-face-min-size" => args.multi_face.min_face_size = parse_number(&value()?)?,
+            "-h" | "--help" => return Err(String::new()),
+            _ => return Err(format!("未知参数 {}", arg)),
+        }
+    }
+
+    if args.write_manifest {
+        return Ok(args);
+    }
+    if args.sources.is_empty() && args.replay.is_none() && args.data_dir.as_os_str().is_empty() {
+        return Err(String::from("必须指定 --data"));
+    }
+    if args.enroll_count == 0 {
+        return Err(String::from("--enroll 至少为 1"));
+    }
+    // 融合后的通过阈值与活体阈值一致，和程序中的设置相同
+    args.fusion.threshold = args.liveness_threshold;
+    args.voting.threshold = args.liveness_threshold;
+    Ok(args)
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("无法解析数值 {}", value))
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    if args.write_manifest {
+        return write_manifest(&args.resource_dir);
+    }
+
+    let mut models = Models {
+        detector: create_detector(&args.resource_dir)?,
+        recognizer: create_recognizer(&args.resource_dir)?,
+        liveness: if args.liveness_enabled {
+            Some(create_liveness(&args.resource_dir)?)
+        } else {
+            None
+        },
+    };
+
+    if let Some(replay) = &args.replay {
+        return run_replay(&mut models, args, replay);
+    }
+    match args.sources.as_slice() {
+        [] => {}
+        [source] => return run_stream(&mut models, args, source),
+        sources => return run_chain(&mut models, args, sources),
+    }
+
+    let dataset = scan_dataset(&args.data_dir)?;
+    if dataset.is_empty() {
+        return Err(format!("{} 下没有找到任何图片", args.data_dir.display()));
+    }
+    if args.bench_rounds > 0 {
+        return run_bench(&mut models, args, &dataset);
+    }
+
+    // 录入：每人取前 enroll_count 张真实图片
+    let mut results: Vec<ImageResult> = Vec::new();
+    // 每人的录入模板，附带成像方式
+    let mut galleries: BTreeMap<String, Vec<(Modality, Mat)>> = BTreeMap::new();
+    for (label, (genuine, _)) in &dataset {
+        for path in genuine.iter().take(args.enroll_count) {
+            let mut result = new_result(path, label, false, "enroll");
+            match process_image(&mut models, args, path) {
+                Ok(processed) => {
+                    fill_result(&mut result, &processed, args);
+                    match processed.feature {
+                        Some(feature) => galleries
+                            .entry(label.clone())
+                            .or_default()
+                            .push((processed.modality, feature)),
+                        None => result.error = Some(String::from("录入图片中未检测到人脸")),
+                    }
+                }
+                Err(e) => result.error = Some(e),
+            }
+            results.push(result);
+        }
+    }
+
+    // 测试：剩下的真实图片和全部攻击图片
+    let mut samples: Vec<ScoreSample> = Vec::new();
+    for (label, (genuine, attacks)) in &dataset {
+        let probes = genuine
+            .iter()
+            .skip(args.enroll_count)
+            .map(|p| (p, false))
+            .chain(attacks.iter().map(|p| (p, true)));
+        for (path, attack) in probes {
+            let mut result = new_result(path, label, attack, "probe");
+            let processed = match process_image(&mut models, args, path) {
+                Ok(processed) => processed,
+                Err(e) => {
+                    result.error = Some(e);
+                    results.push(result);
+                    continue;
+                }
+            };
+            fill_result(&mut result, &processed, args);
+
+            if let Some(feature) = &processed.feature {
+                for (gallery_label, templates) in &galleries {
+                    let mut score = f64::MIN;
+                    for (modality, template) in templates {
+                        if modality_compatible(processed.modality, *modality) {
+                            score = score.max(match_score(&models.recognizer, template, feature)?);
+                        }
+                    }
+                    // 没有同一成像方式的模板，无法比对
+                    if score == f64::MIN {
+                        continue;
+                    }
+                    // 攻击图片不参与 FAR/FRR，单独统计
+                    if !attack {
+                        samples.push(ScoreSample {
+                            score,
+                            genuine: gallery_label == label,
+                        });
+                    }
+                    if gallery_label == label {
+                        result.genuine_score = Some(score);
+                    }
+                    if result.best_score.map_or(true, |best| score > best) {
+                        result.best_score = Some(score);
+                        result.best_label = Some(gallery_label.clone());
+                    }
+                }
+            }
+
+            result.accepted = result.best_label.as_deref() == Some(label.as_str())
+                && result.best_score.map_or(false, |s| s >= args.threshold)
+                && result.fused_passed.or(result.liveness_passed).unwrap_or(true);
+            results.push(result);
+        }
+    }
+
+    let report = calibrate(&samples, args.target_far);
+    let summary = summarize(&dataset, &results, galleries.len());
+    write_outputs(args, &summary, &report, &results)?;
+
+    println!(
+        "评估完成：{} 人，测试图片 {} 张，攻击图片 {} 张",
+        summary.people, summary.probe_images, summary.attack_images
+    );
+    println!(
+        "EER {:.4}（阈值 {:.2}），Rank-1 {:.4}，真实通过率 {:.4}，攻击通过率 {:.4}",
+        report.eer,
+        report.eer_threshold * 100.0,
+        summary.rank1_accuracy,
+        summary.genuine_accept_rate,
+        summary.attack_accept_rate
+    );
+    if let (Some(apcer), Some(bpcer)) = (summary.fused_apcer, summary.fused_bpcer) {
+        println!("融合后 APCER {:.4}，BPCER {:.4}", apcer, bpcer);
+    }
+    for (kind, group) in &summary.spoof_groups {
+        println!(
+            "翻拍/打印检查 {}：{} 张，摩尔纹 {:.3}，高光 {:.3}，边框 {:.3}，色域 {:.3}，融合后通过率 {:.4}",
+            kind, group.images, group.moire, group.specular, group.bezel, group.gamut, group.fused_pass_rate
+        );
+    }
+    Ok(())
+}
+
+// 计算清单中每个模型的 SHA-256 并写回，发布前运行
+fn write_manifest(resource_dir: &Path) -> Result<(), String> {
+    let mut manifest = ModelManifest::load(resource_dir).map_err(|e| e.to_string())?;
+    for entry in manifest.models.iter_mut() {
+        let path = resource_dir.join(&entry.file);
+        entry.sha256 = sha256_file(&path).map_err(|e| format!("读取 {} 失败: {}", path.display(), e))?;
+        println!("{}  {}", entry.sha256, entry.file);
+    }
+    let manifest_path = resource_dir.join(MANIFEST_FILE);
+    let text = serde_json::to_string_pretty(&manifest).map_err(|e| format!("序列化模型清单失败: {}", e))?;
+    fs::write(&manifest_path, text + "\n")
+        .map_err(|e| format!("写入 {} 失败: {}", manifest_path.display(), e))
+}
+
+// 扫描目录，返回 人名 → (真实图片, 攻击图片)，图片按文件名排序
+fn scan_dataset(data_dir: &Path) -> Result<BTreeMap<String, (Vec<PathBuf>, Vec<PathBuf>)>, String> {
+    let mut dataset = BTreeMap::new();
+    let entries = fs::read_dir(data_dir)
+        .map_err(|e| format!("读取目录 {} 失败: {}", data_dir.display(), e))?;
+    for entry in entries.flatten() {
+        let person_dir = entry.path();
+        if !person_dir.is_dir() {
+            continue;
+        }
+        let label = entry.file_name().to_string_lossy().to_string();
+        let genuine = list_images(&person_dir)?;
+        let attack_dir = person_dir.join("attack");
+        let attacks = if attack_dir.is_dir() {
+            list_images(&attack_dir)?
+        } else {
+            Vec::new()
+        };
+        if !genuine.is_empty() || !attacks.is_empty() {
+            dataset.insert(label, (genuine, attacks));
+        }
+    }
+    Ok(dataset)
+}
+
+fn list_images(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut images: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("读取目录 {} 失败: {}", dir.display(), e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .map_or(false, |ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        })
+        .collect();
+    images.sort();
+    Ok(images)
+}
+
+// 单张图片走一遍流水线
+fn process_image(models: &mut Models, args: &Args, path: &Path) -> Result<Processed, String> {
+    let frame = read_normalized(path.to_str().unwrap_or(""), args.modality)?;
+    let img = preprocess_frame(&frame.bgr, &args.preprocess)?;
+    let faces = detect_faces_scaled(
+        &mut models.detector,
+        &img,
+        args.face_detection_threshold,
+        args.working_size,
+    )?;
+    let face = match largest_face(&faces)? {
+        Some(face) => face,
+        None => {
+            return Ok(Processed {
+                modality: frame.modality,
+                face_count: 0,
+                face_score: None,
+                liveness_score: None,
+                spoof: None,
+                feature: None,
+            })
+        }
+    };
+
+    let values = face_values(&face, 0)?;
+    let feature = extract_feature(&mut models.recognizer, &img, &face)?;
+    let liveness_score = match models.liveness.as_mut() {
+        Some(net) => {
+            let crop = liveness::face_crop(&img, &values)?;
+            Some(liveness::liveness_score(net, &crop)?)
+        }
+        None => None,
+    };
+    let spoof = if args.fusion.enabled {
+        Some(spoof_scores(&img, &values, frame.modality)?)
+    } else {
+        None
+    };
+
+    Ok(Processed {
+        modality: frame.modality,
+        face_count: faces.rows(),
+        face_score: Some(values[FACE_SCORE_INDEX]),
+        liveness_score,
+        spoof,
+        feature: Some(feature),
+    })
+}
+
+// 检测耗时基准：同一张图片连续检测，模拟摄像头的连续帧
+// full 整帧，scaled 缩小到工作尺寸，roi 缩小并只在上一帧人脸附近检测
+fn run_bench(
+    models: &mut Models,
+    args: &Args,
+    dataset: &BTreeMap<String, (Vec<PathBuf>, Vec<PathBuf>)>,
+) -> Result<(), String> {
+    let working_size = if args.working_size > 0 {
+        args.working_size
+    } else {
+        FinderConfig::default().working_size
+    };
+    let modes = ["full", "scaled", "roi"];
+    let mut timings: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+
+    let images = dataset.values().flat_map(|(genuine, attacks)| genuine.iter().chain(attacks.iter()));
+    for path in images {
+        let frame = read_normalized(path.to_str().unwrap_or(""), args.modality)?;
+        let img = preprocess_frame(&frame.bgr, &args.preprocess)?;
+        let mut finder = FaceFinder::new(FinderConfig {
+            working_size,
+            ..FinderConfig::default()
+        });
+        for mode in modes {
+            for _ in 0..args.bench_rounds {
+                let start = Instant::now();
+                match mode {
+                    "full" => detect_faces(&mut models.detector, &img, args.face_detection_threshold)?,
+                    "scaled" => detect_faces_scaled(
+                        &mut models.detector,
+                        &img,
+                        args.face_detection_threshold,
+                        working_size,
+                    )?,
+                    _ => finder.detect(&mut models.detector, &img, args.face_detection_threshold)?,
+                };
+                timings
+                    .entry(mode)
+                    .or_default()
+                    .push(start.elapsed().as_secs_f64() * 1000.0);
+            }
+        }
+    }
+
+    let mut bench_csv = String::from("mode,frames,mean_ms,p50_ms,p95_ms\n");
+    println!("检测耗时（工作尺寸 {}，每张图片 {} 帧）：", working_size, args.bench_rounds);
+    for mode in modes {
+        let mut values = timings.remove(mode).unwrap_or_default();
+        if values.is_empty() {
+            continue;
+        }
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let percentile = |p: f64| values[((values.len() - 1) as f64 * p).round() as usize];
+        println!(
+            "  {:<7} 平均 {:>8.2} ms  P50 {:>8.2} ms  P95 {:>8.2} ms",
+            mode,
+            mean,
+            percentile(0.5),
+            percentile(0.95)
+        );
+        bench_csv.push_str(&format!(
+            "{},{},{:.3},{:.3},{:.3}\n",
+            mode,
+            values.len(),
+            mean,
+            percentile(0.5),
+            percentile(0.95)
+        ));
+    }
+
+    let bench_path = with_suffix(&args.out_prefix, "_bench.csv");
+    fs::write(&bench_path, bench_csv)
+        .map_err(|e| format!("写入 {} 失败: {}", bench_path.display(), e))
+}
+
+// 逐帧处理画面来源，检查没有摄像头时整条流程能否跑通，以及每帧的耗时
+fn run_stream(models: &mut Models, args: &Args, source: &str) -> Result<(), String> {
+    let mut frame_source = open_source_uri(source)?;
+    println!("画面来源：{}", frame_source.describe());
+
+    let mut stream_csv = String::from("frame,modality,face_count,face_score,liveness_score,ms\n");
+    let mut detected = 0usize;
+    let mut total_ms = 0.0;
+    let mut frame = Mat::default();
+    for index in 0..args.frames {
+        if let Some(wait) = frame_source.next_frame_in() {
+            thread::sleep(wait);
+        }
+        let read = frame_source
+            .read(&mut frame)
+            .map_err(|e| format!("读取画面失败: {:?}", e))?;
+        if !read || frame.empty() {
+            break;
+        }
+        let start = Instant::now();
+        let processed = process_stream_frame(models, args, &frame)?;
+        if processed.face_score.is_some() {
+            detected += 1;
+        }
+        let ms = start.elapsed().as_secs_f64() * 1000.0;
+        total_ms += ms;
+        stream_csv.push_str(&format!(
+            "{},{},{},{},{},{:.3}\n",
+            index,
+            format!("{:?}", processed.modality).to_lowercase(),
+            processed.face_count,
+            optional(processed.face_score),
+            optional(processed.liveness_score),
+            ms
+        ));
+    }
+
+    let frames = stream_csv.lines().count() - 1;
+    println!(
+        "处理 {} 帧，检测到人脸 {} 帧，平均 {:.2} ms/帧",
+        frames,
+        detected,
+        if frames > 0 { total_ms / frames as f64 } else { 0.0 }
+    );
+    let stream_path = with_suffix(&args.out_prefix, "_stream.csv");
+    fs::write(&stream_path, stream_csv)
+        .map_err(|e| format!("写入 {} 失败: {}", stream_path.display(), e))
+}
+
+// 摄像头链：按顺序使用多个画面来源，记录每帧使用的来源和发生的切换
+fn run_chain(models: &mut Models, args: &Args, sources: &[String]) -> Result<(), String> {
+    let mut chain = CameraChain::new(sources.to_vec(), args.chain);
+    let mut chain_csv = String::from("frame,source,face_count,face_score,switch_from,switch_to,reason,ms\n");
+    let mut used = vec![0usize; sources.len()];
+    let mut switch_count = 0usize;
+    let mut frame = Mat::default();
+    for index in 0..args.frames {
+        if let Some(wait) = chain.next_frame_in() {
+            thread::sleep(wait);
+        }
+        let (active, mut switches) = match chain.read(&mut frame, |i| open_source_uri(&sources[i])) {
+            Ok(read) => read,
+            Err(e) => {
+                println!("第 {} 帧：{}", index, e);
+                break;
+            }
+        };
+        let start = Instant::now();
+        let processed = process_stream_frame(models, args, &frame)?;
+        let ms = start.elapsed().as_secs_f64() * 1000.0;
+        switches.extend(chain.report(processed.face_score.is_some()));
+        used[active] += 1;
+        switch_count += switches.len();
+
+        let row = |switch: Option<&camera_chain::Switch>| {
+            format!(
+                "{},{},{},{},{},{},{},{:.3}\n",
+                index,
+                active,
+                processed.face_count,
+                optional(processed.face_score),
+                optional(switch.map(|s| s.from)),
+                optional(switch.map(|s| s.to)),
+                switch.map(|s| format!("{:?}", s.reason).to_lowercase()).unwrap_or_default(),
+                ms
+            )
+        };
+        if switches.is_empty() {
+            chain_csv.push_str(&row(None));
+        }
+        for switch in &switches {
+            chain_csv.push_str(&row(Some(switch)));
+        }
+    }
+
+    for (i, source) in sources.iter().enumerate() {
+        println!("来源 {}：{}，使用 {} 帧", i, source, used[i]);
+    }
+    println!("切换 {} 次", switch_count);
+    let chain_path = with_suffix(&args.out_prefix, "_chain.csv");
+    fs::write(&chain_path, chain_csv)
+        .map_err(|e| format!("写入 {} 失败: {}", chain_path.display(), e))
+}
+
+// 用当前的流程重新处理回放文件中的每一帧，与录制时的检测和活体结果对比
+// 处理流程与界面录制回放时相同（unlock_attempt），最后给出当前流程的多帧活体投票结果
+fn run_replay(models: &mut Models, args: &Args, path: &Path) -> Result<(), String> {
+    let mut reader = ReplayReader::open(path)?;
+    let header = reader.header().clone();
+    println!("回放：{}，录制于 {}，来源 {}", header.recorder, header.created_at, header.source);
+    if !header.models.is_empty() {
+        println!("录制时的模型：{}", header.models.join("，"));
+    }
+
+    let config = AttemptConfig {
+        face_detection_threshold: args.face_detection_threshold,
+        finder: FinderConfig {
+            working_size: args.working_size,
+            ..FinderConfig::default()
+        },
+        liveness: args.liveness_enabled.then_some(args.voting),
+        fusion: args.fusion,
+        quality: QualityConfig::default(),
+        adaptive: args.adaptive,
+        multi_face: args.multi_face,
+    };
+    let mut attempt_models = AttemptModels {
+        detector: &mut models.detector,
+        recognizer: &mut models.recognizer,
+        liveness: models.liveness.as_mut(),
+    };
+    let faces = match &args.faces_dir {
+        Some(faces_dir) => load_enrolled_faces(&mut attempt_models, args, faces_dir)?,
+        None => Vec::new(),
+    };
+    let mut attempt = UnlockAttempt::new(config, faces);
+
+    // 画面和录制时的识别结果是前后两条记录，识别结果归到它前面的画面
+    let mut rows: Vec<(u64, FrameOutputs, f64, Option<FrameOutputs>)> = Vec::new();
+    while let Some(record) = reader.next_record()? {
+        match record {
+            ReplayRecord::Frame { timestamp_ms, image } => {
+                let start = Instant::now();
+                let normalized = normalize_frame(&image, args.modality)?;
+                let img = preprocess_frame(&normalized.bgr, &args.preprocess)?;
+                let current = attempt.observe(&mut attempt_models, &img, normalized.modality)?;
+                rows.push((timestamp_ms, current, start.elapsed().as_secs_f64() * 1000.0, None));
+            }
+            ReplayRecord::Outputs(outputs) => {
+                if let Some(row) = rows.last_mut() {
+                    row.3 = Some(outputs);
+                }
+            }
+            ReplayRecord::Outcome(outcome) => println!(
+                "录制时的结果：{}{}",
+                if outcome.unlocked { "解锁成功" } else { "解锁失败" },
+                outcome.reason.map(|r| format!("，{}", r)).unwrap_or_default()
+            ),
+        }
+    }
+
+    let mut replay_csv = String::from(
+        "frame,timestamp_ms,recorded_faces,current_faces,recorded_face_score,current_face_score,recorded_liveness,current_liveness,current_quality,ms\n",
+    );
+    let mut face_changed = 0usize;
+    for (index, (timestamp_ms, current, ms, recorded)) in rows.iter().enumerate() {
+        let recorded = recorded.clone().unwrap_or_default();
+        let recorded_score = largest_face_score(&recorded);
+        let current_score = largest_face_score(current);
+        if recorded_score.is_some() != current_score.is_some() {
+            face_changed += 1;
+        }
+        replay_csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{:.3}\n",
+            index,
+            timestamp_ms,
+            recorded.faces.len(),
+            current.faces.len(),
+            optional(recorded_score),
+            optional(current_score),
+            optional(recorded.liveness),
+            optional(current.liveness),
+            optional(current.quality),
+            ms
+        ));
+    }
+
+    println!("处理 {} 帧，是否检测到人脸与录制时不同的有 {} 帧", rows.len(), face_changed);
+    // 只给出学习的决定，不修改模板库
+    let result = attempt.finish(None);
+    println!(
+        "当前流程的结果：{}{}",
+        if result.unlocked { "解锁成功" } else { "解锁失败" },
+        result.reason.as_deref().map(|r| format!("，{}", r)).unwrap_or_default()
+    );
+    if let Some(verdict) = &result.log.liveness {
+        println!(
+            "当前流程的活体投票：{}，聚合分数 {:.4}，通过帧占比 {:.2}（{} 帧）",
+            if verdict.passed { "通过" } else { "未通过" },
+            verdict.aggregate_score,
+            verdict.pass_ratio,
+            verdict.frame_scores.len()
+        );
+    }
+    if let Some(decision) = &result.log.multi_face {
+        println!(
+            "当前流程的多人脸策略：画面中有 {} 张人脸，{}",
+            decision.face_count,
+            decision.reason.as_deref().unwrap_or("允许解锁")
+        );
+    }
+    let replay_path = with_suffix(&args.out_prefix, "_replay.csv");
+    fs::write(&replay_path, replay_csv)
+        .map_err(|e| format!("写入 {} 失败: {}", replay_path.display(), e))?;
+    let attempt_path = with_suffix(&args.out_prefix, "_replay_attempt.json");
+    fs::write(&attempt_path, result.log.to_json_data())
+        .map_err(|e| format!("写入 {} 失败: {}", attempt_path.display(), e))
+}
+
+// 读取面容目录中每个 .faceimg 的主模板和对应的模板库，面容ID按文件名顺序从 1 开始
+fn load_enrolled_faces(models: &mut AttemptModels, args: &Args, faces_dir: &Path) -> Result<Vec<EnrolledFace>, String> {
+    let mut tokens: Vec<String> = fs::read_dir(faces_dir)
+        .map_err(|e| format!("读取目录 {} 失败: {}", faces_dir.display(), e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("faceimg"))
+        .filter_map(|path| path.file_stem().and_then(|stem| stem.to_str()).map(String::from))
+        .collect();
+    tokens.sort();
+
+    let mut faces = Vec::new();
+    for (index, face_token) in tokens.into_iter().enumerate() {
+        let primary = load_primary_template(
+            models.detector,
+            models.recognizer,
+            faces_dir,
+            &face_token,
+            args.modality.unwrap_or_default(),
+            args.face_detection_threshold,
+        )?;
+        let gallery = TemplateGallery::load(faces_dir, &face_token)?;
+        println!("面容 {}：{}，模板库 {} 个模板", index + 1, face_token, gallery.templates.len());
+        faces.push(EnrolledFace {
+            id: index as i64 + 1,
+            face_token,
+            threshold: args.threshold as f32,
+            primary,
+            gallery,
+        });
+    }
+    Ok(faces)
+}
+
+// 与 largest_face 一致，取面积最大的人脸的置信度
+fn largest_face_score(outputs: &FrameOutputs) -> Option<f32> {
+    outputs
+        .faces
+        .iter()
+        .max_by(|a, b| (a[2] * a[3]).total_cmp(&(b[2] * b[3])))
+        .map(|face| face[FACE_SCORE_INDEX])
+}
+
+// 画面来源的一帧：检测人脸、提取特征、活体检测
+fn process_stream_frame(models: &mut Models, args: &Args, frame: &Mat) -> Result<Processed, String> {
+    let normalized = normalize_frame(frame, args.modality)?;
+    let img = preprocess_frame(&normalized.bgr, &args.preprocess)?;
+    let faces = detect_faces_scaled(
+        &mut models.detector,
+        &img,
+        args.face_detection_threshold,
+        args.working_size,
+    )?;
+    let (face_score, liveness_score) = match largest_face(&faces)? {
+        Some(face) => {
+            let values = face_values(&face, 0)?;
+            extract_feature(&mut models.recognizer, &img, &face)?;
+            let liveness_score = match models.liveness.as_mut() {
+                Some(net) => Some(liveness::liveness_score(net, &liveness::face_crop(&img, &values)?)?),
+                None => None,
+            };
+            (Some(values[FACE_SCORE_INDEX]), liveness_score)
+        }
+        None => (None, None),
+    };
+    Ok(Processed {
+        modality: normalized.modality,
+        face_count: faces.rows(),
+        face_score,
+        liveness_score,
+        spoof: None,
+        feature: None,
+    })
+}
+
+fn new_result(path: &Path, label: &str, attack: bool, role: &'static str) -> ImageResult {
+    ImageResult {
+        path: path.display().to_string(),
+        label: label.to_string(),
+        attack,
+        role,
+        modality: None,
+        face_count: 0,
+        face_score: None,
+        liveness_score: None,
+        liveness_passed: None,
+        spoof: None,
+        fused_score: None,
+        fused_passed: None,
+        genuine_score: None,
+        best_label: None,
+        best_score: None,
+        accepted: false,
+        error: None,
+    }
+}
+
+fn fill_result(result: &mut ImageResult, processed: &Processed, args: &Args) {
+    result.modality = Some(processed.modality);
+    result.face_count = processed.face_count;
+    result.face_score = processed.face_score;
+    result.liveness_score = processed.liveness_score;
+    result.liveness_passed = processed
+        .liveness_score
+        .map(|score| score >= args.liveness_threshold);
+    result.spoof = processed.spoof;
+    if let Some(spoof) = &processed.spoof {
+        // 未做活体检测时只看四项检查，活体分数按 1 计
+        let fused = fuse(processed.liveness_score.unwrap_or(1.0), spoof, &args.fusion);
+        result.fused_score = Some(fused.score);
+        result.fused_passed = Some(fused.passed);
+    }
+}
+
+fn summarize(
+    dataset: &BTreeMap<String, (Vec<PathBuf>, Vec<PathBuf>)>,
+    results: &[ImageResult],
+    people: usize,
+) -> Summary {
+    let probes: Vec<&ImageResult> = results
+        .iter()
+        .filter(|r| r.role == "probe" && !r.attack)
+        .collect();
+    let attacks: Vec<&ImageResult> = results.iter().filter(|r| r.attack).collect();
+    let detected_probes: Vec<&&ImageResult> = probes.iter().filter(|r| r.face_count > 0).collect();
+
+    let rate = |count: usize, total: usize| {
+        if total == 0 {
+            0.0
+        } else {
+            count as f64 / total as f64
+        }
+    };
+
+    let attack_liveness: Vec<bool> = attacks.iter().filter_map(|r| r.liveness_passed).collect();
+    let probe_liveness: Vec<bool> = probes.iter().filter_map(|r| r.liveness_passed).collect();
+    let attack_fused: Vec<bool> = attacks.iter().filter_map(|r| r.fused_passed).collect();
+    let probe_fused: Vec<bool> = probes.iter().filter_map(|r| r.fused_passed).collect();
+
+    Summary {
+        people,
+        enrolled_images: results.iter().filter(|r| r.role == "enroll").count(),
+        probe_images: probes.len(),
+        attack_images: dataset.values().map(|(_, a)| a.len()).sum(),
+        failure_to_acquire_rate: rate(probes.len() - detected_probes.len(), probes.len()),
+        rank1_accuracy: rate(
+            detected_probes
+                .iter()
+                .filter(|r| r.best_label.as_deref() == Some(r.label.as_str()))
+                .count(),
+            detected_probes.len(),
+        ),
+        genuine_accept_rate: rate(probes.iter().filter(|r| r.accepted).count(), probes.len()),
+        attack_accept_rate: rate(attacks.iter().filter(|r| r.accepted).count(), attacks.len()),
+        apcer: (!attack_liveness.is_empty())
+            .then(|| rate(attack_liveness.iter().filter(|p| **p).count(), attack_liveness.len())),
+        bpcer: (!probe_liveness.is_empty())
+            .then(|| rate(probe_liveness.iter().filter(|p| !**p).count(), probe_liveness.len())),
+        fused_apcer: (!attack_fused.is_empty())
+            .then(|| rate(attack_fused.iter().filter(|p| **p).count(), attack_fused.len())),
+        fused_bpcer: (!probe_fused.is_empty())
+            .then(|| rate(probe_fused.iter().filter(|p| !**p).count(), probe_fused.len())),
+        spoof_groups: spoof_groups(&probes, &attacks),
+    }
+}
+
+// 只统计算过四项检查的图片，未开启 --spoof-heuristics 时为空
+fn spoof_groups(probes: &[&ImageResult], attacks: &[&ImageResult]) -> BTreeMap<String, SpoofGroup> {
+    let mut groups: BTreeMap<String, Vec<(SpoofScores, bool)>> = BTreeMap::new();
+    let samples = probes
+        .iter()
+        .map(|r| (String::from("genuine"), r))
+        .chain(attacks.iter().map(|r| (attack_kind(&r.path), r)));
+    for (kind, r) in samples {
+        if let (Some(spoof), Some(passed)) = (r.spoof, r.fused_passed) {
+            groups.entry(kind).or_default().push((spoof, passed));
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(kind, samples)| {
+            let count = samples.len() as f64;
+            let mean = |f: fn(&SpoofScores) -> f32| samples.iter().map(|(s, _)| f(s) as f64).sum::<f64>() / count;
+            let group = SpoofGroup {
+                images: samples.len(),
+                moire: mean(|s| s.moire),
+                specular: mean(|s| s.specular),
+                bezel: mean(|s| s.bezel),
+                gamut: mean(|s| s.gamut),
+                fused_pass_rate: samples.iter().filter(|(_, passed)| *passed).count() as f64 / count,
+            };
+            (kind, group)
+        })
+        .collect()
+}
+
+// 攻击方式取文件名第一个 _ 之前的部分，如 phone_01.jpg 为 phone
+fn attack_kind(path: &str) -> String {
+    let stem = Path::new(path).file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    match stem.split_once('_') {
+        Some((kind, _)) if !kind.is_empty() => kind.to_string(),
+        _ => String::from("other"),
+    }
+}
+
+fn write_outputs(
+    args: &Args,
+    summary: &Summary,
+    report: &CalibrationReport,
+    results: &[ImageResult],
+) -> Result<(), String> {
+    let output = json!({
+        "config": {
+            "data_dir": args.data_dir.display().to_string(),
+            "enroll_count": args.enroll_count,
+            "threshold": args.threshold * 100.0,
+            "face_detection_threshold": args.face_detection_threshold,
+            "working_size": args.working_size,
+            "modality": args.modality,
+            "liveness_enabled": args.liveness_enabled,
+            "liveness_threshold": args.liveness_threshold,
+            "fusion": args.fusion,
+            "preprocess": args.preprocess.is_active().then_some(args.preprocess),
+        },
+        "summary": summary,
+        "verification": report,
+        "images": results,
+    });
+    let json_path = with_suffix(&args.out_prefix, ".json");
+    let json_text = serde_json::to_string_pretty(&output).map_err(|e| format!("序列化结果失败: {}", e))?;
+    fs::write(&json_path, json_text)
+        .map_err(|e| format!("写入 {} 失败: {}", json_path.display(), e))?;
+
+    let mut images_csv = String::from(
+        "path,label,attack,role,modality,face_count,face_score,liveness_score,liveness_passed,moire,specular,bezel,gamut,fused_score,fused_passed,genuine_score,best_label,best_score,accepted,error\n",
+    );
+    for r in results {
+        let row = [
+            csv_field(&r.path),
+            csv_field(&r.label),
+            r.attack.to_string(),
+            r.role.to_string(),
+            r.modality.map(|m| format!("{:?}", m).to_lowercase()).unwrap_or_default(),
+            r.face_count.to_string(),
+            optional(r.face_score),
+            optional(r.liveness_score),
+            optional(r.liveness_passed),
+            optional(r.spoof.map(|s| s.moire)),
+            optional(r.spoof.map(|s| s.specular)),
+            optional(r.spoof.map(|s| s.bezel)),
+            optional(r.spoof.map(|s| s.gamut)),
+            optional(r.fused_score),
+            optional(r.fused_passed),
+            optional(r.genuine_score),
+            csv_field(r.best_label.as_deref().unwrap_or("")),
+            optional(r.best_score),
+            r.accepted.to_string(),
+            csv_field(r.error.as_deref().unwrap_or("")),
+        ];
+        images_csv.push_str(&row.join(","));
+        images_csv.push('\n');
+    }
+    let images_path = with_suffix(&args.out_prefix, "_images.csv");
+    fs::write(&images_path, images_csv)
+        .map_err(|e| format!("写入 {} 失败: {}", images_path.display(), e))?;
+
+    // ROC/DET 曲线：每个阈值的 FAR、FRR
+    let mut curve_csv = String::from("threshold,far,frr\n");
+    for point in &report.curve {
+        curve_csv.push_str(&format!("{:.2},{:.6},{:.6}\n", point.threshold, point.far, point.frr));
+    }
+    let curve_path = with_suffix(&args.out_prefix, "_curve.csv");
+    fs::write(&curve_path, curve_csv)
+        .map_err(|e| format!("写入 {} 失败: {}", curve_path.display(), e))?;
+
+    Ok(())
+}
+
+fn with_suffix(prefix: &Path, suffix: &str) -> PathBuf {
+    let mut path = prefix.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
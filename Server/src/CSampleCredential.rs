@@ -100,7 +100,10 @@ impl ICredentialProviderCredential_Impl for SampleCredential_Impl {
     /// dwfieldid: 字段ID
     fn GetStringValue(&self, dwfieldid: u32) -> windows_core::Result<PWSTR> {
         info!("SampleCredential::GetStringValue - 获取字段 {} 的文本内容", dwfieldid);
+        // 解锁服务发来的提示优先显示
+        let prompt = self.shared_creds.lock().map(|c| c.prompt.clone()).unwrap_or_default();
         let val = match dwfieldid {
+            1 if !prompt.is_empty() => prompt.as_str(),
             1 => "FaceWinUnlock-Tauri-请勿点击此磁贴",  // 字段1的文本内容
             _ => {
                 warn!("SampleCredential::GetStringValue - 字段 {} 无文本内容", dwfieldid);
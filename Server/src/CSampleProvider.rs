@@ -34,6 +34,7 @@ impl SampleProvider {
             password: String::new(),
             domain: String::from("."),
             is_ready: false,
+            prompt: String::new(),
         }));
 
         // 获取认证包ID
@@ -122,12 +122,18 @@ pub fn read_facewinunlock_registry(key_name: &str) -> windows::core::Result<Stri
 // 8a7b9c6d-4e5f-89a0-8b7c-6d5e4f3e2d1c
 pub const CLSID_SampleProvider: GUID = GUID::from_u128(0x8a7b9c6d_4e5f_89a0_8b7c_6d5e4f3e2d1c);
 
+// 解锁服务通过管道发送的提示消息前缀，例如 "prompt:请向左转头"
+// 收到后写入 SharedCredentials.prompt 并调用 CredentialsChanged 刷新磁贴，发送 "prompt:" 清空提示
+pub const PIPE_PROMPT_PREFIX: &str = "prompt:";
+
 // 共享的凭据信息
 pub struct SharedCredentials {
     pub username: String,
     pub password: String,
     pub domain: String,
     pub is_ready: bool,
+    // 磁贴上显示的提示（动作活体检测等），为空时显示默认文本
+    pub prompt: String,
 }
 
 /// 类工厂实现，用于创建凭据提供程序实例
@@ -14,17 +14,28 @@ use windows::Win32::{
 pub mod modules;
 pub mod proc;
 pub mod utils;
+use modules::active_liveness::{check_active_liveness, start_active_liveness, stop_active_liveness};
+use modules::calibration::calibrate_threshold;
+use modules::camera::{
+    get_camera_capture, get_camera_devices, get_camera_modality, preview_preprocess, resolve_camera_device,
+    set_camera_capture,
+};
+use modules::conflicts::{check_enrollment_conflict, get_consistency_report};
 use modules::faces::{
     check_face_from_camera, check_face_from_img, save_face_registration, verify_face,
 };
+use modules::gallery::{get_face_gallery, reset_learned_templates};
 use modules::init::{
     check_admin_privileges, check_camera_status, deploy_core_components, uninstall_init,
 };
 use modules::options::write_to_registry;
+use modules::preview::{start_preview, stop_preview, stop_preview_stream};
+use modules::quality::check_face_quality;
+use modules::replay::{delete_replay, list_replays, record_replay};
+use modules::video_enroll::enroll_from_video;
 use opencv::{
     core::Ptr,
     objdetect::{FaceDetectorYN, FaceRecognizerSF},
-    videoio::VideoCapture,
 };
 use proc::wnd_proc_subclass;
 use tauri_plugin_log::{Target, TargetKind};
@@ -32,7 +43,7 @@ use utils::api::{
     add_scheduled_task, check_process_running, check_scheduled_task, close_app,
     delete_process_running, disable_scheduled_task, get_camera, get_now_username, init_model,
     load_opencv_model, open_camera, open_directory, stop_camera, test_win_logon, unload_model, get_uuid_v4, get_cache_dir, run_scheduled_task,
-    check_trigger_via_xml
+    check_trigger_via_xml, verify_models, get_model_versions
 };
 mod tray;
 use tray::create_system_tray;
@@ -47,7 +58,12 @@ pub struct AppState {
     pub detector: Option<OpenCVResource<Ptr<FaceDetectorYN>>>,
     pub recognizer: Option<OpenCVResource<Ptr<FaceRecognizerSF>>>,
     pub liveness: Option<OpenCVResource<opencv::dnn::Net>>,
-    pub camera: Option<OpenCVResource<VideoCapture>>,
+    // 摄像头，或 open_camera 传入 source 时的图片目录、视频文件、合成画面
+    pub camera: Option<OpenCVResource<Box<dyn utils::frame_source::FrameSource>>>,
+    // 摄像头的租约，关闭摄像头时释放
+    pub camera_lease: Option<utils::camera_lease::Lease>,
+    // 冲突检查用到的面容图片特征，卸载模型时清空
+    pub feature_cache: utils::face_conflicts::FeatureCache,
 }
 
 lazy_static::lazy_static! {
@@ -60,6 +76,8 @@ lazy_static::lazy_static! {
         recognizer: None,
         liveness: None,
         camera: None,
+        camera_lease: None,
+        feature_cache: utils::face_conflicts::FeatureCache::default(),
     });
 
     // 全局只读软件根目录
@@ -162,6 +180,12 @@ pub fn run() {
                         tauri::WindowEvent::CloseRequested { api, .. } => {
                             api.prevent_close();
                             let _ = window.hide();
+                            // 窗口隐藏后没人看预览，停止推送并释放读取画面的线程
+                            stop_preview_stream("窗口已隐藏");
+                        }
+                        // 最小化时窗口大小变为 0
+                        tauri::WindowEvent::Resized(size) if size.width == 0 || size.height == 0 => {
+                            stop_preview_stream("窗口已最小化");
                         }
                         _ => {}
                     }
@@ -178,12 +202,43 @@ pub fn run() {
                 check_face_from_camera,
                 verify_face,
                 save_face_registration,
+                enroll_from_video,
                 // 配置模块
                 write_to_registry,
+                // 阈值校准
+                calibrate_threshold,
+                // 动作活体检测
+                start_active_liveness,
+                check_active_liveness,
+                stop_active_liveness,
+                // 人脸质量评估
+                check_face_quality,
+                // 面容模板库
+                get_face_gallery,
+                reset_learned_templates,
+                // 重复录入检查
+                check_enrollment_conflict,
+                get_consistency_report,
+                // 摄像头
+                get_camera_modality,
+                preview_preprocess,
+                get_camera_devices,
+                resolve_camera_device,
+                get_camera_capture,
+                set_camera_capture,
+                // 实时预览
+                start_preview,
+                stop_preview,
+                // 录制与回放
+                record_replay,
+                list_replays,
+                delete_replay,
                 // 通用api
                 get_now_username,
                 test_win_logon,
                 init_model,
+                verify_models,
+                get_model_versions,
                 open_camera,
                 stop_camera,
                 get_camera,
@@ -0,0 +1,195 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    utils::{
+        active_liveness::{eye_openness, pick_challenges, ChallengeStatus, ChallengeVerifier, LandmarkFrame},
+        custom_result::CustomResult,
+        face_finder::{FaceFinder, FinderConfig},
+        face_pipeline::{encode_base64_image, face_values},
+        face_tracker::FaceTracker,
+        frame_normalize::normalize_frame,
+        preprocess::{preprocess_frame, PreprocessConfig},
+    },
+    APP_STATE,
+};
+use opencv::{core::Mat, imgproc, prelude::*};
+use serde_json::json;
+use tauri_plugin_log::log::info;
+
+// 进行中的动作活体检测
+struct ActiveLivenessSession {
+    finder: FaceFinder,
+    tracker: FaceTracker,
+    verifier: ChallengeVerifier,
+    // 验证的人脸，第一次检测到的最大人脸
+    track_id: Option<u32>,
+    // 当前摄像头的画面预处理
+    preprocess: PreprocessConfig,
+    // 验证的人脸最正对摄像头的一帧（偏航绝对值, 未预处理的画面），通过后作为录入的图片
+    verified_frame: Option<(f32, Mat)>,
+}
+
+lazy_static::lazy_static! {
+    static ref ACTIVE_LIVENESS: Mutex<Option<ActiveLivenessSession>> = Mutex::new(None);
+}
+
+// 开始动作活体检测，随机挑选 count 个动作
+// preprocess: 当前摄像头的画面预处理设置，不传时不处理
+// options: 设置 {设置名称: 设置值}，从 detectWorkingSize、detectRoiTracking 读取检测的工作尺寸和区域跟踪
+#[tauri::command]
+pub fn start_active_liveness(
+    count: usize,
+    preprocess: Option<PreprocessConfig>,
+    options: Option<HashMap<String, String>>,
+) -> Result<CustomResult, CustomResult> {
+    let options = options.unwrap_or_default();
+    let challenges = pick_challenges(uuid::Uuid::new_v4().as_bytes(), count.max(1));
+    info!("开始动作活体检测，动作：{:?}", challenges);
+
+    let mut session = ACTIVE_LIVENESS
+        .lock()
+        .map_err(|e| CustomResult::error(Some(format!("获取动作活体状态失败 {}", e)), None))?;
+    *session = Some(ActiveLivenessSession {
+        finder: FaceFinder::new(FinderConfig::from_options(|key| options.get(key).cloned())),
+        tracker: FaceTracker::default(),
+        verifier: ChallengeVerifier::new(challenges.clone()),
+        track_id: None,
+        preprocess: preprocess.unwrap_or_default(),
+        verified_frame: None,
+    });
+
+    Ok(CustomResult::success(None, Some(json!({"challenges": challenges}))))
+}
+
+// 从摄像头读取一帧，推进动作活体检测，返回当前状态和提示
+// 通过后返回的 raw_base64 是完成动作的同一张人脸的画面，录入时必须使用这张图片，不能重新从摄像头取
+#[tauri::command]
+pub fn check_active_liveness(face_detection_threshold: f32) -> Result<CustomResult, CustomResult> {
+    let mut session_guard = ACTIVE_LIVENESS
+        .lock()
+        .map_err(|e| CustomResult::error(Some(format!("获取动作活体状态失败 {}", e)), None))?;
+    let session = session_guard.as_mut().ok_or_else(|| {
+        CustomResult::error(Some(String::from("动作活体检测未开始")), None)
+    })?;
+
+    let mut app_state = APP_STATE
+        .lock()
+        .map_err(|e| CustomResult::error(Some(format!("获取app状态失败 {}", e)), None))?;
+    let state = &mut *app_state;
+
+    let mut frame = Mat::default();
+    let camera = state
+        .camera
+        .as_mut()
+        .ok_or_else(|| CustomResult::error(Some(String::from("摄像头未打开")), None))?;
+    camera
+        .inner
+        .read(&mut frame)
+        .map_err(|e| CustomResult::error(Some(format!("读取摄像头画面失败: {:?}", e)), None))?;
+    if frame.empty() {
+        return Err(CustomResult::error(Some(String::from("摄像头画面为空")), None));
+    }
+    if matches!(session.verifier.status(), ChallengeStatus::Passed) {
+        return passed_result(session);
+    }
+    // 红外摄像头的单通道、16 位画面先转成 BGR
+    let original = normalize_frame(&frame, None)
+        .map_err(|e| CustomResult::error(Some(e), None))?
+        .bgr;
+    let frame = preprocess_frame(&original, &session.preprocess).map_err(|e| CustomResult::error(Some(e), None))?;
+
+    let detector = state
+        .detector
+        .as_mut()
+        .ok_or_else(|| CustomResult::error(Some(String::from("模型未加载")), None))?;
+    let faces = session
+        .finder
+        .detect(&mut detector.inner, &frame, face_detection_threshold)
+        .map_err(|e| CustomResult::error(Some(e), None))?;
+
+    let mut all_values = Vec::new();
+    for row in 0..faces.rows() {
+        all_values.push(face_values(&faces, row).map_err(|e| CustomResult::error(Some(e), None))?);
+    }
+    let rects: Vec<[f32; 4]> = all_values.iter().map(|v| [v[0], v[1], v[2], v[3]]).collect();
+    let ids = session.tracker.update(&rects);
+
+    // 第一次检测到人脸时锁定最大的那张
+    if session.track_id.is_none() {
+        session.track_id = all_values
+            .iter()
+            .zip(ids.iter())
+            .max_by(|(a, _), (b, _)| (a[2] * a[3]).partial_cmp(&(b[2] * b[3])).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(_, id)| *id);
+    }
+
+    let tracked = session
+        .track_id
+        .and_then(|track_id| ids.iter().position(|id| *id == track_id));
+    match tracked {
+        Some(index) => {
+            let values = &all_values[index];
+            let mut gray = Mat::default();
+            imgproc::cvt_color(&frame, &mut gray, imgproc::COLOR_BGR2GRAY, 0)
+                .map_err(|e| CustomResult::error(Some(format!("转换灰度图失败: {:?}", e)), None))?;
+            let openness = eye_openness(&gray, values).ok();
+            let timestamp_ms = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0);
+            let landmarks = LandmarkFrame::from_face_values(values, timestamp_ms, openness);
+            let yaw = landmarks.yaw().abs();
+            if session.verified_frame.as_ref().map_or(true, |(best, _)| yaw < *best) {
+                session.verified_frame = Some((yaw, original));
+            }
+            session.verifier.push(landmarks);
+        }
+        None => {
+            // 还没锁定人脸时只是等待，锁定后连续多帧丢失才算失败
+            if let Some(track_id) = session.track_id {
+                if !session.tracker.active_ids().contains(&track_id) {
+                    session.verifier.lose_face();
+                }
+            }
+        }
+    }
+
+    let status = session.verifier.status().clone();
+    if matches!(status, ChallengeStatus::Passed | ChallengeStatus::Failed { .. }) {
+        info!("动作活体检测结束：{:?}", status);
+    }
+    if status == ChallengeStatus::Passed {
+        return passed_result(session);
+    }
+    Ok(CustomResult::success(None, Some(json!(status))))
+}
+
+// 通过后的结果，带上录入用的画面
+fn passed_result(session: &ActiveLivenessSession) -> Result<CustomResult, CustomResult> {
+    let (_, frame) = session
+        .verified_frame
+        .as_ref()
+        .ok_or_else(|| CustomResult::error(Some(String::from("没有保存到完成动作时的画面")), None))?;
+    let image = encode_base64_image(frame).map_err(|e| CustomResult::error(Some(e), None))?;
+    Ok(CustomResult::success(
+        None,
+        Some(json!({
+            "state": "passed",
+            "raw_base64": format!("data:image/jpeg;base64,{}", image),
+        })),
+    ))
+}
+
+// 结束动作活体检测
+#[tauri::command]
+pub fn stop_active_liveness() -> Result<CustomResult, CustomResult> {
+    let mut session = ACTIVE_LIVENESS
+        .lock()
+        .map_err(|e| CustomResult::error(Some(format!("获取动作活体状态失败 {}", e)), None))?;
+    *session = None;
+    Ok(CustomResult::success(None, None))
+}
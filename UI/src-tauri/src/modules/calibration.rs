@@ -0,0 +1,142 @@
+use crate::{
+    utils::{
+        attempt_log::AttemptLog,
+        custom_result::CustomResult,
+        face_pipeline::{image_feature, match_score},
+        metrics::{calibrate, labeled_sample, ScoreSample},
+        template_gallery::validate_face_token,
+    },
+    APP_STATE, ROOT_DIR,
+};
+use serde_json::json;
+use tauri_plugin_log::log::{info, warn};
+
+// 阈值校准
+// face_token: 作为参考的面容，本人/他人图片都与它的 .faceimg 比对
+// genuine_imgs / impostor_imgs: 本人图片和他人图片的路径
+// face_id / attempts: 要校准的面容和登录日志（unlock_log）中已标注尝试者的 json_data，见 labeled_samples
+// target_far: 目标误识率，例如 0.001
+#[tauri::command]
+pub fn calibrate_threshold(
+    face_token: Option<String>,
+    genuine_imgs: Vec<String>,
+    impostor_imgs: Vec<String>,
+    face_id: Option<i64>,
+    attempts: Vec<String>,
+    target_far: f64,
+    face_detection_threshold: f32,
+) -> Result<CustomResult, CustomResult> {
+    if !(0.0..=1.0).contains(&target_far) {
+        return Err(CustomResult::error(
+            Some(format!("目标误识率必须在 0 ~ 1 之间，当前为 {}", target_far)),
+            None,
+        ));
+    }
+
+    let mut samples: Vec<ScoreSample> = Vec::new();
+    // 无法参与计算的图片、尝试记录及原因
+    let mut skipped: Vec<serde_json::Value> = Vec::new();
+    if !attempts.is_empty() {
+        let face_id = face_id.ok_or_else(|| {
+            CustomResult::error(Some(String::from("使用登录日志校准时必须指定面容")), None)
+        })?;
+        samples.extend(labeled_samples(face_id, &attempts, &mut skipped));
+    }
+
+    if !genuine_imgs.is_empty() || !impostor_imgs.is_empty() {
+        let face_token = face_token.ok_or_else(|| {
+            CustomResult::error(Some(String::from("使用图片校准时必须指定参考面容")), None)
+        })?;
+        validate_face_token(&face_token).map_err(|e| CustomResult::error(Some(e), None))?;
+
+        let mut app_state = APP_STATE
+            .lock()
+            .map_err(|e| CustomResult::error(Some(format!("获取app状态失败 {}", e)), None))?;
+        let state = &mut *app_state;
+        let (detector, recognizer) = match (state.detector.as_mut(), state.recognizer.as_mut()) {
+            (Some(detector), Some(recognizer)) => (&mut detector.inner, &mut recognizer.inner),
+            _ => {
+                return Err(CustomResult::error(
+                    Some(String::from("模型未加载，请先调用 load_opencv_model")),
+                    None,
+                ))
+            }
+        };
+
+        let reference_path = ROOT_DIR
+            .join("faces")
+            .join(format!("{}.faceimg", face_token));
+        let reference = image_feature(
+            detector,
+            recognizer,
+            reference_path.to_str().unwrap_or(""),
+            face_detection_threshold,
+        )
+        .map_err(|e| CustomResult::error(Some(e), None))?
+        .ok_or_else(|| {
+            CustomResult::error(Some(String::from("参考面容图片中未检测到人脸")), None)
+        })?;
+
+        let labeled = genuine_imgs
+            .iter()
+            .map(|p| (p, true))
+            .chain(impostor_imgs.iter().map(|p| (p, false)));
+        for (path, genuine) in labeled {
+            let feature = match image_feature(detector, recognizer, path, face_detection_threshold) {
+                Ok(Some(feature)) => feature,
+                Ok(None) => {
+                    skipped.push(json!({"path": path, "reason": "未检测到人脸"}));
+                    continue;
+                }
+                Err(e) => {
+                    skipped.push(json!({"path": path, "reason": e}));
+                    continue;
+                }
+            };
+
+            match match_score(recognizer, &reference, &feature) {
+                Ok(score) => samples.push(ScoreSample { score, genuine }),
+                Err(e) => skipped.push(json!({"path": path, "reason": e})),
+            }
+        }
+    }
+
+    if !skipped.is_empty() {
+        warn!("阈值校准时跳过了 {} 个图片或尝试记录", skipped.len());
+    }
+
+    let report = calibrate(&samples, target_far);
+    if report.recommended_threshold.is_none() {
+        return Err(CustomResult::error(
+            Some(String::from("本人和他人样本都至少需要一个，才能计算推荐阈值")),
+            Some(json!({"report": report, "skipped": skipped})),
+        ));
+    }
+
+    info!(
+        "阈值校准完成，本人样本 {} 个，他人样本 {} 个，EER {:.4}，推荐阈值 {:?}",
+        report.genuine_count, report.impostor_count, report.eer, report.recommended_threshold
+    );
+
+    Ok(CustomResult::success(
+        None,
+        Some(json!({"report": report, "skipped": skipped})),
+    ))
+}
+
+// 登录日志的 json_data 中有与每个面容的相似度，用户标注的尝试者决定是本人还是他人样本
+// 没有标注、没有与该面容比对过的尝试跳过
+fn labeled_samples(face_id: i64, attempts: &[String], skipped: &mut Vec<serde_json::Value>) -> Vec<ScoreSample> {
+    let mut samples = Vec::new();
+    for (index, json_data) in attempts.iter().enumerate() {
+        let attempt = AttemptLog::from_json_data(Some(json_data));
+        let sample = attempt
+            .label
+            .and_then(|label| labeled_sample(face_id, label.face_id, &attempt.scores));
+        match sample {
+            Some(sample) => samples.push(sample),
+            None => skipped.push(json!({"attempt": index, "reason": "没有标注或没有与该面容比对的分数"})),
+        }
+    }
+    samples
+}
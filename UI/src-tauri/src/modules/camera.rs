@@ -0,0 +1,147 @@
+use std::thread;
+
+use crate::{
+    utils::{
+        camera_identity::{enumerate_video_devices, resolve_camera, CameraDescriptor, CameraFallback},
+        capture_profile::{apply_capture_profile, read_capture, CaptureProfile},
+        custom_result::CustomResult,
+        face_pipeline::{decode_base64_image, encode_base64_image},
+        frame_normalize::{normalize_frame, Modality},
+        preprocess::{preprocess_frame, PreprocessConfig},
+    },
+    AppState, APP_STATE,
+};
+use opencv::{core::Mat, prelude::*, videoio::VideoCapture};
+use serde_json::json;
+
+// 按录制间隔播放回放文件时先等到下一帧的时间，等待时不持有 APP_STATE
+pub fn wait_for_next_frame() {
+    let wait = APP_STATE
+        .lock()
+        .ok()
+        .and_then(|mut app_state| app_state.camera.as_mut().and_then(|camera| camera.inner.next_frame_in()));
+    if let Some(wait) = wait {
+        thread::sleep(wait);
+    }
+}
+
+// 读取一帧，判断当前摄像头的画面格式和成像方式，录入时随面容一起保存
+// modality: 设置中指定的成像方式（rgb / ir），auto 或不传时自动判断
+#[tauri::command]
+pub fn get_camera_modality(modality: Option<String>) -> Result<CustomResult, CustomResult> {
+    let mut app_state = APP_STATE
+        .lock()
+        .map_err(|e| CustomResult::error(Some(format!("获取app状态失败 {}", e)), None))?;
+    let camera = app_state
+        .camera
+        .as_mut()
+        .ok_or_else(|| CustomResult::error(Some(String::from("摄像头未打开")), None))?;
+
+    let mut frame = Mat::default();
+    camera
+        .inner
+        .read(&mut frame)
+        .map_err(|e| CustomResult::error(Some(format!("读取摄像头画面失败: {:?}", e)), None))?;
+
+    let normalized = normalize_frame(&frame, Modality::from_options(modality.as_deref()))
+        .map_err(|e| CustomResult::error(Some(e), None))?;
+    Ok(CustomResult::success(
+        None,
+        Some(json!({"format": normalized.format, "modality": normalized.modality})),
+    ))
+}
+
+// 预览画面预处理的效果，设置页面调整参数时使用
+// image_base64: 不带 data:image 前缀的图片，不传时从已打开的摄像头读取一帧
+#[tauri::command]
+pub fn preview_preprocess(
+    image_base64: Option<String>,
+    config: PreprocessConfig,
+) -> Result<CustomResult, CustomResult> {
+    let img = match image_base64 {
+        Some(image_base64) => decode_base64_image(&image_base64).map_err(|e| CustomResult::error(Some(e), None))?,
+        None => {
+            let mut app_state = APP_STATE
+                .lock()
+                .map_err(|e| CustomResult::error(Some(format!("获取app状态失败 {}", e)), None))?;
+            let camera = app_state
+                .camera
+                .as_mut()
+                .ok_or_else(|| CustomResult::error(Some(String::from("摄像头未打开")), None))?;
+            let mut frame = Mat::default();
+            camera
+                .inner
+                .read(&mut frame)
+                .map_err(|e| CustomResult::error(Some(format!("读取摄像头画面失败: {:?}", e)), None))?;
+            normalize_frame(&frame, None)
+                .map_err(|e| CustomResult::error(Some(e), None))?
+                .bgr
+        }
+    };
+
+    // 预览时忽略开关，直接看参数的效果
+    let processed = preprocess_frame(&img, &PreprocessConfig { enabled: true, ..config })
+        .map_err(|e| CustomResult::error(Some(e), None))?;
+    let original = encode_base64_image(&img).map_err(|e| CustomResult::error(Some(e), None))?;
+    let processed = encode_base64_image(&processed).map_err(|e| CustomResult::error(Some(e), None))?;
+    Ok(CustomResult::success(
+        None,
+        Some(json!({
+            "original": format!("data:image/jpeg;base64,{}", original),
+            "processed": format!("data:image/jpeg;base64,{}", processed),
+        })),
+    ))
+}
+
+// 列出当前连接的视频设备，包含设备路径，设置页面保存摄像头时使用
+#[tauri::command]
+pub fn get_camera_devices() -> Result<CustomResult, CustomResult> {
+    let devices = enumerate_video_devices()
+        .map_err(|e| CustomResult::error(Some(format!("枚举摄像头失败: {:?}", e)), None))?;
+    Ok(CustomResult::success(None, Some(json!(devices))))
+}
+
+// 把保存的摄像头换算成当前序号，设置页面用来显示当前选中的摄像头
+#[tauri::command]
+pub fn resolve_camera_device(
+    device: CameraDescriptor,
+    fallback: Option<CameraFallback>,
+) -> Result<CustomResult, CustomResult> {
+    let devices = enumerate_video_devices()
+        .map_err(|e| CustomResult::error(Some(format!("枚举摄像头失败: {:?}", e)), None))?;
+    let resolved = resolve_camera(&device, &devices, fallback.unwrap_or_default())
+        .map_err(|e| CustomResult::error(Some(e), Some(json!({"devices": devices}))))?;
+    Ok(CustomResult::success(None, Some(json!(resolved))))
+}
+
+// 读取已打开摄像头当前的采集参数
+#[tauri::command]
+pub fn get_camera_capture() -> Result<CustomResult, CustomResult> {
+    let mut app_state = APP_STATE
+        .lock()
+        .map_err(|e| CustomResult::error(Some(format!("获取app状态失败 {}", e)), None))?;
+    let capture = opened_capture(&mut app_state)?;
+    Ok(CustomResult::success(None, Some(json!(read_capture(capture)))))
+}
+
+// 修改已打开摄像头的采集参数，返回驱动实际接受的值
+// 设置页面确认后把 profile 保存到 cameraCapture，下次打开摄像头时由 open_camera 应用
+#[tauri::command]
+pub fn set_camera_capture(profile: CaptureProfile) -> Result<CustomResult, CustomResult> {
+    let mut app_state = APP_STATE
+        .lock()
+        .map_err(|e| CustomResult::error(Some(format!("获取app状态失败 {}", e)), None))?;
+    let capture = opened_capture(&mut app_state)?;
+    let readback = apply_capture_profile(capture, &profile).map_err(|e| CustomResult::error(Some(e), None))?;
+    Ok(CustomResult::success(None, Some(json!(readback))))
+}
+
+fn opened_capture(app_state: &mut AppState) -> Result<&mut VideoCapture, CustomResult> {
+    app_state
+        .camera
+        .as_mut()
+        .ok_or_else(|| CustomResult::error(Some(String::from("摄像头未打开")), None))?
+        .inner
+        .capture_mut()
+        .ok_or_else(|| CustomResult::error(Some(String::from("当前画面来源不是摄像头，不能设置采集参数")), None))
+}
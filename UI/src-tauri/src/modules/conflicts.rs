@@ -0,0 +1,135 @@
+use crate::{
+    utils::{
+        custom_result::CustomResult,
+        face_conflicts::{
+            consistency_report, find_conflicts, load_face_features, FaceFeatures, FaceRef,
+            DEFAULT_CONFLICT_THRESHOLD,
+        },
+        face_pipeline::{decode_base64_image, detect_faces, extract_feature, feature_to_vec, largest_face},
+        frame_normalize::Modality,
+    },
+    AppState, OpenCVResource, APP_STATE, ROOT_DIR,
+};
+use opencv::{
+    core::Ptr,
+    objdetect::{FaceDetectorYN, FaceRecognizerSF},
+};
+use serde_json::json;
+use tauri_plugin_log::log::{info, warn};
+
+// 保存面容前检查新图片是否与其他账户的面容过于相似
+// image_base64: 不带 data:image 前缀的图片
+// user_name: 将要关联的账户，同一账户下的面容不算冲突
+// faces: 数据库中已有的面容，编辑时不包含正在编辑的面容
+#[tauri::command]
+pub fn check_enrollment_conflict(
+    image_base64: String,
+    face_detection_threshold: f32,
+    user_name: String,
+    modality: Option<Modality>,
+    faces: Vec<FaceRef>,
+    threshold: Option<f32>,
+) -> Result<CustomResult, CustomResult> {
+    let img = decode_base64_image(&image_base64).map_err(|e| CustomResult::error(Some(e), None))?;
+
+    let mut app_state = APP_STATE
+        .lock()
+        .map_err(|e| CustomResult::error(Some(format!("获取app状态失败 {}", e)), None))?;
+    let (existing, skipped) = load_all_features(&mut app_state, &faces)?;
+    let (detector, recognizer) = models(&mut app_state)?;
+
+    let faces_mat = detect_faces(&mut detector.inner, &img, face_detection_threshold)
+        .map_err(|e| CustomResult::error(Some(e), None))?;
+    let face = largest_face(&faces_mat)
+        .map_err(|e| CustomResult::error(Some(e), None))?
+        .ok_or_else(|| CustomResult::error(Some(String::from("未检测到人脸")), None))?;
+    let feature = extract_feature(&mut recognizer.inner, &img, &face)
+        .and_then(|f| feature_to_vec(&f))
+        .map_err(|e| CustomResult::error(Some(e), None))?;
+
+    let threshold = threshold.unwrap_or(DEFAULT_CONFLICT_THRESHOLD);
+    let conflicts = find_conflicts(&feature, modality.unwrap_or_default(), &user_name, &existing, threshold);
+    if !conflicts.is_empty() {
+        warn!(
+            "新录入的面容与 {} 个其他账户的面容相似，最高 {:.3}",
+            conflicts.len(),
+            conflicts[0].score
+        );
+    }
+    Ok(CustomResult::success(
+        None,
+        Some(json!({"threshold": threshold, "conflicts": conflicts, "skipped": skipped})),
+    ))
+}
+
+// 检查已有面容中属于不同账户但过于相似的面容
+#[tauri::command]
+pub fn get_consistency_report(faces: Vec<FaceRef>, threshold: Option<f32>) -> Result<CustomResult, CustomResult> {
+    let mut app_state = APP_STATE
+        .lock()
+        .map_err(|e| CustomResult::error(Some(format!("获取app状态失败 {}", e)), None))?;
+    let (existing, skipped) = load_all_features(&mut app_state, &faces)?;
+
+    let threshold = threshold.unwrap_or(DEFAULT_CONFLICT_THRESHOLD);
+    let pairs = consistency_report(&existing, threshold);
+    info!(
+        "一致性检查：{} 个面容，{} 对可疑，{} 个无法读取",
+        faces.len(),
+        pairs.len(),
+        skipped.len()
+    );
+    Ok(CustomResult::success(
+        None,
+        Some(json!({"threshold": threshold, "checked": existing.len(), "pairs": pairs, "skipped": skipped})),
+    ))
+}
+
+// 读取全部面容的特征，读取失败的面容跳过并返回原因
+fn load_all_features(
+    app_state: &mut AppState,
+    faces: &[FaceRef],
+) -> Result<(Vec<FaceFeatures>, Vec<serde_json::Value>), CustomResult> {
+    let AppState {
+        detector,
+        recognizer,
+        feature_cache,
+        ..
+    } = app_state;
+    let (detector, recognizer) = match (detector.as_mut(), recognizer.as_mut()) {
+        (Some(detector), Some(recognizer)) => (detector, recognizer),
+        _ => return Err(models_not_loaded()),
+    };
+    let faces_dir = ROOT_DIR.join("faces");
+    let mut loaded = Vec::new();
+    let mut skipped = Vec::new();
+    for face in faces {
+        match load_face_features(
+            &mut detector.inner,
+            &mut recognizer.inner,
+            feature_cache,
+            &faces_dir,
+            face,
+        ) {
+            Ok(features) if !features.features.is_empty() => loaded.push(features),
+            Ok(_) => skipped.push(json!({"faceId": face.id, "reason": "面容图片中未检测到人脸"})),
+            Err(e) => skipped.push(json!({"faceId": face.id, "reason": e})),
+        }
+    }
+    Ok((loaded, skipped))
+}
+
+type Models<'a> = (
+    &'a mut OpenCVResource<Ptr<FaceDetectorYN>>,
+    &'a mut OpenCVResource<Ptr<FaceRecognizerSF>>,
+);
+
+fn models(app_state: &mut AppState) -> Result<Models<'_>, CustomResult> {
+    match (app_state.detector.as_mut(), app_state.recognizer.as_mut()) {
+        (Some(detector), Some(recognizer)) => Ok((detector, recognizer)),
+        _ => Err(models_not_loaded()),
+    }
+}
+
+fn models_not_loaded() -> CustomResult {
+    CustomResult::error(Some(String::from("模型未加载，请先调用 load_opencv_model")), None)
+}
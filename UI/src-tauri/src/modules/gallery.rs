@@ -0,0 +1,75 @@
+use crate::{
+    utils::{
+        custom_result::CustomResult,
+        template_gallery::{
+            append_audit, now_secs, validate_face_token, AuditEntry, TemplateGallery, TemplateSource,
+        },
+    },
+    ROOT_DIR,
+};
+use serde_json::json;
+use tauri_plugin_log::log::info;
+
+// 查看面容的模板库，不返回特征向量本身
+#[tauri::command]
+pub fn get_face_gallery(face_token: String) -> Result<CustomResult, CustomResult> {
+    validate_face_token(&face_token).map_err(|e| CustomResult::error(Some(e), None))?;
+    let faces_dir = ROOT_DIR.join("faces");
+    let gallery = TemplateGallery::load(&faces_dir, &face_token)
+        .map_err(|e| CustomResult::error(Some(e), None))?;
+
+    let templates: Vec<serde_json::Value> = gallery
+        .templates
+        .iter()
+        .map(|t| {
+            json!({
+                "id": t.id,
+                "source": t.source,
+                "createdAt": t.created_at,
+                "matchCount": t.match_count,
+                "lastMatchedAt": t.last_matched_at,
+                "quality": t.quality,
+            })
+        })
+        .collect();
+    Ok(CustomResult::success(
+        None,
+        Some(json!({"learnedCount": gallery.learned_count(), "templates": templates})),
+    ))
+}
+
+// 清除面容自适应学习到的模板，学偏时用来恢复到录入时的状态
+#[tauri::command]
+pub fn reset_learned_templates(face_token: String) -> Result<CustomResult, CustomResult> {
+    validate_face_token(&face_token).map_err(|e| CustomResult::error(Some(e), None))?;
+    let faces_dir = ROOT_DIR.join("faces");
+    let mut gallery = TemplateGallery::load(&faces_dir, &face_token)
+        .map_err(|e| CustomResult::error(Some(e), None))?;
+
+    let before = gallery.templates.len();
+    gallery.templates.retain(|t| t.source != TemplateSource::Learned);
+    let removed = before - gallery.templates.len();
+    if removed == 0 {
+        return Ok(CustomResult::success(None, Some(json!({"removed": 0}))));
+    }
+
+    gallery
+        .save(&faces_dir, &face_token)
+        .map_err(|e| CustomResult::error(Some(e), None))?;
+    append_audit(
+        &faces_dir,
+        &AuditEntry {
+            time: now_secs(),
+            face_token: face_token.clone(),
+            action: String::from("reset"),
+            template_id: None,
+            score: None,
+            threshold: None,
+            liveness_passed: None,
+        },
+    )
+    .map_err(|e| CustomResult::error(Some(e), None))?;
+
+    info!("已清除面容 {} 的 {} 个学习模板", face_token, removed);
+    Ok(CustomResult::success(None, Some(json!({"removed": removed}))))
+}
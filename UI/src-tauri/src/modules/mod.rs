@@ -1,3 +1,12 @@
+pub mod active_liveness;
+pub mod calibration;
+pub mod camera;
+pub mod conflicts;
 pub mod faces;
+pub mod gallery;
 pub mod init;
 pub mod options;
+pub mod preview;
+pub mod quality;
+pub mod replay;
+pub mod video_enroll;
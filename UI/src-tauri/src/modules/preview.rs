@@ -0,0 +1,391 @@
+// 实时预览：后台线程读取摄像头，画面编码为 JPEG 后通过 Channel 以二进制推送给前端
+// 人脸框和关键点作为单独的元数据推送，不再画在画面上，也不用 base64 经过 invoke 的 JSON
+// 窗口隐藏或最小化（lib.rs 的 on_window_event）、锁屏、摄像头关闭时自动停止，停止时推送 stopped 消息
+// 一致性验证时同一个线程顺带和参考图片比对，结果随元数据推送，不再由前端轮询 verify_face
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    modules::camera::wait_for_next_frame,
+    utils::{
+        custom_result::CustomResult,
+        face_finder::{FaceFinder, FinderConfig},
+        face_pipeline::{
+            decode_base64_image, detect_faces, extract_feature, face_values, largest_face, match_score,
+            FACE_SCORE_INDEX,
+        },
+        frame_normalize::normalize_frame,
+        liveness::{face_crop, liveness_score},
+        preprocess::{preprocess_frame, PreprocessConfig},
+    },
+    AppState, APP_STATE,
+};
+use opencv::{
+    core::{Mat, Size, Vector},
+    imgcodecs, imgproc,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use tauri::ipc::{Channel, InvokeResponseBody};
+use tauri_plugin_log::log::info;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PreviewConfig {
+    // 最高帧率，检测和编码的耗时也算在内
+    pub max_fps: f64,
+    // 推送画面的最大宽度，超过时等比缩小，人脸框也按缩小后的坐标
+    pub max_width: i32,
+    // JPEG 质量 0~100
+    pub quality: i32,
+    // 人脸检测置信度，不传时只推送画面
+    pub face_detection_threshold: Option<f32>,
+}
+
+impl Default for PreviewConfig {
+    fn default() -> Self {
+        Self {
+            max_fps: 15.0,
+            max_width: 640,
+            quality: 80,
+            face_detection_threshold: None,
+        }
+    }
+}
+
+// 一致性验证：和参考图片中的人脸比对
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewVerify {
+    // 不带 data:image 前缀的参考图片
+    pub reference_base64: String,
+    // 活体阈值，不传时不做活体检测
+    pub liveness_threshold: Option<f32>,
+}
+
+// 一帧的验证结果，score 为余弦相似度，画面中没有人脸时为 None
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewVerification {
+    pub score: Option<f64>,
+    pub liveness: Option<f32>,
+    // 未通过时的原因
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewFace {
+    // x, y, 宽, 高
+    pub rect: [f32; 4],
+    // 右眼、左眼、鼻尖、右嘴角、左嘴角
+    pub landmarks: [[f32; 2]; 5],
+    pub score: f32,
+}
+
+// 元数据通道的消息
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum PreviewMessage {
+    // 紧跟在同一帧的画面之后发送，seq 从 0 开始，与收到的画面一一对应
+    Frame {
+        seq: u64,
+        width: i32,
+        height: i32,
+        faces: Vec<PreviewFace>,
+        // 一致性验证的结果，没有传 verify 时为 None
+        verification: Option<PreviewVerification>,
+        // 读取、检测、编码的耗时
+        ms: u64,
+    },
+    Stopped {
+        reason: String,
+    },
+}
+
+struct PreviewStream {
+    id: u64,
+    stop: Arc<AtomicBool>,
+    // 停止的原因，由 stop_preview_stream 设置
+    reason: Arc<Mutex<Option<String>>>,
+}
+
+lazy_static::lazy_static! {
+    static ref PREVIEW: Mutex<Option<PreviewStream>> = Mutex::new(None);
+}
+
+// 开始推送预览，需要先 open_camera；已有预览时先停止旧的
+// frames: 接收 JPEG 画面的通道，前端收到的是 ArrayBuffer
+// meta: 接收 PreviewMessage 的通道
+// preprocess: 检测人脸前的画面预处理，推送的画面不做预处理
+// verify: 一致性验证的参考图片，传入时每帧都和参考图片比对
+// options: 设置 {设置名称: 设置值}，从 detectWorkingSize、detectRoiTracking 读取检测的工作尺寸和区域跟踪，与解锁时相同
+#[tauri::command]
+pub fn start_preview(
+    frames: Channel,
+    meta: Channel<PreviewMessage>,
+    config: Option<PreviewConfig>,
+    preprocess: Option<PreprocessConfig>,
+    verify: Option<PreviewVerify>,
+    options: Option<HashMap<String, String>>,
+) -> Result<CustomResult, CustomResult> {
+    stop_preview_stream("开始了新的预览");
+    let options = options.unwrap_or_default();
+    let finder_config = FinderConfig::from_options(|key| options.get(key).cloned());
+    let mut config = config.unwrap_or_default();
+    // 验证需要检测人脸，没有传检测置信度时使用默认的 0.9
+    let reference = match &verify {
+        Some(verify) => {
+            let threshold = *config.face_detection_threshold.get_or_insert(0.9);
+            Some(reference_feature(&verify.reference_base64, threshold).map_err(|e| CustomResult::error(Some(e), None))?)
+        }
+        None => None,
+    };
+    let verifier = reference.map(|feature| Verifier {
+        reference: feature,
+        liveness_threshold: verify.and_then(|v| v.liveness_threshold),
+    });
+    let stream = PreviewStream {
+        id: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0),
+        stop: Arc::new(AtomicBool::new(false)),
+        reason: Arc::new(Mutex::new(None)),
+    };
+    let id = stream.id;
+    let stop = stream.stop.clone();
+    let reason = stream.reason.clone();
+    *PREVIEW
+        .lock()
+        .map_err(|e| CustomResult::error(Some(format!("获取预览状态失败 {}", e)), None))? = Some(stream);
+
+    info!("开始预览，最高 {} 帧/秒，最大宽度 {}", config.max_fps, config.max_width);
+    thread::spawn(move || {
+        let start = Instant::now();
+        let (sent, stopped_by) = run_preview(
+            &stop,
+            &frames,
+            &meta,
+            &config,
+            finder_config,
+            &preprocess.unwrap_or_default(),
+            verifier.as_ref(),
+        );
+        let stopped_by = reason.lock().ok().and_then(|r| r.clone()).unwrap_or(stopped_by);
+        info!(
+            "预览停止：{}，推送 {} 帧，持续 {} 秒",
+            stopped_by,
+            sent,
+            start.elapsed().as_secs()
+        );
+        let _ = meta.send(PreviewMessage::Stopped { reason: stopped_by });
+        if let Ok(mut preview) = PREVIEW.lock() {
+            if preview.as_ref().map_or(false, |p| p.id == id) {
+                *preview = None;
+            }
+        }
+    });
+    Ok(CustomResult::success(None, None))
+}
+
+// 停止预览，前端离开页面或关闭摄像头前调用
+#[tauri::command]
+pub fn stop_preview() -> Result<CustomResult, CustomResult> {
+    stop_preview_stream("前端停止预览");
+    Ok(CustomResult::success(None, None))
+}
+
+// 通知预览线程停止，线程在当前帧结束后退出；锁屏时由 wnd_proc_subclass 调用
+pub fn stop_preview_stream(reason: &str) {
+    if let Ok(mut preview) = PREVIEW.lock() {
+        if let Some(stream) = preview.take() {
+            if let Ok(mut r) = stream.reason.lock() {
+                *r = Some(reason.to_string());
+            }
+            stream.stop.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+// 参考图片中最大人脸的特征
+fn reference_feature(reference_base64: &str, face_detection_threshold: f32) -> Result<Mat, String> {
+    let img = decode_base64_image(reference_base64)?;
+    let mut app_state = APP_STATE.lock().map_err(|e| format!("获取app状态失败 {}", e))?;
+    let state = &mut *app_state;
+    let detector = state.detector.as_mut().ok_or_else(|| String::from("模型未加载"))?;
+    let recognizer = state.recognizer.as_mut().ok_or_else(|| String::from("模型未加载"))?;
+    let faces = detect_faces(&mut detector.inner, &img, face_detection_threshold)?;
+    let face = largest_face(&faces)?.ok_or_else(|| String::from("参考图片中没有检测到人脸"))?;
+    extract_feature(&mut recognizer.inner, &img, &face)
+}
+
+struct Verifier {
+    reference: Mat,
+    liveness_threshold: Option<f32>,
+}
+
+// 画面中最大的人脸和参考特征比对，开启活体检测时活体未通过不给出分数
+fn verify_frame(
+    state: &mut AppState,
+    verifier: &Verifier,
+    img: &Mat,
+    detected: &Mat,
+) -> Result<PreviewVerification, String> {
+    let face = match largest_face(detected)? {
+        Some(face) => face,
+        None => {
+            return Ok(PreviewVerification {
+                score: None,
+                liveness: None,
+                message: Some(String::from("未检测到人脸")),
+            })
+        }
+    };
+    let mut liveness = None;
+    if let Some(threshold) = verifier.liveness_threshold {
+        let net = state.liveness.as_mut().ok_or_else(|| String::from("活体检测模型未加载"))?;
+        let values = face_values(&face, 0)?;
+        let score = liveness_score(&mut net.inner, &face_crop(img, &values)?)?;
+        liveness = Some(score);
+        if score < threshold {
+            return Ok(PreviewVerification {
+                score: None,
+                liveness,
+                message: Some(format!("活体检测未通过（{:.2}）", score)),
+            });
+        }
+    }
+    let recognizer = state.recognizer.as_mut().ok_or_else(|| String::from("模型未加载"))?;
+    let feature = extract_feature(&mut recognizer.inner, img, &face)?;
+    Ok(PreviewVerification {
+        score: Some(match_score(&recognizer.inner, &verifier.reference, &feature)?),
+        liveness,
+        message: None,
+    })
+}
+
+// 循环读取并推送画面，返回推送的帧数和停止的原因
+fn run_preview(
+    stop: &AtomicBool,
+    frames: &Channel,
+    meta: &Channel<PreviewMessage>,
+    config: &PreviewConfig,
+    finder_config: FinderConfig,
+    preprocess: &PreprocessConfig,
+    verifier: Option<&Verifier>,
+) -> (u64, String) {
+    let interval = Duration::from_secs_f64(1.0 / config.max_fps.max(1.0));
+    let mut finder = FaceFinder::new(finder_config);
+    let mut seq = 0u64;
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            return (seq, String::from("已停止"));
+        }
+
+        wait_for_next_frame();
+        let start = Instant::now();
+        match preview_frame(&mut finder, config, preprocess, verifier) {
+            Ok(Some((jpeg, width, height, faces, verification))) => {
+                let message = PreviewMessage::Frame {
+                    seq,
+                    width,
+                    height,
+                    faces,
+                    verification,
+                    ms: start.elapsed().as_millis() as u64,
+                };
+                if frames.send(InvokeResponseBody::Raw(jpeg)).is_err() || meta.send(message).is_err() {
+                    return (seq, String::from("前端已关闭预览"));
+                }
+                seq += 1;
+            }
+            // 偶尔读到空帧时跳过这一帧
+            Ok(None) => {}
+            Err(e) => return (seq, e),
+        }
+
+        if let Some(rest) = interval.checked_sub(start.elapsed()) {
+            thread::sleep(rest);
+        }
+    }
+}
+
+// 一帧推送的内容：JPEG、宽、高、人脸、验证结果
+type PreviewFrame = (Vec<u8>, i32, i32, Vec<PreviewFace>, Option<PreviewVerification>);
+
+// 读取一帧，检测人脸后缩小并编码，摄像头已关闭时返回错误
+fn preview_frame(
+    finder: &mut FaceFinder,
+    config: &PreviewConfig,
+    preprocess: &PreprocessConfig,
+    verifier: Option<&Verifier>,
+) -> Result<Option<PreviewFrame>, String> {
+    let (frame, mut faces, verification) = {
+        let mut app_state = APP_STATE.lock().map_err(|e| format!("获取app状态失败 {}", e))?;
+        let state = &mut *app_state;
+        let camera = state.camera.as_mut().ok_or_else(|| String::from("摄像头已关闭"))?;
+        let mut frame = Mat::default();
+        let read = camera
+            .inner
+            .read(&mut frame)
+            .map_err(|e| format!("读取摄像头画面失败: {:?}", e))?;
+        if !read || frame.empty() {
+            return Ok(None);
+        }
+        // 红外摄像头的单通道、16 位画面先转成 BGR
+        let frame = normalize_frame(&frame, None)?.bgr;
+
+        let mut faces = Vec::new();
+        let mut verification = None;
+        if let (Some(threshold), Some(detector)) = (config.face_detection_threshold, state.detector.as_mut()) {
+            let img = preprocess_frame(&frame, preprocess)?;
+            let detected = finder.detect(&mut detector.inner, &img, threshold)?;
+            for row in 0..detected.rows() {
+                let v = face_values(&detected, row)?;
+                faces.push(PreviewFace {
+                    rect: [v[0], v[1], v[2], v[3]],
+                    landmarks: [[v[4], v[5]], [v[6], v[7]], [v[8], v[9]], [v[10], v[11]], [v[12], v[13]]],
+                    score: v[FACE_SCORE_INDEX],
+                });
+            }
+            if let Some(verifier) = verifier {
+                verification = Some(verify_frame(state, verifier, &img, &detected)?);
+            }
+        }
+        (frame, faces, verification)
+    };
+
+    let frame = if config.max_width > 0 && frame.cols() > config.max_width {
+        let scale = config.max_width as f32 / frame.cols() as f32;
+        let mut small = Mat::default();
+        imgproc::resize(
+            &frame,
+            &mut small,
+            Size::default(),
+            scale as f64,
+            scale as f64,
+            imgproc::INTER_AREA,
+        )
+        .map_err(|e| format!("缩小画面失败: {:?}", e))?;
+        for face in faces.iter_mut() {
+            face.rect.iter_mut().for_each(|v| *v *= scale);
+            face.landmarks.iter_mut().flatten().for_each(|v| *v *= scale);
+        }
+        small
+    } else {
+        frame
+    };
+
+    let mut buffer = Vector::<u8>::new();
+    let params = Vector::<i32>::from_slice(&[imgcodecs::IMWRITE_JPEG_QUALITY, config.quality.clamp(1, 100)]);
+    imgcodecs::imencode(".jpg", &frame, &mut buffer, &params).map_err(|e| format!("画面编码失败: {:?}", e))?;
+    Ok(Some((buffer.to_vec(), frame.cols(), frame.rows(), faces, verification)))
+}
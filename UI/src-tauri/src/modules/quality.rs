@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+
+use crate::{
+    utils::{
+        custom_result::CustomResult,
+        face_pipeline::{decode_base64_image, detect_faces, face_values, largest_face},
+        face_quality::{assess_face_quality, QualityConfig},
+    },
+    APP_STATE,
+};
+use serde_json::json;
+
+// 评估人脸图片的质量
+// image_base64: 不带 data:image 前缀的图片
+// options: 设置 {设置名称: 设置值}，从 quality* 设置读取门槛，与解锁时相同
+// quality_config: 单独指定的门槛，优先于 options，只传部分字段时其余使用默认值
+#[tauri::command]
+pub fn check_face_quality(
+    image_base64: String,
+    face_detection_threshold: f32,
+    options: Option<HashMap<String, String>>,
+    quality_config: Option<QualityConfig>,
+) -> Result<CustomResult, CustomResult> {
+    let options = options.unwrap_or_default();
+    let quality_config =
+        quality_config.unwrap_or_else(|| QualityConfig::from_options(|key| options.get(key).cloned()));
+    let img = decode_base64_image(&image_base64).map_err(|e| CustomResult::error(Some(e), None))?;
+
+    let mut app_state = APP_STATE
+        .lock()
+        .map_err(|e| CustomResult::error(Some(format!("获取app状态失败 {}", e)), None))?;
+    let detector = app_state.detector.as_mut().ok_or_else(|| {
+        CustomResult::error(Some(String::from("模型未加载，请先调用 load_opencv_model")), None)
+    })?;
+
+    let faces = detect_faces(&mut detector.inner, &img, face_detection_threshold)
+        .map_err(|e| CustomResult::error(Some(e), None))?;
+    let face = largest_face(&faces)
+        .map_err(|e| CustomResult::error(Some(e), None))?
+        .ok_or_else(|| CustomResult::error(Some(String::from("未检测到人脸")), None))?;
+    let values = face_values(&face, 0).map_err(|e| CustomResult::error(Some(e), None))?;
+
+    let report = assess_face_quality(&img, &values, &quality_config)
+        .map_err(|e| CustomResult::error(Some(e), None))?;
+    Ok(CustomResult::success(None, Some(json!(report))))
+}
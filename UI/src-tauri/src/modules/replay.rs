@@ -0,0 +1,242 @@
+use std::{collections::HashMap, sync::MutexGuard, time::Instant};
+
+use crate::{
+    modules::camera::wait_for_next_frame,
+    utils::{
+        custom_result::CustomResult,
+        frame_normalize::{normalize_frame, Modality},
+        model_manifest::ModelManifest,
+        replay::{
+            list_replay_files, now_ms, prune_replays, replay_file_name, summarize_replay, ReplayHeader,
+            ReplayWriter,
+        },
+        template_gallery::TemplateGallery,
+        unlock_attempt::{load_primary_template, AttemptConfig, AttemptModels, EnrolledFace, UnlockAttempt},
+    },
+    AppState, APP_STATE, ROOT_DIR,
+};
+use opencv::{core::Mat, prelude::*};
+use serde::Deserialize;
+use serde_json::json;
+use tauri_plugin_log::log::{info, warn};
+
+// 回放文件保存在程序目录下的 replays
+pub const REPLAY_DIR: &str = "replays";
+// 没有设置 recordKeep 时保留的回放数量
+const DEFAULT_KEEP: usize = 20;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayFace {
+    pub id: i64,
+    pub face_token: String,
+    // 面容的比对阈值（0 ~ 100），与 json_data 中的 threshold 一致
+    pub threshold: f32,
+    // 录入时的成像方式和检测阈值，用于从面容图片提取主模板
+    pub modality: Option<Modality>,
+    pub face_detection_threshold: Option<f32>,
+}
+
+// 从已打开的摄像头录制一段回放，用与解锁相同的检测、比对、多帧活体投票流程处理每一帧
+// 只在界面中手动录制，解锁服务（本仓库中为占位代码）不会调用；结果中的是否解锁表示同样的画面在解锁时是否会通过
+// options: 设置 {设置名称: 设置值}，活体检测和投票的参数从这里读取，不传时不做活体检测
+// faces: 要比对的面容，不传时只记录检测和活体结果
+// keep: 最多保留的回放数量，超出时删除最旧的
+#[tauri::command]
+pub fn record_replay(
+    frames: u32,
+    face_detection_threshold: f32,
+    options: Option<HashMap<String, String>>,
+    faces: Option<Vec<ReplayFace>>,
+    keep: Option<usize>,
+) -> Result<CustomResult, CustomResult> {
+    let options = options.unwrap_or_default();
+    let config = AttemptConfig {
+        face_detection_threshold,
+        ..AttemptConfig::from_options(|key| options.get(key).cloned())
+    };
+    // 准备阶段和每一帧分别加锁，录制期间预览和租约续期不会被阻塞
+    let (enrolled, source) = {
+        let mut app_state = lock_state()?;
+        let state = &mut *app_state;
+        let source = state
+            .camera
+            .as_ref()
+            .ok_or_else(|| CustomResult::error(Some(String::from("摄像头未打开")), None))?
+            .inner
+            .describe();
+        let models = attempt_models(state)?;
+        if config.liveness.is_some() && models.liveness.is_none() {
+            return Err(CustomResult::error(Some(String::from("已开启活体检测，但活体模型未加载")), None));
+        }
+
+        // 主模板（.faceimg）和模板库都参与比对，与解锁时一致
+        let faces_dir = ROOT_DIR.join("faces");
+        let mut enrolled: Vec<EnrolledFace> = Vec::new();
+        for face in faces.unwrap_or_default() {
+            let primary = load_primary_template(
+                models.detector,
+                models.recognizer,
+                &faces_dir,
+                &face.face_token,
+                face.modality.unwrap_or_default(),
+                face.face_detection_threshold.unwrap_or(face_detection_threshold),
+            );
+            let gallery = TemplateGallery::load(&faces_dir, &face.face_token);
+            match (primary, gallery) {
+                (Ok(primary), Ok(gallery)) => enrolled.push(EnrolledFace {
+                    id: face.id,
+                    threshold: face.threshold / 100.0,
+                    face_token: face.face_token,
+                    primary,
+                    gallery,
+                }),
+                (Err(e), _) | (_, Err(e)) => warn!("录制回放时读取面容 {} 失败: {}", face.id, e),
+            }
+        }
+        (enrolled, source)
+    };
+
+    let header = ReplayHeader {
+        created_at: now_ms() / 1000,
+        recorder: format!("facewinunlock-tauri {}", env!("CARGO_PKG_VERSION")),
+        models: ModelManifest::load(&ROOT_DIR.join("resources"))
+            .map(|m| m.models.iter().map(|e| format!("{} {}", e.name, e.version)).collect())
+            .unwrap_or_default(),
+        source,
+        face_detection_threshold,
+        threshold: None,
+        liveness_threshold: config.liveness.map(|l| l.threshold),
+    };
+    let dir = ROOT_DIR.join(REPLAY_DIR);
+    let path = dir.join(replay_file_name(now_ms()));
+    let mut writer = ReplayWriter::create(&path, &header).map_err(|e| CustomResult::error(Some(e), None))?;
+
+    let mut attempt = UnlockAttempt::new(config, enrolled);
+    let mut detected = 0u32;
+    let mut frame = Mat::default();
+    for _ in 0..frames.max(1) {
+        wait_for_next_frame();
+        let mut app_state = lock_state()?;
+        let state = &mut *app_state;
+        // 录制期间摄像头可能被关闭（锁屏、租约被收回），已写入的帧保留为中断的回放
+        let camera = state
+            .camera
+            .as_mut()
+            .ok_or_else(|| CustomResult::error(Some(String::from("录制过程中摄像头已关闭")), None))?;
+        let read = camera
+            .inner
+            .read(&mut frame)
+            .map_err(|e| CustomResult::error(Some(format!("读取摄像头画面失败: {:?}", e)), None))?;
+        if !read || frame.empty() {
+            continue;
+        }
+        let captured_at = now_ms();
+        let start = Instant::now();
+        let normalized = normalize_frame(&frame, None).map_err(|e| CustomResult::error(Some(e), None))?;
+        let mut outputs = attempt
+            .observe(&mut attempt_models(state)?, &normalized.bgr, normalized.modality)
+            .map_err(|e| CustomResult::error(Some(e), None))?;
+        let ms = start.elapsed().as_secs_f64() * 1000.0;
+        drop(app_state);
+
+        if !outputs.faces.is_empty() {
+            detected += 1;
+        }
+        outputs.frame = writer
+            .write_frame(captured_at, &frame)
+            .map_err(|e| CustomResult::error(Some(e), None))?;
+        outputs.ms = ms;
+        writer.write_outputs(&outputs).map_err(|e| CustomResult::error(Some(e), None))?;
+    }
+    // 录制不是真正的解锁，只在尝试记录中给出学习决定，不修改模板库
+    let result = attempt.finish(None);
+
+    let written = writer
+        .finish(&result.to_outcome())
+        .map_err(|e| CustomResult::error(Some(e), None))?;
+    info!(
+        "录制回放 {}，{} 帧，检测到人脸 {} 帧，{}",
+        path.display(),
+        written,
+        detected,
+        result.reason.as_deref().unwrap_or("会解锁")
+    );
+
+    if let Err(e) = prune_replays(&dir, keep.unwrap_or(DEFAULT_KEEP)) {
+        warn!("清理旧的回放失败: {}", e);
+    }
+    Ok(CustomResult::success(
+        None,
+        Some(json!({
+            "path": path.display().to_string(),
+            "frames": written,
+            "detected": detected,
+            "unlocked": result.unlocked,
+            "matched": result.matched,
+            "reason": result.reason,
+            "attempt": result.log,
+        })),
+    ))
+}
+
+fn lock_state() -> Result<MutexGuard<'static, AppState>, CustomResult> {
+    APP_STATE
+        .lock()
+        .map_err(|e| CustomResult::error(Some(format!("获取app状态失败 {}", e)), None))
+}
+
+// 借出识别用到的模型，模型在录制过程中被卸载时返回错误
+fn attempt_models(state: &mut AppState) -> Result<AttemptModels<'_>, CustomResult> {
+    match (state.detector.as_mut(), state.recognizer.as_mut()) {
+        (Some(detector), Some(recognizer)) => Ok(AttemptModels {
+            detector: &mut detector.inner,
+            recognizer: &mut recognizer.inner,
+            liveness: state.liveness.as_mut().map(|net| &mut net.inner),
+        }),
+        _ => Err(CustomResult::error(Some(String::from("模型未加载，请先调用 load_opencv_model")), None)),
+    }
+}
+
+// 列出保存的回放，最新的在前
+#[tauri::command]
+pub fn list_replays() -> Result<CustomResult, CustomResult> {
+    let mut files = list_replay_files(&ROOT_DIR.join(REPLAY_DIR)).map_err(|e| CustomResult::error(Some(e), None))?;
+    files.sort();
+    files.reverse();
+
+    let replays: Vec<serde_json::Value> = files
+        .iter()
+        .map(|path| {
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+            let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            match summarize_replay(path) {
+                Ok(summary) => json!({
+                    "name": name,
+                    "path": path.display().to_string(),
+                    "size": size,
+                    "createdAt": summary.header.created_at,
+                    "recorder": summary.header.recorder,
+                    "source": summary.header.source,
+                    "frames": summary.frames,
+                    "outcome": summary.outcome,
+                }),
+                Err(e) => json!({"name": name, "path": path.display().to_string(), "size": size, "error": e}),
+            }
+        })
+        .collect();
+    Ok(CustomResult::success(None, Some(json!(replays))))
+}
+
+// 删除一个回放，name 为 list_replays 返回的文件名
+#[tauri::command]
+pub fn delete_replay(name: String) -> Result<CustomResult, CustomResult> {
+    // 只允许删除回放目录下的文件
+    if name.contains(['/', '\\']) || name.contains("..") {
+        return Err(CustomResult::error(Some(format!("回放文件名错误 {}", name)), None));
+    }
+    let path = ROOT_DIR.join(REPLAY_DIR).join(&name);
+    std::fs::remove_file(&path)
+        .map_err(|e| CustomResult::error(Some(format!("删除 {} 失败: {}", path.display(), e)), None))?;
+    Ok(CustomResult::success(None, None))
+}
@@ -0,0 +1,314 @@
+use std::{collections::HashMap, fs};
+
+use crate::{
+    modules::faces::save_face_registration,
+    utils::{
+        custom_result::CustomResult,
+        face_pipeline::{
+            detect_faces, encode_base64_image, extract_feature, face_values, feature_to_vec, largest_face,
+        },
+        face_quality::{assess_face_quality, QualityConfig},
+        frame_normalize::{normalize_frame, Modality},
+        liveness,
+        template_gallery::{now_secs, Template, TemplateGallery, TemplateSource},
+        video_enroll::{
+            frontal_score, sample_step, select_frames, FrameCandidate, Pose, RejectStats, VideoEnrollConfig,
+            VideoEnrollProgress,
+        },
+    },
+    APP_STATE, ROOT_DIR,
+};
+use opencv::{
+    core::Mat,
+    prelude::*,
+    videoio::{self, VideoCapture},
+};
+use serde_json::json;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_log::log::info;
+
+// 进度事件名
+const PROGRESS_EVENT: &str = "enroll-video-progress";
+
+// 从视频文件录入面容，一次生成主模板（.face / .faceimg）和其余姿态的模板（.gallery）
+// 视频较长，放到阻塞线程中处理，进度通过 enroll-video-progress 事件通知界面
+// liveness_threshold: 不传时不做活体检查
+// modality: 设置中指定的成像方式（rgb / ir），auto 或不传时自动判断
+// options: 设置 {设置名称: 设置值}，从 quality* 设置读取质量门槛
+#[tauri::command]
+pub async fn enroll_from_video(
+    app_handle: AppHandle,
+    video_path: String,
+    name: String,
+    face_detection_threshold: f32,
+    liveness_threshold: Option<f32>,
+    modality: Option<String>,
+    options: Option<HashMap<String, String>>,
+    config: Option<VideoEnrollConfig>,
+) -> Result<CustomResult, CustomResult> {
+    let options = options.unwrap_or_default();
+    let quality_config = QualityConfig::from_options(|key| options.get(key).cloned());
+    tauri::async_runtime::spawn_blocking(move || {
+        enroll_blocking(
+            &app_handle,
+            &video_path,
+            name,
+            face_detection_threshold,
+            liveness_threshold,
+            Modality::from_options(modality.as_deref()),
+            &quality_config,
+            &config.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| CustomResult::error(Some(format!("视频录入任务失败: {:?}", e)), None))?
+}
+
+fn enroll_blocking(
+    app_handle: &AppHandle,
+    video_path: &str,
+    name: String,
+    face_detection_threshold: f32,
+    liveness_threshold: Option<f32>,
+    modality_hint: Option<Modality>,
+    quality_config: &QualityConfig,
+    config: &VideoEnrollConfig,
+) -> Result<CustomResult, CustomResult> {
+    let mut video = VideoCapture::from_file(video_path, videoio::CAP_ANY)
+        .map_err(|e| CustomResult::error(Some(format!("打开视频失败: {:?}", e)), None))?;
+    if !video.is_opened().unwrap_or(false) {
+        return Err(CustomResult::error(Some(format!("无法打开视频 {}", video_path)), None));
+    }
+
+    let frame_count = video.get(videoio::CAP_PROP_FRAME_COUNT).unwrap_or(0.0) as i64;
+    let step = sample_step(video.get(videoio::CAP_PROP_FPS).unwrap_or(0.0), config);
+    // 部分容器读不到总帧数，按最多抽帧数估计进度
+    let total = if frame_count > 0 {
+        ((frame_count + step - 1) / step).min(config.max_samples as i64) as usize
+    } else {
+        config.max_samples
+    };
+    // 要求了活体检查就必须有模型，不能悄悄跳过
+    if liveness_threshold.is_some() {
+        let loaded = APP_STATE
+            .lock()
+            .map_err(|e| CustomResult::error(Some(format!("获取app状态失败 {}", e)), None))?
+            .liveness
+            .is_some();
+        if !loaded {
+            return Err(CustomResult::error(
+                Some(String::from("已开启活体检查，但活体检测模型未加载")),
+                None,
+            ));
+        }
+    }
+
+    let mut candidates: Vec<FrameCandidate> = Vec::new();
+    // 只有主模板需要图片，抽帧时只保留 frontal_score 最高的一帧的 jpg，与 select_frames 选出的主模板一致
+    let mut primary_image: Option<(usize, f32, String)> = None;
+    let mut rejected = RejectStats::default();
+    let mut modality = modality_hint.unwrap_or_default();
+    let mut processed = 0usize;
+    let mut frame_index = 0i64;
+    let mut frame = Mat::default();
+
+    while processed < config.max_samples {
+        let current_index = frame_index;
+        frame_index += 1;
+        // 不抽样的帧只 grab 不解码
+        if current_index % step != 0 {
+            let grabbed = video
+                .grab()
+                .map_err(|e| CustomResult::error(Some(format!("读取视频帧失败: {:?}", e)), None))?;
+            if !grabbed {
+                break;
+            }
+            continue;
+        }
+        let read = video
+            .read(&mut frame)
+            .map_err(|e| CustomResult::error(Some(format!("读取视频帧失败: {:?}", e)), None))?;
+        if !read || frame.empty() {
+            break;
+        }
+        let time_ms = video.get(videoio::CAP_PROP_POS_MSEC).unwrap_or(0.0);
+        processed += 1;
+
+        let normalized = normalize_frame(&frame, modality_hint).map_err(|e| CustomResult::error(Some(e), None))?;
+        modality = normalized.modality;
+        let img = normalized.bgr;
+
+        let candidate = {
+            let mut app_state = APP_STATE
+                .lock()
+                .map_err(|e| CustomResult::error(Some(format!("获取app状态失败 {}", e)), None))?;
+            let app_state = &mut *app_state;
+            let (detector, recognizer) = match (app_state.detector.as_mut(), app_state.recognizer.as_mut()) {
+                (Some(detector), Some(recognizer)) => (detector, recognizer),
+                _ => {
+                    return Err(CustomResult::error(
+                        Some(String::from("模型未加载，请先调用 load_opencv_model")),
+                        None,
+                    ))
+                }
+            };
+
+            let faces = detect_faces(&mut detector.inner, &img, face_detection_threshold)
+                .map_err(|e| CustomResult::error(Some(e), None))?;
+            match largest_face(&faces).map_err(|e| CustomResult::error(Some(e), None))? {
+                None => {
+                    rejected.no_face += 1;
+                    None
+                }
+                Some(face) => {
+                    let values = face_values(&face, 0).map_err(|e| CustomResult::error(Some(e), None))?;
+                    let quality = assess_face_quality(&img, &values, quality_config)
+                        .map_err(|e| CustomResult::error(Some(e), None))?;
+
+                    let liveness_score = match (liveness_threshold, app_state.liveness.as_mut()) {
+                        (Some(_), Some(net)) => {
+                            let crop = liveness::face_crop(&img, &values).map_err(|e| CustomResult::error(Some(e), None))?;
+                            Some(liveness::liveness_score(&mut net.inner, &crop).map_err(|e| CustomResult::error(Some(e), None))?)
+                        }
+                        _ => None,
+                    };
+
+                    if !quality.passed {
+                        rejected.quality += 1;
+                        None
+                    } else if liveness_threshold.zip(liveness_score).map_or(false, |(t, s)| s < t) {
+                        rejected.liveness += 1;
+                        None
+                    } else {
+                        let feature = extract_feature(&mut recognizer.inner, &img, &face)
+                            .and_then(|f| feature_to_vec(&f))
+                            .map_err(|e| CustomResult::error(Some(e), None))?;
+                        Some(FrameCandidate {
+                            frame_index: current_index,
+                            time_ms,
+                            quality,
+                            liveness_score,
+                            pose: Pose::from_landmarks(&values),
+                            feature,
+                        })
+                    }
+                }
+            }
+        };
+
+        if let Some(candidate) = candidate {
+            // 分数相同取靠后的一帧，与 select_frames 一致
+            let score = frontal_score(&candidate);
+            if primary_image.as_ref().map_or(true, |(_, best, _)| score >= *best) {
+                let image = encode_base64_image(&img).map_err(|e| CustomResult::error(Some(e), None))?;
+                primary_image = Some((candidates.len(), score, image));
+            }
+            candidates.push(candidate);
+        }
+        emit_progress(app_handle, "sampling", processed, total.max(processed), candidates.len());
+    }
+
+    let selected = select_frames(&candidates, config);
+    let primary = match selected.first() {
+        Some(index) => *index,
+        None => {
+            return Err(CustomResult::error(
+                Some(format!(
+                    "视频中没有可用的画面（共检查 {} 帧，未检测到人脸 {} 帧，质量不合格 {} 帧，活体未通过 {} 帧）",
+                    processed, rejected.no_face, rejected.quality, rejected.liveness
+                )),
+                Some(json!({"rejected": rejected})),
+            ))
+        }
+    };
+    let primary_image = match primary_image {
+        Some((index, _, image)) if index == primary => image,
+        _ => {
+            return Err(CustomResult::error(
+                Some(String::from("主模板的画面与选中的帧不一致")),
+                None,
+            ))
+        }
+    };
+    emit_progress(app_handle, "saving", processed, processed, candidates.len());
+
+    // 主模板走与图片录入相同的流程，save_face_registration 会自己获取 APP_STATE
+    let saved = save_face_registration(name, primary_image.clone(), face_detection_threshold)?;
+    let face_token = saved.data["file_name"]
+        .as_str()
+        .ok_or_else(|| CustomResult::error(Some(String::from("保存面容失败：没有返回文件名")), None))?
+        .to_string();
+
+    let faces_dir = ROOT_DIR.join("faces");
+    let mut gallery = TemplateGallery::default();
+    for index in selected.iter().skip(1) {
+        let candidate = &candidates[*index];
+        gallery.templates.push(Template {
+            id: uuid::Uuid::new_v4().to_string(),
+            source: TemplateSource::Enrolled,
+            modality,
+            feature: candidate.feature.clone(),
+            created_at: now_secs(),
+            match_count: 0,
+            last_matched_at: None,
+            quality: Some(candidate.quality.score),
+        });
+    }
+    if !gallery.templates.is_empty() {
+        if let Err(e) = gallery.save(&faces_dir, &face_token) {
+            // 模板库没写成功，主模板也不保留，避免出现只有一半的面容
+            for ext in ["face", "faceimg"] {
+                let _ = fs::remove_file(faces_dir.join(format!("{}.{}", face_token, ext)));
+            }
+            return Err(CustomResult::error(Some(e), None));
+        }
+    }
+    emit_progress(app_handle, "done", processed, processed, candidates.len());
+
+    let frames: Vec<serde_json::Value> = selected
+        .iter()
+        .map(|index| {
+            let c = &candidates[*index];
+            json!({
+                "frameIndex": c.frame_index,
+                "timeMs": c.time_ms,
+                "quality": c.quality.score,
+                "liveness": c.liveness_score,
+                "yaw": c.pose.yaw,
+                "pitch": c.pose.pitch,
+            })
+        })
+        .collect();
+    info!(
+        "视频录入完成：检查 {} 帧，可用 {} 帧，生成 {} 个模板",
+        processed,
+        candidates.len(),
+        selected.len()
+    );
+
+    Ok(CustomResult::success(
+        None,
+        Some(json!({
+            "file_name": face_token,
+            "raw_base64": format!("data:image/jpeg;base64,{}", primary_image),
+            "quality": candidates[primary].quality.score,
+            "modality": modality,
+            "templates": selected.len(),
+            "frames": frames,
+            "processed": processed,
+            "rejected": rejected,
+        })),
+    ))
+}
+
+fn emit_progress(app_handle: &AppHandle, stage: &'static str, processed: usize, total: usize, accepted: usize) {
+    let _ = app_handle.emit(
+        PROGRESS_EVENT,
+        VideoEnrollProgress {
+            stage,
+            processed,
+            total,
+            accepted,
+        },
+    );
+}
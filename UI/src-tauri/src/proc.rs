@@ -10,9 +10,12 @@ use windows::Win32::{
     },
 };
 
-use crate::
-    utils::api::stop_camera
-;
+use std::sync::atomic::Ordering;
+
+use crate::{
+    modules::preview::stop_preview_stream,
+    utils::api::{stop_camera, SESSION_LOCKED},
+};
 // windows回调
 pub unsafe extern "system" fn wnd_proc_subclass(
     hwnd: HWND,
@@ -29,12 +32,15 @@ pub unsafe extern "system" fn wnd_proc_subclass(
         match event_type {
             WTS_SESSION_LOCK => {
                 // 屏幕锁屏，关闭摄像头，因为不确定用户是否开启了摄像头
+                // 锁屏期间摄像头留给解锁服务（LeasePriority::LockScreen），open_camera 不再打开
+                SESSION_LOCKED.store(true, Ordering::SeqCst);
+                stop_preview_stream("锁屏");
                 if let Err(e) = stop_camera() {
                     error!("关闭摄像头失败: {}", e.to_string());
                 }
             }
             WTS_SESSION_UNLOCK => {
-
+                SESSION_LOCKED.store(false, Ordering::SeqCst);
             }
             _ => {}
         }
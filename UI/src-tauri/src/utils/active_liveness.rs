@@ -0,0 +1,524 @@
+// 动作活体检测（挑战-应答）
+// 提示用户眨眼、转头或点头，根据同一张人脸在多帧中的关键点轨迹判断是否完成了动作
+// 转头、点头只用 YuNet 的 5 个关键点；眨眼用眼睛关键点定位眼部区域，再看区域对比度的变化
+use opencv::{
+    core::{Mat, Rect},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use super::face_pipeline::FACE_ROW_LEN;
+
+// 基准姿态需要的帧数
+const BASELINE_FRAMES: usize = 3;
+// 基准帧允许的最大偏航，太歪了不能作为基准
+const BASELINE_MAX_YAW: f32 = 0.2;
+// 转头时偏航相对基准的最小变化（以两眼间距归一化）
+const TURN_DELTA: f32 = 0.25;
+// 点头时俯仰相对基准的最小变化
+const NOD_DELTA: f32 = 0.15;
+// 眨眼时眼部对比度相对基准的下降比例
+const BLINK_DROP_RATIO: f32 = 0.7;
+// 动作需要连续保持的帧数
+const HOLD_FRAMES: usize = 2;
+// 单个动作的默认超时
+const DEFAULT_CHALLENGE_TIMEOUT_MS: u64 = 5000;
+// 采集基准姿态的超时，从第一次检测到人脸开始计算
+const BASELINE_TIMEOUT_MS: u64 = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Challenge {
+    Blink,
+    TurnLeft,
+    TurnRight,
+    Nod,
+}
+
+impl Challenge {
+    pub const ALL: [Challenge; 4] = [
+        Challenge::Blink,
+        Challenge::TurnLeft,
+        Challenge::TurnRight,
+        Challenge::Nod,
+    ];
+
+    // 界面上显示的提示
+    pub fn prompt(&self) -> &'static str {
+        match self {
+            Challenge::Blink => "请眨眨眼",
+            Challenge::TurnLeft => "请向左转头",
+            Challenge::TurnRight => "请向右转头",
+            Challenge::Nod => "请点点头",
+        }
+    }
+}
+
+// 按随机数挑选 count 个不重复的动作
+pub fn pick_challenges(random: &[u8], count: usize) -> Vec<Challenge> {
+    let mut pool: Vec<Challenge> = Challenge::ALL.to_vec();
+    let mut picked = Vec::new();
+    for byte in random.iter().take(count.min(Challenge::ALL.len())) {
+        let index = *byte as usize % pool.len();
+        picked.push(pool.remove(index));
+    }
+    picked
+}
+
+// 一帧的观测数据
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LandmarkFrame {
+    // 毫秒时间戳
+    pub timestamp_ms: u64,
+    // 右眼、左眼、鼻尖、右嘴角、左嘴角，每个点 [x, y]
+    pub landmarks: [[f32; 2]; 5],
+    // 眼部区域的对比度，不做眨眼检测时可以为 None
+    pub eye_openness: Option<f32>,
+}
+
+impl LandmarkFrame {
+    // 从 YuNet 的一行数据构造
+    pub fn from_face_values(values: &[f32; FACE_ROW_LEN], timestamp_ms: u64, eye_openness: Option<f32>) -> Self {
+        let mut landmarks = [[0f32; 2]; 5];
+        for (i, point) in landmarks.iter_mut().enumerate() {
+            *point = [values[4 + i * 2], values[5 + i * 2]];
+        }
+        Self {
+            timestamp_ms,
+            landmarks,
+            eye_openness,
+        }
+    }
+
+    fn inter_eye_distance(&self) -> f32 {
+        let [right, left] = [self.landmarks[0], self.landmarks[1]];
+        ((left[0] - right[0]).powi(2) + (left[1] - right[1]).powi(2)).sqrt().max(1.0)
+    }
+
+    // 偏航：鼻尖相对两眼中点的水平偏移，正数为用户向自己的左侧转头（原始画面中鼻尖向右移动）
+    pub fn yaw(&self) -> f32 {
+        let eye_mid_x = (self.landmarks[0][0] + self.landmarks[1][0]) / 2.0;
+        (self.landmarks[2][0] - eye_mid_x) / self.inter_eye_distance()
+    }
+
+    // 俯仰：鼻尖相对两眼中点的垂直距离，低头时变大
+    pub fn pitch(&self) -> f32 {
+        let eye_mid_y = (self.landmarks[0][1] + self.landmarks[1][1]) / 2.0;
+        (self.landmarks[2][1] - eye_mid_y) / self.inter_eye_distance()
+    }
+}
+
+// 计算双眼区域的对比度（灰度标准差），闭眼时眼部接近均匀的皮肤，对比度会明显下降
+pub fn eye_openness(gray: &Mat, values: &[f32; FACE_ROW_LEN]) -> Result<f32, String> {
+    let frame = LandmarkFrame::from_face_values(values, 0, None);
+    let half = (frame.inter_eye_distance() * 0.2).max(3.0);
+    let mut total = 0f32;
+    for eye in &frame.landmarks[0..2] {
+        let left = ((eye[0] - half).max(0.0)) as i32;
+        let top = ((eye[1] - half * 0.6).max(0.0)) as i32;
+        let right = ((eye[0] + half) as i32).min(gray.cols());
+        let bottom = ((eye[1] + half * 0.6) as i32).min(gray.rows());
+        if right <= left || bottom <= top {
+            return Err(String::from("眼部区域超出图片范围"));
+        }
+        let roi = Mat::roi(gray, Rect::new(left, top, right - left, bottom - top))
+            .map_err(|e| format!("裁剪眼部区域失败: {:?}", e))?;
+        let mut mean = Mat::default();
+        let mut stddev = Mat::default();
+        opencv::core::mean_std_dev(&roi, &mut mean, &mut stddev, &opencv::core::no_array())
+            .map_err(|e| format!("计算眼部对比度失败: {:?}", e))?;
+        total += *stddev
+            .at::<f64>(0)
+            .map_err(|e| format!("读取眼部对比度失败: {:?}", e))? as f32;
+    }
+    Ok(total / 2.0)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub enum ChallengeStatus {
+    // 正在采集基准姿态，请正对摄像头
+    Baseline,
+    // 等待用户完成动作
+    Prompt { challenge: Challenge, prompt: String, index: usize, total: usize },
+    Passed,
+    Failed { reason: String },
+}
+
+// 基准姿态
+#[derive(Debug, Clone, Copy)]
+struct Baseline {
+    yaw: f32,
+    pitch: f32,
+    eye_openness: Option<f32>,
+}
+
+// 动作验证器，一次只验证一张人脸（同一个 track id）
+pub struct ChallengeVerifier {
+    challenges: Vec<Challenge>,
+    current: usize,
+    timeout_ms: u64,
+    baseline_frames: Vec<LandmarkFrame>,
+    baseline: Option<Baseline>,
+    // 开始采集基准姿态的时间
+    baseline_started_ms: Option<u64>,
+    // 当前动作开始的时间
+    started_ms: Option<u64>,
+    // 动作达到幅度的连续帧数
+    hold: usize,
+    // 点头、眨眼需要先到达峰值再回到基准
+    reached_peak: bool,
+    status: ChallengeStatus,
+}
+
+impl ChallengeVerifier {
+    pub fn new(challenges: Vec<Challenge>) -> Self {
+        Self::with_timeout(challenges, DEFAULT_CHALLENGE_TIMEOUT_MS)
+    }
+
+    pub fn with_timeout(challenges: Vec<Challenge>, timeout_ms: u64) -> Self {
+        let status = if challenges.is_empty() {
+            ChallengeStatus::Passed
+        } else {
+            ChallengeStatus::Baseline
+        };
+        Self {
+            challenges,
+            current: 0,
+            timeout_ms,
+            baseline_frames: Vec::new(),
+            baseline: None,
+            baseline_started_ms: None,
+            started_ms: None,
+            hold: 0,
+            reached_peak: false,
+            status,
+        }
+    }
+
+    pub fn status(&self) -> &ChallengeStatus {
+        &self.status
+    }
+
+    // 跟踪的人脸丢失或换人时调用
+    pub fn lose_face(&mut self) {
+        if matches!(self.status, ChallengeStatus::Passed | ChallengeStatus::Failed { .. }) {
+            return;
+        }
+        self.status = ChallengeStatus::Failed {
+            reason: String::from("动作过程中人脸离开了画面"),
+        };
+    }
+
+    // 输入一帧，返回最新状态
+    pub fn push(&mut self, frame: LandmarkFrame) -> &ChallengeStatus {
+        match self.status {
+            ChallengeStatus::Passed | ChallengeStatus::Failed { .. } => return &self.status,
+            ChallengeStatus::Baseline => {
+                self.push_baseline(frame);
+                return &self.status;
+            }
+            ChallengeStatus::Prompt { .. } => {}
+        }
+
+        let started = *self.started_ms.get_or_insert(frame.timestamp_ms);
+        if frame.timestamp_ms.saturating_sub(started) > self.timeout_ms {
+            self.status = ChallengeStatus::Failed {
+                reason: format!("未在规定时间内完成动作：{}", self.challenges[self.current].prompt()),
+            };
+            return &self.status;
+        }
+
+        if self.check_action(&frame) {
+            self.next_challenge(frame.timestamp_ms);
+        }
+        &self.status
+    }
+
+    fn push_baseline(&mut self, frame: LandmarkFrame) {
+        let started = *self.baseline_started_ms.get_or_insert(frame.timestamp_ms);
+        if frame.timestamp_ms.saturating_sub(started) > BASELINE_TIMEOUT_MS {
+            self.status = ChallengeStatus::Failed {
+                reason: String::from("未能采集到正对摄像头的姿态"),
+            };
+            return;
+        }
+        // 基准帧必须大致正对摄像头
+        if frame.yaw().abs() > BASELINE_MAX_YAW {
+            self.baseline_frames.clear();
+            return;
+        }
+        self.baseline_frames.push(frame);
+        if self.baseline_frames.len() < BASELINE_FRAMES {
+            return;
+        }
+
+        let count = self.baseline_frames.len() as f32;
+        let openness: Vec<f32> = self.baseline_frames.iter().filter_map(|f| f.eye_openness).collect();
+        self.baseline = Some(Baseline {
+            yaw: self.baseline_frames.iter().map(|f| f.yaw()).sum::<f32>() / count,
+            pitch: self.baseline_frames.iter().map(|f| f.pitch()).sum::<f32>() / count,
+            eye_openness: if openness.is_empty() {
+                None
+            } else {
+                Some(openness.iter().sum::<f32>() / openness.len() as f32)
+            },
+        });
+        self.current = 0;
+        self.set_prompt(frame.timestamp_ms);
+    }
+
+    fn check_action(&mut self, frame: &LandmarkFrame) -> bool {
+        let baseline = match self.baseline {
+            Some(baseline) => baseline,
+            None => return false,
+        };
+
+        match self.challenges[self.current] {
+            Challenge::TurnLeft => self.hold_until(frame.yaw() - baseline.yaw >= TURN_DELTA),
+            Challenge::TurnRight => self.hold_until(baseline.yaw - frame.yaw() >= TURN_DELTA),
+            Challenge::Nod => {
+                let delta = frame.pitch() - baseline.pitch;
+                self.peak_then_return(delta >= NOD_DELTA, delta.abs() < NOD_DELTA / 2.0)
+            }
+            Challenge::Blink => match (baseline.eye_openness, frame.eye_openness) {
+                (Some(base), Some(current)) if base > 0.0 => {
+                    let ratio = current / base;
+                    self.peak_then_return(ratio <= BLINK_DROP_RATIO, ratio >= 0.9)
+                }
+                // 个别帧眼部区域超出画面时跳过这一帧，由动作超时兜底
+                (Some(base), None) if base > 0.0 => false,
+                _ => {
+                    self.status = ChallengeStatus::Failed {
+                        reason: String::from("缺少眼部数据，无法验证眨眼"),
+                    };
+                    false
+                }
+            },
+        }
+    }
+
+    // 动作幅度需要连续保持 HOLD_FRAMES 帧
+    fn hold_until(&mut self, reached: bool) -> bool {
+        if reached {
+            self.hold += 1;
+        } else {
+            self.hold = 0;
+        }
+        self.hold >= HOLD_FRAMES
+    }
+
+    // 先到达峰值，再回到基准附近才算完成
+    fn peak_then_return(&mut self, at_peak: bool, at_rest: bool) -> bool {
+        if at_peak {
+            self.reached_peak = true;
+            return false;
+        }
+        self.reached_peak && at_rest
+    }
+
+    fn next_challenge(&mut self, timestamp_ms: u64) {
+        self.current += 1;
+        if self.current >= self.challenges.len() {
+            self.status = ChallengeStatus::Passed;
+            return;
+        }
+        self.set_prompt(timestamp_ms);
+    }
+
+    fn set_prompt(&mut self, timestamp_ms: u64) {
+        let challenge = self.challenges[self.current];
+        self.started_ms = Some(timestamp_ms);
+        self.hold = 0;
+        self.reached_peak = false;
+        self.status = ChallengeStatus::Prompt {
+            challenge,
+            prompt: challenge.prompt().to_string(),
+            index: self.current + 1,
+            total: self.challenges.len(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 两眼间距 60 像素的正脸，yaw / pitch 与 LandmarkFrame 的定义一致
+    fn frame(timestamp_ms: u64, yaw: f32, pitch: f32, eye_openness: Option<f32>) -> LandmarkFrame {
+        frame_at(timestamp_ms, yaw, pitch, eye_openness, [0.0, 0.0], 1.0)
+    }
+
+    // offset / scale 模拟照片在镜头前平移、前后移动
+    fn frame_at(
+        timestamp_ms: u64,
+        yaw: f32,
+        pitch: f32,
+        eye_openness: Option<f32>,
+        offset: [f32; 2],
+        scale: f32,
+    ) -> LandmarkFrame {
+        let point = |x: f32, y: f32| [offset[0] + 200.0 + x * scale, offset[1] + 150.0 + y * scale];
+        LandmarkFrame {
+            timestamp_ms,
+            landmarks: [
+                point(-30.0, 0.0),
+                point(30.0, 0.0),
+                point(yaw * 60.0, (0.6 + pitch) * 60.0),
+                point(-22.0, 70.0),
+                point(22.0, 70.0),
+            ],
+            eye_openness,
+        }
+    }
+
+    // 依次输入 (yaw, pitch, 眼部对比度)，每帧间隔 100ms，返回最后的状态
+    fn run(verifier: &mut ChallengeVerifier, start_ms: u64, frames: &[(f32, f32, Option<f32>)]) -> ChallengeStatus {
+        for (i, (yaw, pitch, openness)) in frames.iter().enumerate() {
+            verifier.push(frame(start_ms + i as u64 * 100, *yaw, *pitch, *openness));
+        }
+        verifier.status().clone()
+    }
+
+    const OPEN: Option<f32> = Some(20.0);
+    const CLOSED: Option<f32> = Some(9.0);
+
+    fn baseline() -> Vec<(f32, f32, Option<f32>)> {
+        vec![(0.0, 0.0, OPEN); BASELINE_FRAMES]
+    }
+
+    #[test]
+    fn landmark_geometry() {
+        let f = frame(0, 0.3, 0.1, None);
+        assert!((f.yaw() - 0.3).abs() < 1e-4);
+        assert!((f.pitch() - 0.7).abs() < 1e-4);
+    }
+
+    #[test]
+    fn blink_passes_after_close_and_reopen() {
+        let mut verifier = ChallengeVerifier::new(vec![Challenge::Blink]);
+        let mut frames = baseline();
+        frames.extend([(0.0, 0.0, OPEN), (0.0, 0.0, CLOSED), (0.0, 0.0, OPEN)]);
+        assert_eq!(run(&mut verifier, 0, &frames), ChallengeStatus::Passed);
+    }
+
+    #[test]
+    fn blink_skips_frames_without_eye_data() {
+        let mut verifier = ChallengeVerifier::new(vec![Challenge::Blink]);
+        let mut frames = baseline();
+        frames.extend([(0.0, 0.0, None), (0.0, 0.0, CLOSED), (0.0, 0.0, None), (0.0, 0.0, OPEN)]);
+        assert_eq!(run(&mut verifier, 0, &frames), ChallengeStatus::Passed);
+    }
+
+    #[test]
+    fn blink_fails_without_baseline_eye_data() {
+        let mut verifier = ChallengeVerifier::new(vec![Challenge::Blink]);
+        let frames = vec![(0.0, 0.0, None); BASELINE_FRAMES + 1];
+        assert!(matches!(run(&mut verifier, 0, &frames), ChallengeStatus::Failed { .. }));
+    }
+
+    #[test]
+    fn closed_eyes_without_reopening_do_not_pass() {
+        let mut verifier = ChallengeVerifier::new(vec![Challenge::Blink]);
+        let mut frames = baseline();
+        frames.extend([(0.0, 0.0, CLOSED); 5]);
+        assert!(matches!(run(&mut verifier, 0, &frames), ChallengeStatus::Prompt { .. }));
+    }
+
+    #[test]
+    fn turn_left_and_right() {
+        let mut left = ChallengeVerifier::new(vec![Challenge::TurnLeft]);
+        let mut frames = baseline();
+        frames.extend([(0.15, 0.0, OPEN), (0.3, 0.0, OPEN), (0.35, 0.0, OPEN)]);
+        assert_eq!(run(&mut left, 0, &frames), ChallengeStatus::Passed);
+
+        let mut right = ChallengeVerifier::new(vec![Challenge::TurnRight]);
+        let mut frames = baseline();
+        frames.extend([(-0.3, 0.0, OPEN), (-0.35, 0.0, OPEN)]);
+        assert_eq!(run(&mut right, 0, &frames), ChallengeStatus::Passed);
+    }
+
+    #[test]
+    fn turn_must_be_held() {
+        let mut verifier = ChallengeVerifier::new(vec![Challenge::TurnLeft]);
+        let mut frames = baseline();
+        frames.extend([(0.3, 0.0, OPEN), (0.0, 0.0, OPEN), (0.3, 0.0, OPEN), (0.0, 0.0, OPEN)]);
+        assert!(matches!(run(&mut verifier, 0, &frames), ChallengeStatus::Prompt { .. }));
+    }
+
+    #[test]
+    fn wrong_direction_times_out() {
+        let mut verifier = ChallengeVerifier::new(vec![Challenge::TurnLeft]);
+        let mut frames = baseline();
+        frames.extend(vec![(-0.4, 0.0, OPEN); 60]);
+        match run(&mut verifier, 0, &frames) {
+            ChallengeStatus::Failed { reason } => assert!(reason.contains(Challenge::TurnLeft.prompt())),
+            status => panic!("应当超时失败，实际为 {:?}", status),
+        }
+    }
+
+    #[test]
+    fn nod_then_second_challenge() {
+        let mut verifier = ChallengeVerifier::new(vec![Challenge::Nod, Challenge::TurnRight]);
+        let mut frames = baseline();
+        frames.extend([(0.0, 0.2, OPEN), (0.0, 0.01, OPEN)]);
+        match run(&mut verifier, 0, &frames) {
+            ChallengeStatus::Prompt { challenge, index, total, .. } => {
+                assert_eq!((challenge, index, total), (Challenge::TurnRight, 2, 2));
+            }
+            status => panic!("应当进入第二个动作，实际为 {:?}", status),
+        }
+        assert_eq!(
+            run(&mut verifier, 10_000, &[(-0.3, 0.0, OPEN), (-0.3, 0.0, OPEN)]),
+            ChallengeStatus::Failed {
+                reason: format!("未在规定时间内完成动作：{}", Challenge::TurnRight.prompt())
+            }
+        );
+    }
+
+    #[test]
+    fn baseline_has_its_own_deadline() {
+        // 一直侧着脸，采集不到基准姿态
+        let mut verifier = ChallengeVerifier::new(vec![Challenge::TurnLeft]);
+        let frames = vec![(0.4, 0.0, OPEN); 40];
+        assert_eq!(run(&mut verifier, 0, &frames), ChallengeStatus::Baseline);
+        let frames = vec![(0.4, 0.0, OPEN); 20];
+        assert!(matches!(run(&mut verifier, 4_000, &frames), ChallengeStatus::Failed { .. }));
+    }
+
+    #[test]
+    fn moving_photo_does_not_pass() {
+        // 照片在镜头前平移、靠近：关键点整体移动缩放，偏航、俯仰和眼部对比度都不变
+        for challenge in Challenge::ALL {
+            let mut verifier = ChallengeVerifier::new(vec![challenge]);
+            for i in 0..80u64 {
+                let shift = (i as f32 * 0.7).sin() * 40.0;
+                let scale = 1.0 + (i as f32 * 0.3).cos() * 0.3;
+                verifier.push(frame_at(i * 100, 0.02, 0.0, OPEN, [shift, shift / 2.0], scale));
+            }
+            assert!(
+                matches!(verifier.status(), ChallengeStatus::Failed { .. }),
+                "{:?} 被照片通过",
+                challenge
+            );
+        }
+    }
+
+    #[test]
+    fn losing_the_face_fails() {
+        let mut verifier = ChallengeVerifier::new(vec![Challenge::Blink]);
+        run(&mut verifier, 0, &baseline());
+        verifier.lose_face();
+        assert!(matches!(verifier.status(), ChallengeStatus::Failed { .. }));
+    }
+
+    #[test]
+    fn picks_distinct_challenges() {
+        let picked = pick_challenges(&[7, 7, 7, 7, 7], 10);
+        assert_eq!(picked.len(), Challenge::ALL.len());
+        for challenge in Challenge::ALL {
+            assert!(picked.contains(&challenge));
+        }
+        assert!(ChallengeVerifier::new(Vec::new()).status() == &ChallengeStatus::Passed);
+    }
+}
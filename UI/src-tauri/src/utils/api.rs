@@ -1,18 +1,34 @@
-use std::{os::windows::process::CommandExt, process::Command};
+use std::{
+    collections::BTreeMap,
+    os::windows::process::CommandExt,
+    process::Command,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
 
 use crate::{
     modules::options::{write_to_registry, RegistryItem},
-    utils::custom_result::CustomResult,
+    utils::{
+        camera_backend::{backend_order, BackendMemory},
+        camera_idle::{IdleClock, IdleStopped, IdleTracked, DEFAULT_IDLE_TIMEOUT_SECS, IDLE_STOP_EVENT},
+        camera_identity::{enumerate_video_devices, resolve_camera, CameraDescriptor, CameraFallback},
+        camera_lease::{Lease, LeaseError, LeaseManager, LeaseMode, LeasePriority, LeaseRequest, DEFAULT_TTL_MS},
+        camera_warmup::warm_up,
+        capture_profile::{apply_capture_profile, CaptureProfile},
+        custom_result::CustomResult,
+        face_pipeline::create_detector_from_file,
+        frame_source::{is_source_uri, open_source_uri, CameraSource, FrameSource},
+        model_manifest::{verified_model_path, ModelManifest, ModelRole},
+    },
     OpenCVResource, APP_STATE, GLOBAL_TRAY, ROOT_DIR,
 };
 use opencv::{
-    core::{Mat, MatTraitConst, Size},
-    objdetect::{FaceDetectorYN, FaceRecognizerSF},
+    core::{Mat, MatTraitConst},
+    objdetect::FaceRecognizerSF,
     videoio::{self, VideoCapture, VideoCaptureTrait, VideoCaptureTraitConst},
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tauri::{AppHandle, Manager};
+use tauri::{AppHandle, Emitter, Manager};
 use tauri_plugin_log::log::{error, info, warn};
 use windows::{
     core::{BSTR, HSTRING, PWSTR},
@@ -45,7 +61,7 @@ struct ValidCameraInfo {
 }
 
 // 定义摄像头后端类型枚举
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CameraBackend {
     Any,   // CAP_ANY
     DShow, // CAP_DSHOW
@@ -82,37 +98,57 @@ pub fn test_win_logon(user_name: String, password: String) -> Result<CustomResul
 // 初始化模型
 #[tauri::command]
 pub fn init_model() -> Result<CustomResult, CustomResult> {
-    // 加载模型
-    let resource_path = ROOT_DIR
-        .join("resources")
-        .join("face_detection_yunet_2023mar.onnx");
-
-    // 这个不用检查文件是否存在，不存在opencv会报错
-    let _ = FaceDetectorYN::create(
-        resource_path.to_str().unwrap_or(""),
-        "",
-        Size::new(320, 320), // 初始尺寸，后面会动态更新
-        0.9,
-        0.3,
-        5000,
-        0,
-        0,
-    )
-    .map_err(|e| CustomResult::error(Some(format!("初始化检测器模型失败: {:?}", e)), None))?;
-
-    let resource_path = ROOT_DIR
-        .join("resources")
-        .join("face_recognition_sface_2021dec.onnx");
+    let resource_dir = ROOT_DIR.join("resources");
+    // 先按清单检查模型文件，文件缺失或被替换时给出明确的错误
+    let manifest = ModelManifest::load(&resource_dir)
+        .map_err(|e| CustomResult::error(Some(e.to_string()), Some(json!(e))))?;
+    let verify = |role: ModelRole| {
+        manifest
+            .verify(&resource_dir, role)
+            .map_err(|e| CustomResult::error(Some(e.to_string()), Some(json!(e))))
+    };
+
+    let resource_path = verify(ModelRole::Detector)?;
+    let _ = create_detector_from_file(&resource_path).map_err(|e| CustomResult::error(Some(e), None))?;
+
+    let resource_path = verify(ModelRole::Recognizer)?;
     let _ = FaceRecognizerSF::create(resource_path.to_str().unwrap_or(""), "", 0, 0)
         .map_err(|e| CustomResult::error(Some(format!("初始化识别器模型失败: {:?}", e)), None))?;
 
     // 加载活体检测模型
-    let _ = opencv::dnn::read_net_from_onnx(ROOT_DIR.join("resources").join("face_liveness.onnx").to_str().unwrap())
+    let resource_path = verify(ModelRole::Liveness)?;
+    let _ = opencv::dnn::read_net_from_onnx(resource_path.to_str().unwrap_or(""))
             .map_err(|e| CustomResult::error(Some(format!("初始化活体检测模型失败: {:?}", e)), None))?;
 
     Ok(CustomResult::success(None, None))
 }
 
+// 检查全部模型文件，安装和初始化时使用
+#[tauri::command]
+pub fn verify_models() -> Result<CustomResult, CustomResult> {
+    let resource_dir = ROOT_DIR.join("resources");
+    let manifest = ModelManifest::load(&resource_dir)
+        .map_err(|e| CustomResult::error(Some(e.to_string()), Some(json!(e))))?;
+    let statuses = manifest.verify_all(&resource_dir);
+    if let Some(failed) = statuses.iter().find(|s| !s.ok) {
+        return Err(CustomResult::error(failed.message.clone(), Some(json!(statuses))));
+    }
+    Ok(CustomResult::success(None, Some(json!(statuses))))
+}
+
+// 获取当前各模型的版本，用于判断面容模板是否需要迁移
+#[tauri::command]
+pub fn get_model_versions() -> Result<CustomResult, CustomResult> {
+    let manifest = ModelManifest::load(&ROOT_DIR.join("resources"))
+        .map_err(|e| CustomResult::error(Some(e.to_string()), Some(json!(e))))?;
+    let versions: Vec<serde_json::Value> = manifest
+        .models
+        .iter()
+        .map(|m| json!({"role": m.role, "name": m.name, "version": m.version}))
+        .collect();
+    Ok(CustomResult::success(None, Some(json!(versions))))
+}
+
 // 获取windows所有摄像头
 #[tauri::command]
 pub fn get_camera() -> Result<CustomResult, CustomResult> {
@@ -123,11 +159,132 @@ pub fn get_camera() -> Result<CustomResult, CustomResult> {
 }
 
 // 打开摄像头
+// source: 不使用摄像头时的画面来源，如 file:///D:/demo、synthetic://640x480，见 frame_source
+// device: 设置中保存的摄像头，传入时按名称和设备路径换算成当前序号，忽略 camear_index
+// fallback: 保存的摄像头未连接时的处理方式，默认使用保存时的序号
+// capture: 该摄像头的采集参数，不传时使用驱动默认值
+// priority: 申请摄像头租约的优先级，默认为预览；锁屏时解锁服务会收回界面的租约
+// backends: 后端的尝试顺序，不传时使用默认顺序；backend 指定时只尝试这一个
+// chain: 备用摄像头，device 打不开时按顺序尝试
+// captures: 备用摄像头的采集参数 {摄像头标识: 参数}，即设置中的 cameraCapture，键见 CameraDescriptor::profile_key
+// idle_timeout_secs: 超过多少秒没有读取画面时自动关闭摄像头，0 为不关闭，默认 60 秒
 #[tauri::command]
 pub fn open_camera(
+    app: AppHandle,
     backend: Option<CameraBackend>,
     camear_index: i32,
+    source: Option<String>,
+    device: Option<CameraDescriptor>,
+    fallback: Option<CameraFallback>,
+    capture: Option<CaptureProfile>,
+    priority: Option<LeasePriority>,
+    backends: Option<Vec<CameraBackend>>,
+    chain: Option<Vec<CameraDescriptor>>,
+    captures: Option<BTreeMap<String, CaptureProfile>>,
+    idle_timeout_secs: Option<u64>,
 ) -> Result<CustomResult, CustomResult> {
+    let idle_timeout_secs = idle_timeout_secs.unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS);
+    // 画面来源只接受 file:// 和 synthetic://，填错时报错而不是悄悄改用摄像头
+    if let Some(source) = source.filter(|s| !s.trim().is_empty()) {
+        if !is_source_uri(&source) {
+            return Err(CustomResult::error(
+                Some(format!("画面来源 {} 格式错误，应以 file:// 或 synthetic:// 开头", source)),
+                None,
+            ));
+        }
+        let frame_source = open_source_uri(&source).map_err(|e| CustomResult::error(Some(e), None))?;
+        info!("使用画面来源：{}", frame_source.describe());
+        // 不占用摄像头，之前打开摄像头时的租约不再需要
+        release_camera_lease(APP_STATE.lock().ok().and_then(|mut app_state| app_state.camera_lease.take()));
+        return set_camera_source(&app, frame_source, idle_timeout_secs);
+    }
+
+    let capture = capture.filter(|c| !c.is_empty());
+
+    // 锁屏期间摄像头留给解锁服务，界面不再申请
+    if SESSION_LOCKED.load(Ordering::SeqCst) {
+        return Err(CustomResult::error(
+            Some(String::from("锁屏期间摄像头由解锁服务使用")),
+            None,
+        ));
+    }
+
+    // 先拿到租约再打开设备，摄像头被解锁服务占用时直接告诉用户
+    let lease = LeaseManager::new(&ROOT_DIR)
+        .acquire(&LeaseRequest {
+            holder: String::from("面容管理程序"),
+            mode: LeaseMode::Exclusive,
+            priority: priority.unwrap_or(LeasePriority::Preview).for_ui(),
+            ttl_ms: DEFAULT_TTL_MS,
+            wait_ms: 1000,
+        })
+        .map_err(|e| CustomResult::error(Some(e.to_string()), Some(json!(e))))?;
+    let lease_id = lease.id.clone();
+    APP_STATE
+        .lock()
+        .map_err(|e| CustomResult::error(Some(format!("获取app状态失败 {}", e)), None))?
+        .camera_lease = Some(lease);
+    keep_camera_lease(lease_id);
+
+    // 设置的摄像头在前，备用摄像头按顺序在后；都没有时按 camear_index 打开
+    let device_given = device.is_some();
+    let candidates: Vec<CameraDescriptor> = device.into_iter().chain(chain.unwrap_or_default()).collect();
+    let mut memory = BackendMemory::load(&ROOT_DIR);
+    if candidates.is_empty() {
+        let key = format!("#{}", camear_index);
+        if let Some(source) =
+            open_with_backends(camear_index, &key, backend, backends.as_deref(), capture.as_ref(), &mut memory)
+        {
+            return set_camera_source(&app, Box::new(source), idle_timeout_secs);
+        }
+    } else {
+        let devices = enumerate_video_devices()
+            .map_err(|e| CustomResult::error(Some(format!("枚举摄像头失败: {:?}", e)), None))?;
+        // 备用摄像头没有连接时直接跳过，不按保存的序号打开别的设备
+        for (i, candidate) in candidates.iter().enumerate() {
+            let fallback = if i == 0 {
+                fallback.unwrap_or_default()
+            } else {
+                CameraFallback::Fail
+            };
+            let resolved = match resolve_camera(candidate, &devices, fallback) {
+                Ok(resolved) => resolved,
+                Err(e) => {
+                    warn!("{}", e);
+                    continue;
+                }
+            };
+            info!(
+                "摄像头 {} 换算为序号 {}（{:?}）",
+                candidate.name, resolved.index, resolved.matched_by
+            );
+            // 记录后端用的摄像头标识，优先使用设备路径
+            let key = match &resolved.device {
+                Some(found) => found.device_path.clone().unwrap_or_else(|| found.name.clone()),
+                None => format!("#{}", resolved.index),
+            };
+            // 设置的摄像头使用 capture，备用摄像头各自使用自己的采集参数，没有设置时使用驱动默认值
+            let profile = if i == 0 && device_given {
+                capture.as_ref()
+            } else {
+                captures
+                    .as_ref()
+                    .and_then(|captures| captures.get(&candidate.profile_key()))
+                    .filter(|c| !c.is_empty())
+            };
+            if let Some(source) =
+                open_with_backends(resolved.index, &key, backend, backends.as_deref(), profile, &mut memory)
+            {
+                if i > 0 {
+                    warn!("设置的摄像头无法打开，使用备用摄像头 {}", candidate.name);
+                }
+                return set_camera_source(&app, Box::new(source), idle_timeout_secs);
+            }
+        }
+    }
+    // 全部失败，摄像头没有打开，不再占用租约
+    release_camera_lease(APP_STATE.lock().ok().and_then(|mut app_state| app_state.camera_lease.take()));
+
     // 因发现市面上有人在盗卖本项目，更有甚者改个软件名字，就当成自己软件在卖，多次举报无果。所以从2026年3月1日开始，本项目闭源。
     // 如果你对程序某一块功能感兴趣，可以提交 issues，我看到后会给你提供一些支持。
 
@@ -145,9 +302,189 @@ pub fn stop_camera() -> Result<CustomResult, CustomResult> {
         .lock()
         .map_err(|e| CustomResult::error(Some(format!("获取app状态失败 {}", e)), None))?;
     app_state.camera = None;
+    let lease = app_state.camera_lease.take();
+    drop(app_state);
+    release_camera_lease(lease);
     Ok(CustomResult::success(None, None))
 }
 
+// 释放已经从 APP_STATE 取出的租约，读写租约文件时不持有 APP_STATE
+fn release_camera_lease(lease: Option<Lease>) {
+    if let Some(lease) = lease {
+        if let Err(e) = LeaseManager::new(&ROOT_DIR).release(&lease.id) {
+            warn!("释放摄像头租约失败: {}", e);
+        }
+    }
+}
+
+// 按顺序尝试后端，上次成功的后端排在最前面，记录每个后端的耗时
+// profile: 该摄像头的采集参数，为 None 时使用驱动默认值
+fn open_with_backends(
+    camear_index: i32,
+    camera_key: &str,
+    backend: Option<CameraBackend>,
+    backends: Option<&[CameraBackend]>,
+    profile: Option<&CaptureProfile>,
+    memory: &mut BackendMemory,
+) -> Option<CameraSource> {
+    let order = match backend {
+        Some(backend) => vec![backend],
+        None => backend_order(backends, memory.get(camera_key)),
+    };
+    for backend in order {
+        let start = std::time::Instant::now();
+        match try_open_camera_with_backend(backend, camear_index, profile) {
+            Ok(cam) => {
+                info!(
+                    "后端 {:?} 打开摄像头 {} 成功，耗时 {} ms",
+                    backend,
+                    camear_index,
+                    start.elapsed().as_millis()
+                );
+                if let Err(e) = memory.remember(&ROOT_DIR, camera_key, backend) {
+                    warn!("{}", e);
+                }
+                return Some(CameraSource::new(cam, format!("摄像头 {}（{:?}）", camera_key, backend)));
+            }
+            Err(e) => warn!(
+                "后端 {:?} 打开摄像头 {} 失败，耗时 {} ms: {}",
+                backend,
+                camear_index,
+                start.elapsed().as_millis(),
+                e
+            ),
+        }
+    }
+    None
+}
+
+fn set_camera_source(
+    app: &AppHandle,
+    source: Box<dyn FrameSource>,
+    idle_timeout_secs: u64,
+) -> Result<CustomResult, CustomResult> {
+    let mut app_state = APP_STATE
+        .lock()
+        .map_err(|e| CustomResult::error(Some(format!("获取app状态失败 {}", e)), None))?;
+    let description = source.describe();
+    let clock = IdleClock::new();
+    app_state.camera = Some(OpenCVResource {
+        inner: Box::new(IdleTracked::new(source, clock.clone())),
+    });
+    // 每次打开都换一个代号，之前打开时的检查线程看到代号变化后退出，同一时间只有一个线程在检查
+    let generation = IDLE_WATCH_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    if idle_timeout_secs > 0 {
+        watch_camera_idle(
+            app.clone(),
+            clock,
+            std::time::Duration::from_secs(idle_timeout_secs),
+            generation,
+        );
+    }
+    Ok(CustomResult::success(None, Some(json!({"camera": description}))))
+}
+
+// 当前空闲检查线程的代号，见 set_camera_source
+static IDLE_WATCH_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+// 后台检查摄像头是否空闲，超时后关闭摄像头并通知界面
+// 摄像头已关闭或已经重新打开（代号变化）时退出
+fn watch_camera_idle(
+    app: AppHandle,
+    clock: std::sync::Arc<IdleClock>,
+    timeout: std::time::Duration,
+    generation: u64,
+) {
+    let superseded = move || IDLE_WATCH_GENERATION.load(Ordering::SeqCst) != generation;
+    std::thread::spawn(move || loop {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        if clock.is_closed() || superseded() {
+            return;
+        }
+        if clock.idle_for() < timeout {
+            continue;
+        }
+        let mut app_state = match APP_STATE.lock() {
+            Ok(app_state) => app_state,
+            Err(_) => return,
+        };
+        // 拿到锁之前可能已经关闭、重新打开或者又读取了画面
+        if clock.is_closed() || superseded() {
+            return;
+        }
+        if clock.idle_for() < timeout {
+            continue;
+        }
+        let stopped = IdleStopped {
+            description: app_state
+                .camera
+                .as_ref()
+                .map(|camera| camera.inner.describe())
+                .unwrap_or_default(),
+            idle_secs: clock.idle_for().as_secs(),
+            held_secs: clock.held_for().as_secs(),
+        };
+        app_state.camera = None;
+        let lease = app_state.camera_lease.take();
+        drop(app_state);
+        release_camera_lease(lease);
+        // 使用时长在 IdleTracked 被丢弃时记录
+        warn!("{} 已 {} 秒没有读取画面，自动关闭", stopped.description, stopped.idle_secs);
+        let _ = app.emit(IDLE_STOP_EVENT, stopped);
+        return;
+    });
+}
+
+// 后台定期续租，租约被解锁服务收回时关闭摄像头
+// 打开摄像头失败时 camera 一直为空，超过 OPEN_GRACE_MS 后释放租约
+// 读写租约文件可能要等待锁文件，期间不持有 APP_STATE，不影响读取画面
+fn keep_camera_lease(lease_id: String) {
+    const OPEN_GRACE_MS: u128 = 15000;
+    std::thread::spawn(move || {
+        let manager = LeaseManager::new(&ROOT_DIR);
+        let started = std::time::Instant::now();
+        loop {
+            std::thread::sleep(std::time::Duration::from_millis(DEFAULT_TTL_MS / 3));
+            {
+                let mut app_state = match APP_STATE.lock() {
+                    Ok(app_state) => app_state,
+                    Err(_) => return,
+                };
+                // 已经关闭或重新打开
+                if app_state.camera_lease.as_ref().map(|l| l.id.as_str()) != Some(lease_id.as_str()) {
+                    return;
+                }
+                if app_state.camera.is_none() && started.elapsed().as_millis() > OPEN_GRACE_MS {
+                    app_state.camera_lease = None;
+                    drop(app_state);
+                    let _ = manager.release(&lease_id);
+                    return;
+                }
+            }
+            match manager.renew(&lease_id, DEFAULT_TTL_MS) {
+                Ok(_) => {}
+                Err(e @ LeaseError::Revoked { .. }) => {
+                    warn!("{}，关闭摄像头", e);
+                    if let Ok(mut app_state) = APP_STATE.lock() {
+                        // 续租期间可能已经关闭或重新打开，只关闭这一次打开的摄像头
+                        if app_state.camera_lease.as_ref().map(|l| l.id.as_str()) == Some(lease_id.as_str()) {
+                            app_state.camera = None;
+                            app_state.camera_lease = None;
+                        }
+                    }
+                    let _ = manager.release(&lease_id);
+                    return;
+                }
+                // 租约文件暂时读写失败，下次再续
+                Err(e) => warn!("{}", e),
+            }
+        }
+    });
+}
+
+// 当前会话是否已锁屏，由窗口的 WTS 通知设置
+pub static SESSION_LOCKED: AtomicBool = AtomicBool::new(false);
+
 // 打开指定目录用资源管理器
 #[tauri::command]
 pub fn open_directory(path: String) -> Result<CustomResult, CustomResult> {
@@ -336,31 +673,16 @@ pub fn load_opencv_model() -> Result<(), String> {
         .lock()
         .map_err(|e| format!("获取app状态失败 {}", e))?;
 
+    let resource_dir = ROOT_DIR.join("resources");
     if app_state.detector.is_none() {
-        let resource_path = ROOT_DIR
-            .join("resources")
-            .join("face_detection_yunet_2023mar.onnx");
-
-        // 这个不用检查文件是否存在，不存在opencv会报错
-        let detector = FaceDetectorYN::create(
-            resource_path.to_str().unwrap_or(""),
-            "",
-            Size::new(320, 320), // 初始尺寸，后面会动态更新
-            0.9,
-            0.3,
-            5000,
-            0,
-            0,
-        )
-        .map_err(|e| format!("初始化检测器模型失败: {:?}", e))?;
+        let resource_path = verified_model_path(&resource_dir, ModelRole::Detector).map_err(|e| e.to_string())?;
+        let detector = create_detector_from_file(&resource_path)?;
 
         app_state.detector = Some(OpenCVResource { inner: detector });
     }
 
     if app_state.recognizer.is_none() {
-        let resource_path = ROOT_DIR
-            .join("resources")
-            .join("face_recognition_sface_2021dec.onnx");
+        let resource_path = verified_model_path(&resource_dir, ModelRole::Recognizer).map_err(|e| e.to_string())?;
         let recognizer = FaceRecognizerSF::create(resource_path.to_str().unwrap_or(""), "", 0, 0)
             .map_err(|e| format!("初始化识别器模型失败: {:?}", e))?;
 
@@ -368,9 +690,7 @@ pub fn load_opencv_model() -> Result<(), String> {
     }
 
     if app_state.liveness.is_none() {
-        let resource_path = ROOT_DIR
-            .join("resources")
-            .join("face_liveness.onnx");
+        let resource_path = verified_model_path(&resource_dir, ModelRole::Liveness).map_err(|e| e.to_string())?;
         let liveness = opencv::dnn::read_net_from_onnx(resource_path.to_str().unwrap_or(""))
             .map_err(|e| format!("初始化活体检测模型失败: {:?}", e))?;
 
@@ -398,6 +718,9 @@ pub fn unload_model() -> Result<(), String> {
     if app_state.liveness.is_some() {
         app_state.liveness = None;
     }
+
+    // 缓存的特征由卸载的识别模型提取，重新加载后不能再用
+    app_state.feature_cache.clear();
     Ok(())
 }
 
@@ -459,6 +782,7 @@ fn fix_gbk_encoding(bytes: &[u8]) -> String {
 fn try_open_camera_with_backend(
     backend: CameraBackend,
     camear_index: i32,
+    profile: Option<&CaptureProfile>,
 ) -> Result<VideoCapture, Box<dyn std::error::Error>> {
     let mut cam = VideoCapture::new(camear_index, backend.into())?;
 
@@ -466,6 +790,18 @@ fn try_open_camera_with_backend(
         return Err(format!("后端 {:?} 打开摄像头后状态为未激活", backend).into());
     }
 
+    // 采集参数要在读取第一帧之前设置，开始输出画面后部分驱动不允许修改像素格式
+    if let Some(profile) = profile {
+        let readback = apply_capture_profile(&mut cam, profile)?;
+        info!(
+            "后端 {:?} 采集参数：{}x{} {:.1}fps {}",
+            backend, readback.width, readback.height, readback.fps, readback.fourcc
+        );
+        if !readback.rejected.is_empty() {
+            warn!("摄像头未接受的采集参数：{}", readback.rejected.join("，"));
+        }
+    }
+
     // 激活摄像头
     let mut frame = Mat::default();
     let read_result = cam.read(&mut frame);
@@ -481,6 +817,24 @@ fn try_open_camera_with_backend(
         }
     }
 
+    // 丢弃自动曝光稳定之前的画面，只在该摄像头的采集参数中开启了预热时执行
+    let warmup = match profile.and_then(|p| p.warmup).filter(|w| w.enabled) {
+        Some(warmup) => warmup,
+        None => return Ok(cam),
+    };
+    let stats = warm_up(&mut cam, &warmup).map_err(|e| format!("后端 {:?} 预热失败: {}", backend, e))?;
+    if stats.stabilized {
+        info!(
+            "摄像头预热完成：{} 帧 {} ms，亮度 {:.1}，清晰度 {:.1}",
+            stats.frames, stats.elapsed_ms, stats.brightness, stats.sharpness
+        );
+    } else {
+        warn!(
+            "摄像头预热超时：{} 帧 {} ms，亮度 {:.1}，清晰度 {:.1}",
+            stats.frames, stats.elapsed_ms, stats.brightness, stats.sharpness
+        );
+    }
+
     Ok(cam)
 }
 // 获取windows所有摄像头
@@ -0,0 +1,58 @@
+// 解锁尝试的详细信息，to_json_data 的结果是 unlock_log 表 json_data 列的格式，登录日志按这个格式显示和标注
+// unlock_log 由解锁服务写入，本仓库中的解锁服务只是占位代码，还没有写入这些信息；
+// 目前能看到尝试记录的地方是回放文件的结尾（ReplayOutcome.attempt）和界面录制回放的结果
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    face_policy::PolicyDecision, frame_normalize::Modality, liveness::LivenessVerdict,
+    spoof_heuristics::FusionResult, template_gallery::LearnDecision,
+};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AttemptLog {
+    // 本次尝试与每个面容的相似度 {面容ID: 0~1}，阈值校准从已标注的尝试中读取
+    #[serde(default)]
+    pub scores: BTreeMap<i64, f64>,
+    // 多帧活体投票的结果，未开启活体检测时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub liveness: Option<LivenessVerdict>,
+    // 自适应模板学习的结果，未开启时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub learned: Option<LearnDecision>,
+    // 多人脸策略的决定
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multi_face: Option<PolicyDecision>,
+    // 本次画面的成像方式，只与同一成像方式的模板比对
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modality: Option<Modality>,
+    // 活体模型与翻拍/打印检查融合后的结果，未开启时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spoof: Option<FusionResult>,
+    // 使用的摄像头，切换到备用摄像头时可以看出来
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera: Option<String>,
+    // 用户在登录日志中标注的尝试者，未标注时为空，阈值校准只使用标注过的尝试
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<AttemptLabel>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct AttemptLabel {
+    // 尝试者对应的面容，None 表示不是任何已录入面容的本人
+    pub face_id: Option<i64>,
+}
+
+impl AttemptLog {
+    pub fn to_json_data(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| String::from("{}"))
+    }
+
+    // 旧日志没有 json_data，解析失败时返回空记录
+    pub fn from_json_data(json_data: Option<&str>) -> Self {
+        json_data
+            .and_then(|s| serde_json::from_str(s).ok())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,61 @@
+// 摄像头后端的尝试顺序
+// 依次尝试全部后端可能要好几秒，每个摄像头上次成功的后端记在程序目录下的 camera_backends.json，下次最先尝试
+use std::{collections::BTreeMap, fs, path::Path};
+
+use super::api::CameraBackend;
+
+const MEMORY_FILE: &str = "camera_backends.json";
+
+// 没有设置顺序时的默认顺序
+pub const DEFAULT_ORDER: [CameraBackend; 4] = [
+    CameraBackend::Any,
+    CameraBackend::DShow,
+    CameraBackend::MSMF,
+    CameraBackend::VFW,
+];
+
+// 每个摄像头上次成功的后端 {摄像头: 后端}
+#[derive(Debug, Default)]
+pub struct BackendMemory {
+    entries: BTreeMap<String, CameraBackend>,
+}
+
+impl BackendMemory {
+    // 文件不存在或格式错误时当作没有记录
+    pub fn load(dir: &Path) -> Self {
+        let entries = fs::read_to_string(dir.join(MEMORY_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { entries }
+    }
+
+    pub fn get(&self, camera: &str) -> Option<CameraBackend> {
+        self.entries.get(camera).copied()
+    }
+
+    pub fn remember(&mut self, dir: &Path, camera: &str, backend: CameraBackend) -> Result<(), String> {
+        if self.get(camera) == Some(backend) {
+            return Ok(());
+        }
+        self.entries.insert(camera.to_string(), backend);
+        let json = serde_json::to_string_pretty(&self.entries).map_err(|e| format!("序列化后端记录失败: {}", e))?;
+        fs::write(dir.join(MEMORY_FILE), json).map_err(|e| format!("保存后端记录失败: {}", e))
+    }
+}
+
+// 本次的尝试顺序：上次成功的后端 → 设置的顺序（没有设置时为默认顺序），去掉重复
+pub fn backend_order(configured: Option<&[CameraBackend]>, remembered: Option<CameraBackend>) -> Vec<CameraBackend> {
+    let configured = configured.filter(|c| !c.is_empty()).unwrap_or(&DEFAULT_ORDER);
+    let mut order: Vec<CameraBackend> = Vec::with_capacity(configured.len() + 1);
+    // 上次成功的后端不在设置的顺序里时说明用户已经去掉了它，不再使用
+    if let Some(remembered) = remembered.filter(|b| configured.contains(b)) {
+        order.push(remembered);
+    }
+    for backend in configured {
+        if !order.contains(backend) {
+            order.push(*backend);
+        }
+    }
+    order
+}
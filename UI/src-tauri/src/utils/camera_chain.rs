@@ -0,0 +1,337 @@
+// 多摄像头：按顺序切换的摄像头链
+// 笔记本合上盖子接外接摄像头时，内置摄像头打不开或者拍不到人脸，需要换下一个摄像头
+// 只依赖 frame_source，由调用方负责打开摄像头和检测人脸，可以用合成画面测试切换逻辑
+// 目前只有 face_eval 的 --source 按帧报告人脸；界面的 open_camera 只在摄像头打不开时按顺序换备用摄像头
+use std::time::{Duration, Instant};
+
+use opencv::core::Mat;
+use serde::{Deserialize, Serialize};
+
+use super::frame_source::FrameSource;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChainConfig {
+    // 当前摄像头连续多少帧没有人脸后换下一个
+    pub no_face_frames: u32,
+    // 打不开的摄像头多久之后再尝试
+    pub retry_after_ms: u64,
+}
+
+impl Default for ChainConfig {
+    fn default() -> Self {
+        Self {
+            no_face_frames: 15,
+            retry_after_ms: 30000,
+        }
+    }
+}
+
+// 切换摄像头的原因
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SwitchReason {
+    Unavailable,
+    NoFace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Switch {
+    pub from: usize,
+    pub to: usize,
+    pub reason: SwitchReason,
+}
+
+// 只管序号的切换逻辑
+// 顺序靠前的摄像头优先：每次切换都从头找第一个可用的摄像头，打不开的摄像头过一段时间再试
+pub struct ChainSelector {
+    count: usize,
+    active: usize,
+    no_face: u32,
+    // 每个摄像头最近一次打不开的时间
+    unavailable_since: Vec<Option<Instant>>,
+    // 本轮已经因为没有人脸跳过的摄像头，全部跳过后重新开始一轮
+    skipped: Vec<bool>,
+    config: ChainConfig,
+}
+
+impl ChainSelector {
+    pub fn new(count: usize, config: ChainConfig) -> Self {
+        Self {
+            count,
+            active: 0,
+            no_face: 0,
+            unavailable_since: vec![None; count],
+            skipped: vec![false; count],
+            config,
+        }
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    // 当前摄像头打不开，换到下一个可用的摄像头；全部不可用时返回 None
+    pub fn mark_unavailable(&mut self) -> Option<Switch> {
+        self.unavailable_since[self.active] = Some(Instant::now());
+        self.advance(SwitchReason::Unavailable)
+    }
+
+    // 报告一帧的检测结果，连续多帧没有人脸时换下一个摄像头
+    pub fn report(&mut self, has_face: bool) -> Option<Switch> {
+        if has_face {
+            self.no_face = 0;
+            self.skipped.iter_mut().for_each(|s| *s = false);
+            return None;
+        }
+        self.no_face += 1;
+        if self.no_face < self.config.no_face_frames || self.count < 2 {
+            return None;
+        }
+        self.skipped[self.active] = true;
+        self.advance(SwitchReason::NoFace)
+    }
+
+    fn available(&self, index: usize) -> bool {
+        self.unavailable_since[index]
+            .is_none_or(|since| since.elapsed() >= Duration::from_millis(self.config.retry_after_ms))
+    }
+
+    fn advance(&mut self, reason: SwitchReason) -> Option<Switch> {
+        let from = self.active;
+        self.no_face = 0;
+        if (0..self.count).all(|i| self.skipped[i] || !self.available(i)) {
+            // 所有摄像头都没有拍到人脸，重新从第一个开始
+            self.skipped.iter_mut().for_each(|s| *s = false);
+        }
+        let to = (0..self.count)
+            .find(|i| *i != from && !self.skipped[*i] && self.available(*i))
+            .or_else(|| Some(from).filter(|f| self.available(*f)))?;
+        self.active = to;
+        Some(Switch { from, to, reason })
+    }
+}
+
+// 摄像头链：按 ChainSelector 的选择打开并读取摄像头
+pub struct CameraChain {
+    labels: Vec<String>,
+    sources: Vec<Option<Box<dyn FrameSource>>>,
+    selector: ChainSelector,
+}
+
+impl CameraChain {
+    pub fn new(labels: Vec<String>, config: ChainConfig) -> Self {
+        let count = labels.len();
+        Self {
+            labels,
+            sources: (0..count).map(|_| None).collect(),
+            selector: ChainSelector::new(count, config),
+        }
+    }
+
+    pub fn active(&self) -> usize {
+        self.selector.active()
+    }
+
+    pub fn label(&self, index: usize) -> &str {
+        &self.labels[index]
+    }
+
+    // 读取当前摄像头的一帧，打不开或读不到画面时换下一个
+    // open: 按序号打开摄像头，摄像头切换后之前的摄像头会被关闭
+    // 返回读到画面的摄像头序号和发生的切换
+    pub fn read(
+        &mut self,
+        frame: &mut Mat,
+        mut open: impl FnMut(usize) -> Result<Box<dyn FrameSource>, String>,
+    ) -> Result<(usize, Vec<Switch>), String> {
+        let mut switches = Vec::new();
+        for _ in 0..=self.labels.len() {
+            let index = self.selector.active();
+            if self.sources[index].is_none() {
+                match open(index) {
+                    Ok(source) => self.sources[index] = Some(source),
+                    Err(_) => {
+                        match self.selector.mark_unavailable() {
+                            Some(switch) => switches.push(switch),
+                            None => break,
+                        }
+                        continue;
+                    }
+                }
+            }
+            let ok = self.sources[index]
+                .as_mut()
+                .map(|source| source.read(frame).unwrap_or(false))
+                .unwrap_or(false);
+            if ok {
+                self.close_others(index);
+                return Ok((index, switches));
+            }
+            self.sources[index] = None;
+            match self.selector.mark_unavailable() {
+                Some(switch) => switches.push(switch),
+                None => break,
+            }
+        }
+        Err(String::from("所有摄像头都无法使用"))
+    }
+
+    // 当前摄像头距离下一帧还要等多久，见 FrameSource::next_frame_in
+    pub fn next_frame_in(&mut self) -> Option<Duration> {
+        self.sources[self.selector.active()].as_mut()?.next_frame_in()
+    }
+
+    // 报告当前帧是否检测到人脸
+    pub fn report(&mut self, has_face: bool) -> Option<Switch> {
+        self.selector.report(has_face)
+    }
+
+    fn close_others(&mut self, keep: usize) {
+        for (i, source) in self.sources.iter_mut().enumerate() {
+            if i != keep {
+                *source = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::frame_source::open_source_uri;
+    use super::*;
+
+    fn selector(count: usize, no_face_frames: u32) -> ChainSelector {
+        ChainSelector::new(
+            count,
+            ChainConfig {
+                no_face_frames,
+                retry_after_ms: 60000,
+            },
+        )
+    }
+
+    // 序号在 broken 中的摄像头打不开，其余的用合成画面代替
+    fn opener(broken: &'static [usize]) -> impl FnMut(usize) -> Result<Box<dyn FrameSource>, String> {
+        move |index| {
+            if broken.contains(&index) {
+                Err(format!("摄像头 {} 打不开", index))
+            } else {
+                open_source_uri("synthetic://64x48?pattern=gray")
+            }
+        }
+    }
+
+    #[test]
+    fn switches_after_consecutive_frames_without_face() {
+        let mut selector = selector(3, 3);
+        assert_eq!(selector.report(false), None);
+        assert_eq!(selector.report(false), None);
+        // 中间有一帧有人脸时重新计数
+        assert_eq!(selector.report(true), None);
+        assert_eq!(selector.report(false), None);
+        assert_eq!(selector.report(false), None);
+        assert_eq!(
+            selector.report(false),
+            Some(Switch {
+                from: 0,
+                to: 1,
+                reason: SwitchReason::NoFace
+            })
+        );
+        assert_eq!(selector.active(), 1);
+    }
+
+    #[test]
+    fn single_camera_never_switches_for_no_face() {
+        let mut selector = selector(1, 1);
+        for _ in 0..5 {
+            assert_eq!(selector.report(false), None);
+        }
+        assert_eq!(selector.active(), 0);
+    }
+
+    #[test]
+    fn starts_over_when_every_camera_had_no_face() {
+        let mut selector = selector(2, 1);
+        assert_eq!(selector.report(false).map(|s| s.to), Some(1));
+        // 两个摄像头都没有拍到人脸，回到第一个
+        assert_eq!(selector.report(false).map(|s| s.to), Some(0));
+    }
+
+    #[test]
+    fn skips_unavailable_cameras_until_retry() {
+        let mut selector = selector(3, 1);
+        assert_eq!(
+            selector.mark_unavailable(),
+            Some(Switch {
+                from: 0,
+                to: 1,
+                reason: SwitchReason::Unavailable
+            })
+        );
+        // 摄像头 0 在重试时间内不会被选中
+        assert_eq!(selector.report(false).map(|s| s.to), Some(2));
+        assert_eq!(selector.mark_unavailable().map(|s| s.to), Some(1));
+        assert_eq!(selector.mark_unavailable(), None);
+    }
+
+    #[test]
+    fn unavailable_camera_is_retried_after_timeout() {
+        let mut selector = ChainSelector::new(
+            2,
+            ChainConfig {
+                no_face_frames: 1,
+                retry_after_ms: 0,
+            },
+        );
+        assert_eq!(selector.mark_unavailable().map(|s| s.to), Some(1));
+        assert_eq!(selector.report(false).map(|s| s.to), Some(0));
+    }
+
+    #[test]
+    fn chain_reads_from_first_camera_that_opens() {
+        let mut chain = CameraChain::new(
+            vec![String::from("内置"), String::from("外接")],
+            ChainConfig::default(),
+        );
+        let mut frame = Mat::default();
+        let (index, switches) = chain.read(&mut frame, opener(&[0])).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(chain.label(index), "外接");
+        assert_eq!(
+            switches,
+            vec![Switch {
+                from: 0,
+                to: 1,
+                reason: SwitchReason::Unavailable
+            }]
+        );
+        assert!(!frame.empty());
+    }
+
+    #[test]
+    fn chain_moves_on_after_frames_without_face() {
+        let config = ChainConfig {
+            no_face_frames: 2,
+            retry_after_ms: 60000,
+        };
+        let mut chain = CameraChain::new(vec![String::from("a"), String::from("b")], config);
+        let mut frame = Mat::default();
+        assert_eq!(chain.read(&mut frame, opener(&[])).unwrap().0, 0);
+        assert_eq!(chain.report(false), None);
+        assert_eq!(chain.report(false).map(|s| s.reason), Some(SwitchReason::NoFace));
+        let (index, switches) = chain.read(&mut frame, opener(&[])).unwrap();
+        assert_eq!(index, 1);
+        assert!(switches.is_empty());
+        assert_eq!(chain.active(), 1);
+    }
+
+    #[test]
+    fn chain_fails_when_no_camera_opens() {
+        let mut chain = CameraChain::new(vec![String::from("a"), String::from("b")], ChainConfig::default());
+        let mut frame = Mat::default();
+        assert!(chain.read(&mut frame, opener(&[0, 1])).is_err());
+    }
+}
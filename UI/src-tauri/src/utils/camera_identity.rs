@@ -0,0 +1,343 @@
+// 摄像头的稳定标识
+// 摄像头序号会随 USB 重新插拔、安装虚拟摄像头而变化，设置中保存 设备名称 + 设备路径，打开时再换算成当前的序号
+// 设备按 DirectShow 的枚举顺序排列，与 OpenCV 的摄像头序号一致
+use serde::{Deserialize, Serialize};
+use windows::{
+    core::{w, BSTR, PCWSTR},
+    Win32::{
+        Media::{
+            DirectShow::ICreateDevEnum,
+            MediaFoundation::{CLSID_SystemDeviceEnum, CLSID_VideoInputDeviceCategory},
+        },
+        System::{
+            Com::{
+                CoCreateInstance, CoInitializeEx, CoUninitialize, IEnumMoniker, IMoniker,
+                StructuredStorage::IPropertyBag, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED,
+            },
+            Variant::{VariantClear, VARIANT},
+        },
+    },
+};
+
+// 当前连接的视频设备
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoDevice {
+    pub name: String,
+    // 设备路径（包含 USB 的 VID/PID 和实例 ID），部分虚拟摄像头没有
+    pub device_path: Option<String>,
+    pub index: i32,
+}
+
+// 设置中保存的摄像头
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CameraDescriptor {
+    pub name: String,
+    pub device_path: Option<String>,
+    // 保存时的序号，同名设备有多个时用来区分，旧版本只保存了序号
+    pub index: Option<i32>,
+}
+
+impl CameraDescriptor {
+    // 设置中的 cameraDevice 为 JSON；旧版本的 camera 只保存了序号
+    pub fn from_options(get: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        get("cameraDevice")
+            .and_then(|v| serde_json::from_str::<CameraDescriptor>(&v).ok())
+            .or_else(|| {
+                get("camera")
+                    .and_then(|v| v.trim().parse::<i32>().ok())
+                    .filter(|index| *index >= 0)
+                    .map(|index| CameraDescriptor {
+                        index: Some(index),
+                        ..CameraDescriptor::default()
+                    })
+            })
+    }
+
+    // cameraCapture、cameraPreprocess 中的键，与界面的 cameraProfileKey 一致
+    // 优先使用设备路径，其次名称，都没有时才用序号
+    pub fn profile_key(&self) -> String {
+        match self.device_path.as_deref().filter(|p| !p.is_empty()) {
+            Some(path) => path.to_string(),
+            None if !self.name.is_empty() => self.name.clone(),
+            None => format!("#{}", self.index.unwrap_or(0)),
+        }
+    }
+}
+
+// 保存的摄像头找不到时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CameraFallback {
+    // 使用保存时的序号，与旧版本的行为相同
+    #[default]
+    SavedIndex,
+    // 使用第一个摄像头
+    FirstAvailable,
+    // 报错，不使用其他摄像头，避免解锁时换成了虚拟摄像头
+    Fail,
+}
+
+impl CameraFallback {
+    pub fn from_options(value: Option<&str>) -> Self {
+        match value {
+            Some("firstAvailable") => CameraFallback::FirstAvailable,
+            Some("fail") => CameraFallback::Fail,
+            _ => CameraFallback::SavedIndex,
+        }
+    }
+}
+
+// 换算的依据
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchKind {
+    DevicePath,
+    Name,
+    SavedIndex,
+    FirstAvailable,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedCamera {
+    pub index: i32,
+    pub matched_by: MatchKind,
+    // 换算到的设备，按保存的序号打开且该序号没有设备时为 None
+    pub device: Option<VideoDevice>,
+}
+
+// 把保存的摄像头换算成当前的序号
+// 顺序：设备路径完全一致 → 名称一致（同名时优先保存时的序号）→ 按 fallback 处理
+pub fn resolve_camera(
+    descriptor: &CameraDescriptor,
+    devices: &[VideoDevice],
+    fallback: CameraFallback,
+) -> Result<ResolvedCamera, String> {
+    let found = |device: &VideoDevice, matched_by: MatchKind| ResolvedCamera {
+        index: device.index,
+        matched_by,
+        device: Some(device.clone()),
+    };
+
+    if let Some(path) = descriptor.device_path.as_deref().filter(|p| !p.is_empty()) {
+        if let Some(device) = devices
+            .iter()
+            .find(|d| d.device_path.as_deref().is_some_and(|p| p.eq_ignore_ascii_case(path)))
+        {
+            return Ok(found(device, MatchKind::DevicePath));
+        }
+    }
+
+    if !descriptor.name.is_empty() {
+        let same_name: Vec<&VideoDevice> = devices.iter().filter(|d| d.name == descriptor.name).collect();
+        let device = same_name
+            .iter()
+            .find(|d| Some(d.index) == descriptor.index)
+            .or_else(|| same_name.first());
+        if let Some(device) = device {
+            return Ok(found(device, MatchKind::Name));
+        }
+    }
+
+    match fallback {
+        CameraFallback::SavedIndex => {
+            let index = descriptor
+                .index
+                .ok_or_else(|| format!("摄像头 {} 未连接", describe(descriptor)))?;
+            Ok(ResolvedCamera {
+                index,
+                matched_by: MatchKind::SavedIndex,
+                device: devices.iter().find(|d| d.index == index).cloned(),
+            })
+        }
+        CameraFallback::FirstAvailable => devices
+            .first()
+            .map(|device| found(device, MatchKind::FirstAvailable))
+            .ok_or_else(|| String::from("没有可用的摄像头")),
+        CameraFallback::Fail => Err(format!("摄像头 {} 未连接", describe(descriptor))),
+    }
+}
+
+fn describe(descriptor: &CameraDescriptor) -> String {
+    match (descriptor.name.is_empty(), descriptor.index) {
+        (false, _) => descriptor.name.clone(),
+        (true, Some(index)) => format!("#{}", index),
+        (true, None) => String::from("（未设置）"),
+    }
+}
+
+// 枚举当前连接的视频设备，读取名称和设备路径
+pub fn enumerate_video_devices() -> windows::core::Result<Vec<VideoDevice>> {
+    unsafe {
+        CoInitializeEx(None, COINIT_APARTMENTTHREADED).ok()?;
+        let result = enumerate_with_com();
+        CoUninitialize();
+        result
+    }
+}
+
+unsafe fn enumerate_with_com() -> windows::core::Result<Vec<VideoDevice>> {
+    let dev_enum: ICreateDevEnum = CoCreateInstance(&CLSID_SystemDeviceEnum, None, CLSCTX_INPROC_SERVER)?;
+    let mut enum_moniker: Option<IEnumMoniker> = None;
+    dev_enum.CreateClassEnumerator(&CLSID_VideoInputDeviceCategory, &mut enum_moniker, 0)?;
+    // 没有任何视频设备时返回 S_FALSE，枚举器为空
+    let enum_moniker = match enum_moniker {
+        Some(enum_moniker) => enum_moniker,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut devices = Vec::new();
+    // 读取属性失败的设备也占一个序号
+    let mut index = 0;
+    loop {
+        let mut monikers: [Option<IMoniker>; 1] = [None];
+        let mut fetched = 0u32;
+        if enum_moniker.Next(&mut monikers, Some(&mut fetched)).is_err() || fetched == 0 {
+            break;
+        }
+        let moniker = match monikers[0].take() {
+            Some(moniker) => moniker,
+            None => break,
+        };
+        let current_index = index;
+        index += 1;
+        let bag: IPropertyBag = match moniker.BindToStorage(None, None) {
+            Ok(bag) => bag,
+            Err(_) => continue,
+        };
+        devices.push(VideoDevice {
+            name: read_property(&bag, w!("FriendlyName")).unwrap_or_default(),
+            device_path: read_property(&bag, w!("DevicePath")),
+            index: current_index,
+        });
+    }
+    Ok(devices)
+}
+
+unsafe fn read_property(bag: &IPropertyBag, name: PCWSTR) -> Option<String> {
+    let mut variant = VARIANT::default();
+    let value = bag
+        .Read(name, &mut variant, None)
+        .ok()
+        .and_then(|_| BSTR::try_from(&variant).ok())
+        .map(|s| s.to_string())
+        .filter(|s| !s.is_empty());
+    let _ = VariantClear(&mut variant);
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    const BUILTIN: &str =
+        r"\\?\usb#vid_04f2&pid_b6d9&mi_00#6&1a2b3c&0&0000#{65e8773d-8f56-11d0-a3b9-00a0c9223196}\global";
+    const USB_LEFT: &str =
+        r"\\?\usb#vid_046d&pid_085c&mi_00#7&aaaa&0&0000#{65e8773d-8f56-11d0-a3b9-00a0c9223196}\global";
+    const USB_RIGHT: &str =
+        r"\\?\usb#vid_046d&pid_085c&mi_00#7&bbbb&0&0000#{65e8773d-8f56-11d0-a3b9-00a0c9223196}\global";
+
+    fn device(name: &str, device_path: Option<&str>, index: i32) -> VideoDevice {
+        VideoDevice {
+            name: name.to_string(),
+            device_path: device_path.map(str::to_string),
+            index,
+        }
+    }
+
+    // 内置摄像头、没有设备路径的虚拟摄像头、两个同型号的 USB 摄像头
+    fn devices() -> Vec<VideoDevice> {
+        vec![
+            device("Integrated Camera", Some(BUILTIN), 0),
+            device("OBS Virtual Camera", None, 1),
+            device("C922 Pro Stream Webcam", Some(USB_LEFT), 2),
+            device("C922 Pro Stream Webcam", Some(USB_RIGHT), 3),
+        ]
+    }
+
+    fn descriptor(name: &str, device_path: Option<&str>, index: Option<i32>) -> CameraDescriptor {
+        CameraDescriptor {
+            name: name.to_string(),
+            device_path: device_path.map(str::to_string),
+            index,
+        }
+    }
+
+    #[test]
+    fn device_path_wins_after_index_changes() {
+        // 保存时是 0 号，重新插拔后排到了 3 号，设备路径大小写不同也能找到
+        let saved = descriptor("C922 Pro Stream Webcam", Some(&USB_RIGHT.to_uppercase()), Some(0));
+        let resolved = resolve_camera(&saved, &devices(), CameraFallback::Fail).unwrap();
+        assert_eq!(resolved.index, 3);
+        assert_eq!(resolved.matched_by, MatchKind::DevicePath);
+        assert_eq!(resolved.device.unwrap().device_path.as_deref(), Some(USB_RIGHT));
+    }
+
+    #[test]
+    fn name_is_used_when_device_path_changed() {
+        // 换了 USB 口后设备路径变化，同名设备中优先保存时的序号
+        let saved = descriptor("C922 Pro Stream Webcam", Some("changed"), Some(3));
+        let resolved = resolve_camera(&saved, &devices(), CameraFallback::Fail).unwrap();
+        assert_eq!((resolved.index, resolved.matched_by), (3, MatchKind::Name));
+
+        // 保存时的序号已经不是这个名称，取第一个同名设备
+        let saved = descriptor("C922 Pro Stream Webcam", None, Some(0));
+        let resolved = resolve_camera(&saved, &devices(), CameraFallback::Fail).unwrap();
+        assert_eq!((resolved.index, resolved.matched_by), (2, MatchKind::Name));
+
+        // 没有设备路径的虚拟摄像头只能按名称找
+        let saved = descriptor("OBS Virtual Camera", None, None);
+        let resolved = resolve_camera(&saved, &devices(), CameraFallback::Fail).unwrap();
+        assert_eq!((resolved.index, resolved.matched_by), (1, MatchKind::Name));
+    }
+
+    #[test]
+    fn unplugged_camera_follows_fallback() {
+        let saved = descriptor("Surface Camera Front", Some("gone"), Some(1));
+
+        let resolved = resolve_camera(&saved, &devices(), CameraFallback::SavedIndex).unwrap();
+        assert_eq!((resolved.index, resolved.matched_by), (1, MatchKind::SavedIndex));
+        assert_eq!(resolved.device.unwrap().name, "OBS Virtual Camera");
+
+        let resolved = resolve_camera(&saved, &devices(), CameraFallback::FirstAvailable).unwrap();
+        assert_eq!((resolved.index, resolved.matched_by), (0, MatchKind::FirstAvailable));
+
+        let error = resolve_camera(&saved, &devices(), CameraFallback::Fail).unwrap_err();
+        assert!(error.contains("Surface Camera Front"), "{}", error);
+    }
+
+    #[test]
+    fn unplugged_camera_without_devices() {
+        // 保存的序号没有设备时仍按序号打开，由打开摄像头时报错
+        let saved = descriptor("Integrated Camera", Some(BUILTIN), Some(0));
+        let resolved = resolve_camera(&saved, &[], CameraFallback::SavedIndex).unwrap();
+        assert_eq!((resolved.index, resolved.matched_by), (0, MatchKind::SavedIndex));
+        assert!(resolved.device.is_none());
+
+        assert!(resolve_camera(&saved, &[], CameraFallback::FirstAvailable).is_err());
+        // 没有保存序号时无法按序号打开
+        let saved = descriptor("Integrated Camera", None, None);
+        assert!(resolve_camera(&saved, &devices()[1..], CameraFallback::SavedIndex).is_err());
+    }
+
+    #[test]
+    fn legacy_index_setting_resolves_by_index() {
+        let options = HashMap::from([("camera", "2")]);
+        let saved = CameraDescriptor::from_options(|key| options.get(key).map(|v| v.to_string())).unwrap();
+        assert_eq!(saved.profile_key(), "#2");
+        let resolved = resolve_camera(&saved, &devices(), CameraFallback::SavedIndex).unwrap();
+        assert_eq!((resolved.index, resolved.matched_by), (2, MatchKind::SavedIndex));
+
+        // 新设置优先于旧的序号
+        let device = serde_json::to_string(&descriptor("Integrated Camera", Some(BUILTIN), Some(0))).unwrap();
+        let options = HashMap::from([("camera", "2".to_string()), ("cameraDevice", device)]);
+        let saved = CameraDescriptor::from_options(|key| options.get(key).cloned()).unwrap();
+        assert_eq!(saved.profile_key(), BUILTIN);
+        let empty = HashMap::<&str, String>::new();
+        assert!(CameraDescriptor::from_options(|key| empty.get(key).cloned()).is_none());
+    }
+}
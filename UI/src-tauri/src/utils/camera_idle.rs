@@ -0,0 +1,118 @@
+// 摄像头空闲自动关闭：忘记关闭的预览会让摄像头灯一直亮着
+// 打开摄像头时用 IdleTracked 包装画面来源，每次读取画面都记录时间；超过设置的秒数没有读取时由 open_camera 的后台线程关闭摄像头
+// 来源被替换或关闭（IdleTracked 被丢弃）时记录摄像头的使用时长
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use opencv::{core::Mat, videoio::VideoCapture};
+use serde::Serialize;
+use tauri_plugin_log::log::info;
+
+use super::frame_source::{FrameSource, SourceKind};
+
+// 空闲关闭时发给界面的事件
+pub const IDLE_STOP_EVENT: &str = "camera-idle-stopped";
+
+// 设置中没有 cameraIdleTimeout 时的默认值，0 表示不自动关闭
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 60;
+
+pub struct IdleClock {
+    opened_at: Instant,
+    last_read: Mutex<Instant>,
+    closed: AtomicBool,
+}
+
+impl IdleClock {
+    pub fn new() -> Arc<Self> {
+        let now = Instant::now();
+        Arc::new(Self {
+            opened_at: now,
+            last_read: Mutex::new(now),
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    pub fn touch(&self) {
+        if let Ok(mut last_read) = self.last_read.lock() {
+            *last_read = Instant::now();
+        }
+    }
+
+    // 距离最后一次读取画面的时间
+    pub fn idle_for(&self) -> Duration {
+        self.last_read
+            .lock()
+            .map(|last_read| last_read.elapsed())
+            .unwrap_or_default()
+    }
+
+    // 打开到现在的时间
+    pub fn held_for(&self) -> Duration {
+        self.opened_at.elapsed()
+    }
+
+    // 画面来源已经关闭，后台线程不再需要检查
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+}
+
+// 记录读取时间的画面来源
+pub struct IdleTracked {
+    inner: Box<dyn FrameSource>,
+    clock: Arc<IdleClock>,
+}
+
+impl IdleTracked {
+    pub fn new(inner: Box<dyn FrameSource>, clock: Arc<IdleClock>) -> Self {
+        Self { inner, clock }
+    }
+}
+
+impl FrameSource for IdleTracked {
+    fn read(&mut self, frame: &mut Mat) -> opencv::Result<bool> {
+        self.clock.touch();
+        self.inner.read(frame)
+    }
+
+    fn kind(&self) -> SourceKind {
+        self.inner.kind()
+    }
+
+    fn describe(&self) -> String {
+        self.inner.describe()
+    }
+
+    fn capture_mut(&mut self) -> Option<&mut VideoCapture> {
+        self.inner.capture_mut()
+    }
+
+    fn next_frame_in(&mut self) -> Option<Duration> {
+        self.inner.next_frame_in()
+    }
+}
+
+impl Drop for IdleTracked {
+    fn drop(&mut self) {
+        self.clock.closed.store(true, Ordering::SeqCst);
+        info!(
+            "{} 已关闭，使用了 {} 秒",
+            self.inner.describe(),
+            self.clock.held_for().as_secs()
+        );
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdleStopped {
+    pub description: String,
+    // 关闭前的空闲时间和摄像头的使用时长
+    pub idle_secs: u64,
+    pub held_secs: u64,
+}
@@ -0,0 +1,348 @@
+// 摄像头租约：界面的预览、录入和后台解锁服务都要用摄像头，由这里决定谁可以用
+// 界面和解锁服务是两个进程，租约保存在程序目录下的 camera.lease，修改前先创建 camera.lease.lock 加锁
+// 规则：
+//   共享租约之间可以同时持有，独占租约与其他任何租约冲突
+//   冲突时优先级高的一方收回优先级低的租约，锁屏解锁服务的优先级最高
+//   被收回的一方在续租时得知，需要立即关闭摄像头；超过 REVOKE_GRACE_MS 没有释放的视为已经退出
+//   持有方要在 ttl 内续租，进程崩溃后租约到期自动失效
+// 只依赖标准库和 serde，解锁服务接入时可以直接引用这个文件；目前只有界面申请租约，见 Unlock/README.md
+use std::{
+    fmt,
+    fs::{self, OpenOptions},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+const LEASE_FILE: &str = "camera.lease";
+const LOCK_FILE: &str = "camera.lease.lock";
+// 锁文件超过这个时间还在，说明加锁的进程已经退出
+const STALE_LOCK_MS: u64 = 2000;
+// 租约被收回后等待持有方释放的时间
+pub const REVOKE_GRACE_MS: u64 = 1500;
+// 默认租期，持有方每隔 ttl / 3 续租一次
+pub const DEFAULT_TTL_MS: u64 = 5000;
+// 租约编号的进程内序号，同一毫秒内申请多个租约时编号也不重复
+static NEXT_LEASE_SEQ: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LeaseMode {
+    Exclusive,
+    Shared,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LeasePriority {
+    // 设置页面的预览
+    Preview,
+    // 录入面容
+    Enrollment,
+    // 锁屏时的解锁服务
+    LockScreen,
+}
+
+impl LeasePriority {
+    // 界面进程申请时使用的优先级，LockScreen 只留给锁屏时的解锁服务，界面传入时降为录入
+    pub fn for_ui(self) -> Self {
+        self.min(LeasePriority::Enrollment)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Lease {
+    pub id: String,
+    // 显示给用户的持有方名称
+    pub holder: String,
+    pub pid: u32,
+    pub mode: LeaseMode,
+    pub priority: LeasePriority,
+    pub acquired_at: u64,
+    pub expires_at: u64,
+    // 被哪个持有方收回，以及收回的时间
+    #[serde(default)]
+    pub revoked_by: Option<String>,
+    #[serde(default)]
+    pub revoked_at: Option<u64>,
+}
+
+impl Lease {
+    fn conflicts_with(&self, mode: LeaseMode) -> bool {
+        self.mode == LeaseMode::Exclusive || mode == LeaseMode::Exclusive
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LeaseRequest {
+    pub holder: String,
+    pub mode: LeaseMode,
+    pub priority: LeasePriority,
+    pub ttl_ms: u64,
+    // 摄像头被占用时最多等待的时间
+    pub wait_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum LeaseError {
+    // 摄像头被优先级不低于自己的一方占用
+    Busy { holder: String, pid: u32, mode: LeaseMode },
+    // 租约已经被收回或已过期，需要关闭摄像头
+    Revoked { by: Option<String> },
+    Io { reason: String },
+}
+
+impl fmt::Display for LeaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LeaseError::Busy { holder, pid, .. } => write!(f, "摄像头正忙，正在被 {}（进程 {}）使用", holder, pid),
+            LeaseError::Revoked { by: Some(by) } => write!(f, "摄像头已被 {} 收回", by),
+            LeaseError::Revoked { by: None } => write!(f, "摄像头租约已过期"),
+            LeaseError::Io { reason } => write!(f, "读写摄像头租约失败: {}", reason),
+        }
+    }
+}
+
+pub struct LeaseManager {
+    dir: PathBuf,
+}
+
+impl LeaseManager {
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.to_path_buf() }
+    }
+
+    // 申请租约，冲突的低优先级租约会被收回，等待其释放；等待超时返回 Busy
+    pub fn acquire(&self, request: &LeaseRequest) -> Result<Lease, LeaseError> {
+        let deadline = now_ms() + request.wait_ms;
+        let pid = std::process::id();
+        loop {
+            let outcome = self.with_table(|leases| {
+                // 同一进程的同一持有方重复申请，替换旧的租约
+                leases.retain(|l| !(l.pid == pid && l.holder == request.holder));
+
+                let now = now_ms();
+                let mut blocker = None;
+                for lease in leases.iter_mut().filter(|l| l.conflicts_with(request.mode)) {
+                    if lease.priority < request.priority && lease.revoked_by.is_none() {
+                        lease.revoked_by = Some(request.holder.clone());
+                        lease.revoked_at = Some(now);
+                    }
+                    blocker.get_or_insert_with(|| lease.clone());
+                }
+                if let Some(blocker) = blocker {
+                    return Err(blocker);
+                }
+
+                let lease = Lease {
+                    id: format!("{}-{}-{}", pid, now, NEXT_LEASE_SEQ.fetch_add(1, Ordering::Relaxed)),
+                    holder: request.holder.clone(),
+                    pid,
+                    mode: request.mode,
+                    priority: request.priority,
+                    acquired_at: now,
+                    expires_at: now + request.ttl_ms,
+                    revoked_by: None,
+                    revoked_at: None,
+                };
+                leases.push(lease.clone());
+                Ok(lease)
+            })?;
+
+            match outcome {
+                Ok(lease) => return Ok(lease),
+                Err(blocker) if now_ms() >= deadline => {
+                    return Err(LeaseError::Busy {
+                        holder: blocker.holder,
+                        pid: blocker.pid,
+                        mode: blocker.mode,
+                    });
+                }
+                Err(_) => thread::sleep(Duration::from_millis(100)),
+            }
+        }
+    }
+
+    // 续租，租约被收回或已过期时返回 Revoked
+    pub fn renew(&self, id: &str, ttl_ms: u64) -> Result<Lease, LeaseError> {
+        self.with_table(|leases| match leases.iter_mut().find(|l| l.id == id) {
+            None => Err(LeaseError::Revoked { by: None }),
+            Some(lease) if lease.revoked_by.is_some() => Err(LeaseError::Revoked {
+                by: lease.revoked_by.clone(),
+            }),
+            Some(lease) => {
+                lease.expires_at = now_ms() + ttl_ms;
+                Ok(lease.clone())
+            }
+        })?
+    }
+
+    pub fn release(&self, id: &str) -> Result<(), LeaseError> {
+        self.with_table(|leases| leases.retain(|l| l.id != id))
+    }
+
+    // 当前有效的租约
+    pub fn holders(&self) -> Result<Vec<Lease>, LeaseError> {
+        self.with_table(|leases| leases.clone())
+    }
+
+    // 加锁后读取租约表，清理过期和超时未释放的租约，执行 f 后写回
+    fn with_table<T>(&self, f: impl FnOnce(&mut Vec<Lease>) -> T) -> Result<T, LeaseError> {
+        let _lock = FileLock::acquire(&self.dir.join(LOCK_FILE))?;
+        let path = self.dir.join(LEASE_FILE);
+        let mut leases: Vec<Lease> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+
+        let now = now_ms();
+        leases.retain(|l| l.expires_at > now && l.revoked_at.is_none_or(|at| now < at + REVOKE_GRACE_MS));
+        let result = f(&mut leases);
+
+        let json = serde_json::to_string_pretty(&leases).map_err(|e| LeaseError::Io { reason: e.to_string() })?;
+        fs::write(&path, json).map_err(|e| LeaseError::Io {
+            reason: format!("写入 {} 失败: {}", path.display(), e),
+        })?;
+        Ok(result)
+    }
+}
+
+// 用 create_new 创建锁文件实现的跨进程锁，离开作用域时删除
+struct FileLock {
+    path: PathBuf,
+}
+
+impl FileLock {
+    fn acquire(path: &Path) -> Result<Self, LeaseError> {
+        let start = now_ms();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(_) => return Ok(Self { path: path.to_path_buf() }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let stale = fs::metadata(path)
+                        .and_then(|m| m.modified())
+                        .ok()
+                        .and_then(|t| t.elapsed().ok())
+                        .is_some_and(|age| age.as_millis() as u64 > STALE_LOCK_MS);
+                    if stale {
+                        let _ = fs::remove_file(path);
+                    } else if now_ms() - start > STALE_LOCK_MS * 2 {
+                        return Err(LeaseError::Io {
+                            reason: format!("等待 {} 超时", path.display()),
+                        });
+                    } else {
+                        thread::sleep(Duration::from_millis(10));
+                    }
+                }
+                Err(e) => {
+                    return Err(LeaseError::Io {
+                        reason: format!("创建 {} 失败: {}", path.display(), e),
+                    })
+                }
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 每个测试使用单独的目录，测试并行执行时互不影响
+    fn manager(name: &str) -> LeaseManager {
+        let dir = std::env::temp_dir().join(format!("camera_lease_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        LeaseManager::new(&dir)
+    }
+
+    fn request(holder: &str, priority: LeasePriority, wait_ms: u64) -> LeaseRequest {
+        LeaseRequest {
+            holder: holder.to_string(),
+            mode: LeaseMode::Exclusive,
+            priority,
+            ttl_ms: DEFAULT_TTL_MS,
+            wait_ms,
+        }
+    }
+
+    #[test]
+    fn lock_screen_revokes_ui_lease() {
+        let manager = manager("revoke");
+        let preview = manager.acquire(&request("界面", LeasePriority::Preview, 0)).unwrap();
+
+        // 界面在宽限期内没有释放，解锁服务等待超时
+        let busy = manager.acquire(&request("解锁服务", LeasePriority::LockScreen, 0));
+        assert!(matches!(busy, Err(LeaseError::Busy { .. })));
+        // 界面续租时得知已被收回，释放后解锁服务可以拿到租约
+        assert!(matches!(
+            manager.renew(&preview.id, DEFAULT_TTL_MS),
+            Err(LeaseError::Revoked { by: Some(_) })
+        ));
+        manager.release(&preview.id).unwrap();
+        let lock_screen = manager.acquire(&request("解锁服务", LeasePriority::LockScreen, 0)).unwrap();
+        assert_eq!(lock_screen.priority, LeasePriority::LockScreen);
+    }
+
+    #[test]
+    fn ui_cannot_revoke_lock_screen() {
+        let manager = manager("busy");
+        let lock_screen = manager
+            .acquire(&request("解锁服务", LeasePriority::LockScreen, 0))
+            .unwrap();
+
+        // 界面传入 LockScreen 时按录入处理，不能收回解锁服务的租约
+        let busy = manager.acquire(&request("界面", LeasePriority::LockScreen.for_ui(), 0));
+        assert!(matches!(busy, Err(LeaseError::Busy { .. })));
+        assert!(manager.renew(&lock_screen.id, DEFAULT_TTL_MS).is_ok());
+    }
+
+    #[test]
+    fn shared_leases_coexist() {
+        let manager = manager("shared");
+        let shared = |holder: &str| LeaseRequest {
+            mode: LeaseMode::Shared,
+            ..request(holder, LeasePriority::Preview, 0)
+        };
+        manager.acquire(&shared("预览")).unwrap();
+        manager.acquire(&shared("录制")).unwrap();
+        assert_eq!(manager.holders().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn lease_ids_are_unique() {
+        let manager = manager("unique");
+        let shared = |holder: &str| LeaseRequest {
+            mode: LeaseMode::Shared,
+            ..request(holder, LeasePriority::Preview, 0)
+        };
+        // 连续申请通常落在同一毫秒内，编号仍然不能相同
+        let first = manager.acquire(&shared("预览")).unwrap();
+        let second = manager.acquire(&shared("录制")).unwrap();
+        assert_ne!(first.id, second.id);
+
+        // 释放一个不影响另一个
+        manager.release(&first.id).unwrap();
+        let holders = manager.holders().unwrap();
+        assert_eq!(holders.len(), 1);
+        assert_eq!(holders[0].id, second.id);
+    }
+}
@@ -0,0 +1,252 @@
+// 摄像头预热：很多摄像头刚打开时前几帧是黑的或过曝的，自动曝光稳定之前的画面不适合识别
+// 打开摄像头时持续读取并丢弃画面，直到亮度和清晰度连续几帧不再变化，或者超时
+// 预热在 open_camera 返回之前完成，预热期间没有识别请求，丢弃的画面不会记为识别失败
+// 预热会增加每次打开摄像头的耗时，默认关闭，只在采集参数中开启了的摄像头上执行
+use std::time::{Duration, Instant};
+
+use opencv::{
+    core::{Mat, Size},
+    imgproc,
+    prelude::*,
+    videoio::{VideoCapture, VideoCaptureTrait},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    face_quality::{laplacian_variance, mean_std},
+    frame_normalize::normalize_frame,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WarmupConfig {
+    pub enabled: bool,
+    // 最长预热时间，超时后使用当前画面
+    pub timeout_ms: u64,
+    // 至少丢弃的帧数
+    pub min_frames: u32,
+    // 连续多少帧稳定才算预热完成
+    pub stable_frames: u32,
+    // 相邻两帧平均亮度（0~255）的最大变化
+    pub brightness_delta: f64,
+    // 相邻两帧清晰度的最大相对变化
+    pub sharpness_ratio: f64,
+    // 平均亮度低于此值视为黑帧，高于 255 - dark_level 视为过曝
+    pub dark_level: f64,
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_ms: 2000,
+            min_frames: 2,
+            stable_frames: 3,
+            brightness_delta: 3.0,
+            sharpness_ratio: 0.2,
+            dark_level: 15.0,
+        }
+    }
+}
+
+// 预热的结果，写日志和返回给界面
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmupStats {
+    // 读取的帧数，包括空帧
+    pub frames: u32,
+    pub elapsed_ms: u64,
+    // 最后一帧的平均亮度和清晰度（拉普拉斯方差）
+    pub brightness: f64,
+    pub sharpness: f64,
+    // false 表示超时结束
+    pub stabilized: bool,
+}
+
+// 判断画面是否已经稳定
+pub struct WarmupTracker {
+    config: WarmupConfig,
+    frames: u32,
+    stable_count: u32,
+    last: Option<(f64, f64)>,
+}
+
+impl WarmupTracker {
+    pub fn new(config: WarmupConfig) -> Self {
+        Self {
+            config,
+            frames: 0,
+            stable_count: 0,
+            last: None,
+        }
+    }
+
+    // 加入一帧的亮度和清晰度，返回是否已经稳定
+    pub fn observe(&mut self, brightness: f64, sharpness: f64) -> bool {
+        self.frames += 1;
+        let exposed = brightness >= self.config.dark_level && brightness <= 255.0 - self.config.dark_level;
+        let steady = match self.last {
+            Some((last_brightness, last_sharpness)) => {
+                (brightness - last_brightness).abs() <= self.config.brightness_delta
+                    && (sharpness - last_sharpness).abs() <= self.config.sharpness_ratio * last_sharpness.max(1.0)
+            }
+            None => false,
+        };
+        self.last = Some((brightness, sharpness));
+        self.stable_count = if exposed && steady { self.stable_count + 1 } else { 0 };
+        self.frames >= self.config.min_frames && self.stable_count >= self.config.stable_frames
+    }
+}
+
+// 丢弃画面直到稳定或超时，一帧有效画面都没有读到时返回错误
+pub fn warm_up(capture: &mut VideoCapture, config: &WarmupConfig) -> Result<WarmupStats, String> {
+    let start = Instant::now();
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let mut tracker = WarmupTracker::new(*config);
+    let mut stats = WarmupStats::default();
+    let mut got_frame = false;
+    let mut frame = Mat::default();
+
+    loop {
+        stats.frames += 1;
+        let read = capture
+            .read(&mut frame)
+            .map_err(|e| format!("读取帧失败: {:?}", e))?;
+        if read && !frame.empty() {
+            got_frame = true;
+            // 未开启预热时与原来一样，读到一帧有效画面即可
+            if !config.enabled {
+                stats.stabilized = true;
+                break;
+            }
+            let (brightness, sharpness) = frame_exposure(&frame)?;
+            stats.brightness = brightness;
+            stats.sharpness = sharpness;
+            if tracker.observe(brightness, sharpness) {
+                stats.stabilized = true;
+                break;
+            }
+        }
+        if start.elapsed() >= timeout {
+            break;
+        }
+    }
+
+    stats.elapsed_ms = start.elapsed().as_millis() as u64;
+    if !got_frame {
+        return Err(format!("{} ms 内没有读取到有效画面", stats.elapsed_ms));
+    }
+    Ok(stats)
+}
+
+// 平均亮度和拉普拉斯方差，先缩小到 320 宽减少计算量
+// 红外摄像头的单通道、16 位画面先转成 8 位 BGR，亮度与彩色画面在同一个范围
+pub fn frame_exposure(frame: &Mat) -> Result<(f64, f64), String> {
+    let bgr = normalize_frame(frame, None)?.bgr;
+    let mut gray = Mat::default();
+    imgproc::cvt_color(&bgr, &mut gray, imgproc::COLOR_BGR2GRAY, 0)
+        .map_err(|e| format!("转换灰度失败: {:?}", e))?;
+    if gray.cols() > 320 {
+        let scale = 320.0 / gray.cols() as f64;
+        let mut small = Mat::default();
+        imgproc::resize(&gray, &mut small, Size::default(), scale, scale, imgproc::INTER_AREA)
+            .map_err(|e| format!("缩放画面失败: {:?}", e))?;
+        gray = small;
+    }
+
+    let (brightness, _) = mean_std(&gray)?;
+    let sharpness = laplacian_variance(&gray)?;
+    Ok((brightness as f64, sharpness as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use opencv::core::{self, Scalar, CV_8UC3};
+
+    use super::*;
+
+    // 依次加入 (亮度, 清晰度)，返回每一帧后是否稳定
+    fn observe_all(config: WarmupConfig, frames: &[(f64, f64)]) -> Vec<bool> {
+        let mut tracker = WarmupTracker::new(config);
+        frames.iter().map(|(b, s)| tracker.observe(*b, *s)).collect()
+    }
+
+    #[test]
+    fn stabilizes_after_consecutive_steady_frames() {
+        // 黑帧 → 自动曝光调整中 → 连续三帧变化很小
+        let frames = [
+            (5.0, 0.0),
+            (80.0, 100.0),
+            (120.0, 150.0),
+            (130.0, 150.0),
+            (131.0, 152.0),
+            (132.0, 151.0),
+            (132.0, 150.0),
+        ];
+        assert_eq!(
+            observe_all(WarmupConfig::default(), &frames),
+            vec![false, false, false, false, false, false, true]
+        );
+    }
+
+    #[test]
+    fn dark_and_overexposed_frames_never_settle() {
+        let config = WarmupConfig::default();
+        assert!(observe_all(config, &[(5.0, 0.0); 20]).iter().all(|s| !s));
+        assert!(observe_all(config, &[(250.0, 10.0); 20]).iter().all(|s| !s));
+        // 刚好在范围内的画面可以稳定
+        assert!(observe_all(config, &[(15.0, 10.0); 4])[3]);
+    }
+
+    #[test]
+    fn sharpness_jump_restarts_the_count() {
+        let frames = [
+            (100.0, 100.0),
+            (100.0, 100.0),
+            (100.0, 100.0),
+            // 对焦变化，清晰度变化超过 20%，之前的两帧不再算数
+            (100.0, 200.0),
+            (100.0, 200.0),
+            (100.0, 200.0),
+            (100.0, 200.0),
+        ];
+        assert_eq!(
+            observe_all(WarmupConfig::default(), &frames),
+            vec![false, false, false, false, false, false, true]
+        );
+    }
+
+    #[test]
+    fn waits_for_min_frames() {
+        let config = WarmupConfig {
+            min_frames: 5,
+            stable_frames: 1,
+            ..WarmupConfig::default()
+        };
+        assert_eq!(
+            observe_all(config, &[(100.0, 50.0); 5]),
+            vec![false, false, false, false, true]
+        );
+    }
+
+    #[test]
+    fn reads_partial_settings() {
+        let config: WarmupConfig = serde_json::from_str(r#"{"enabled": true, "stableFrames": 5}"#).unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.stable_frames, 5);
+        assert_eq!(config.timeout_ms, WarmupConfig::default().timeout_ms);
+    }
+
+    #[test]
+    fn measures_frame_exposure() {
+        let flat = Mat::new_size_with_default(Size::new(640, 480), CV_8UC3, Scalar::all(100.0)).unwrap();
+        let (brightness, sharpness) = frame_exposure(&flat).unwrap();
+        assert!((brightness - 100.0).abs() < 0.5);
+        assert!(sharpness < 1e-3);
+
+        let mut textured = flat.try_clone().unwrap();
+        core::randu(&mut textured, &Scalar::all(0.0), &Scalar::all(255.0)).unwrap();
+        let (_, sharpness) = frame_exposure(&textured).unwrap();
+        assert!(sharpness > 100.0);
+    }
+}
@@ -0,0 +1,317 @@
+// 摄像头采集参数：分辨率、帧率、像素格式、曝光、亮度、增益
+// 每个摄像头单独设置，存在 options 表的 cameraCapture 中：{摄像头标识: CaptureProfile}，键见 CameraDescriptor::profile_key
+// 驱动不一定接受设置的值，设置后读回实际生效的值
+use std::collections::BTreeMap;
+
+use opencv::{
+    prelude::*,
+    videoio::{self, VideoCapture, VideoWriter},
+};
+use serde::{Deserialize, Serialize};
+
+use super::camera_warmup::WarmupConfig;
+
+// 不设置的项为 None，保持驱动默认值
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CaptureProfile {
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub fps: Option<f64>,
+    // 四个字符的像素格式，如 MJPG、YUY2
+    pub fourcc: Option<String>,
+    pub auto_exposure: Option<bool>,
+    // 手动曝光时的曝光值，含义由驱动决定，DirectShow 一般为 -13 ~ -1
+    pub exposure: Option<f64>,
+    pub brightness: Option<f64>,
+    pub gain: Option<f64>,
+    // 读取原始画面：关闭 OpenCV 的 RGB 转换（CAP_PROP_CONVERT_RGB = 0），红外摄像头的单通道、16 位或 YUYV 画面
+    // 原样交给 normalize_frame 转换，避免驱动先按彩色解码丢掉红外画面的精度
+    pub raw: Option<bool>,
+    // 打开时的预热设置，不设置或未开启时不预热
+    pub warmup: Option<WarmupConfig>,
+}
+
+// 读回的实际值
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureReadback {
+    pub width: i32,
+    pub height: i32,
+    pub fps: f64,
+    pub fourcc: String,
+    // 驱动返回的原始值，不同后端的含义不同
+    pub auto_exposure: f64,
+    pub exposure: f64,
+    pub brightness: f64,
+    pub gain: f64,
+    // 是否读取原始画面
+    pub raw: bool,
+    // 设置了但驱动没有接受的项
+    pub rejected: Vec<String>,
+}
+
+impl CaptureProfile {
+    // 读取 cameraCapture 中某个摄像头的设置，camera 为 CameraDescriptor::profile_key，没有设置时使用驱动默认值
+    pub fn from_options(get: impl Fn(&str) -> Option<String>, camera: &str) -> Self {
+        get("cameraCapture")
+            .and_then(|v| serde_json::from_str::<BTreeMap<String, CaptureProfile>>(&v).ok())
+            .and_then(|mut profiles| profiles.remove(camera))
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == CaptureProfile::default()
+    }
+}
+
+// 按设置修改摄像头参数并读回实际值
+// 像素格式要在分辨率之前设置，部分驱动只有 MJPG 才支持高分辨率
+pub fn apply_capture_profile(capture: &mut VideoCapture, profile: &CaptureProfile) -> Result<CaptureReadback, String> {
+    let set = |capture: &mut VideoCapture, prop: i32, value: f64, name: &str| {
+        capture
+            .set(prop, value)
+            .map_err(|e| format!("设置摄像头{}失败: {:?}", name, e))
+    };
+
+    if let Some(fourcc) = profile.fourcc.as_deref().filter(|f| !f.is_empty()) {
+        let code = fourcc_code(fourcc)?;
+        set(capture, videoio::CAP_PROP_FOURCC, code as f64, "像素格式")?;
+    }
+    if let Some(width) = profile.width {
+        set(capture, videoio::CAP_PROP_FRAME_WIDTH, width as f64, "宽度")?;
+    }
+    if let Some(height) = profile.height {
+        set(capture, videoio::CAP_PROP_FRAME_HEIGHT, height as f64, "高度")?;
+    }
+    if let Some(fps) = profile.fps {
+        set(capture, videoio::CAP_PROP_FPS, fps, "帧率")?;
+    }
+    if let Some(auto_exposure) = profile.auto_exposure {
+        // DirectShow、MSMF 与 V4L2 的取值不同，0.75 / 0.25 是 OpenCV 约定的 自动 / 手动
+        set(
+            capture,
+            videoio::CAP_PROP_AUTO_EXPOSURE,
+            if auto_exposure { 0.75 } else { 0.25 },
+            "自动曝光",
+        )?;
+        if !auto_exposure {
+            if let Some(exposure) = profile.exposure {
+                set(capture, videoio::CAP_PROP_EXPOSURE, exposure, "曝光")?;
+            }
+        }
+    }
+    if let Some(brightness) = profile.brightness {
+        set(capture, videoio::CAP_PROP_BRIGHTNESS, brightness, "亮度")?;
+    }
+    if let Some(gain) = profile.gain {
+        set(capture, videoio::CAP_PROP_GAIN, gain, "增益")?;
+    }
+    if let Some(raw) = profile.raw {
+        set(
+            capture,
+            videoio::CAP_PROP_CONVERT_RGB,
+            if raw { 0.0 } else { 1.0 },
+            "原始画面",
+        )?;
+    }
+
+    let mut readback = read_capture(capture);
+    readback.rejected = rejected_items(profile, &readback);
+    Ok(readback)
+}
+
+// 读取摄像头当前的参数
+pub fn read_capture(capture: &VideoCapture) -> CaptureReadback {
+    let get = |prop: i32| capture.get(prop).unwrap_or(0.0);
+    CaptureReadback {
+        width: get(videoio::CAP_PROP_FRAME_WIDTH) as i32,
+        height: get(videoio::CAP_PROP_FRAME_HEIGHT) as i32,
+        fps: get(videoio::CAP_PROP_FPS),
+        fourcc: fourcc_string(get(videoio::CAP_PROP_FOURCC) as i32),
+        auto_exposure: get(videoio::CAP_PROP_AUTO_EXPOSURE),
+        exposure: get(videoio::CAP_PROP_EXPOSURE),
+        brightness: get(videoio::CAP_PROP_BRIGHTNESS),
+        gain: get(videoio::CAP_PROP_GAIN),
+        raw: get(videoio::CAP_PROP_CONVERT_RGB) == 0.0,
+        rejected: Vec::new(),
+    }
+}
+
+// 比较设置值和读回值，曝光的取值各后端不同，不做比较
+fn rejected_items(profile: &CaptureProfile, readback: &CaptureReadback) -> Vec<String> {
+    let differs = |expected: f64, actual: f64| (expected - actual).abs() > 0.5;
+    let mut rejected = Vec::new();
+    if let Some(fourcc) = profile.fourcc.as_deref().filter(|f| !f.is_empty()) {
+        if !fourcc.eq_ignore_ascii_case(&readback.fourcc) {
+            rejected.push(format!("像素格式 {}（实际 {}）", fourcc, readback.fourcc));
+        }
+    }
+    if let (Some(width), Some(height)) = (profile.width, profile.height) {
+        if width != readback.width || height != readback.height {
+            rejected.push(format!(
+                "分辨率 {}x{}（实际 {}x{}）",
+                width, height, readback.width, readback.height
+            ));
+        }
+    }
+    if let Some(fps) = profile.fps {
+        if differs(fps, readback.fps) {
+            rejected.push(format!("帧率 {}（实际 {}）", fps, readback.fps));
+        }
+    }
+    if let Some(brightness) = profile.brightness {
+        if differs(brightness, readback.brightness) {
+            rejected.push(format!("亮度 {}（实际 {}）", brightness, readback.brightness));
+        }
+    }
+    if let Some(gain) = profile.gain {
+        if differs(gain, readback.gain) {
+            rejected.push(format!("增益 {}（实际 {}）", gain, readback.gain));
+        }
+    }
+    if profile.raw == Some(true) && !readback.raw {
+        rejected.push(String::from("原始画面"));
+    }
+    rejected
+}
+
+fn fourcc_code(fourcc: &str) -> Result<i32, String> {
+    let chars: Vec<char> = fourcc.chars().collect();
+    if chars.len() != 4 || !chars.iter().all(|c| c.is_ascii()) {
+        return Err(format!("像素格式 {} 必须是四个字符", fourcc));
+    }
+    VideoWriter::fourcc(chars[0], chars[1], chars[2], chars[3])
+        .map_err(|e| format!("像素格式 {} 无效: {:?}", fourcc, e))
+}
+
+fn fourcc_string(code: i32) -> String {
+    code.to_le_bytes()
+        .iter()
+        .filter(|b| b.is_ascii_graphic())
+        .map(|b| *b as char)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    // 驱动接受了全部设置时读回的值
+    fn readback() -> CaptureReadback {
+        CaptureReadback {
+            width: 1280,
+            height: 720,
+            fps: 30.0,
+            fourcc: String::from("MJPG"),
+            auto_exposure: 0.25,
+            exposure: -6.0,
+            brightness: 128.0,
+            gain: 0.0,
+            raw: false,
+            rejected: Vec::new(),
+        }
+    }
+
+    fn profile() -> CaptureProfile {
+        CaptureProfile {
+            width: Some(1280),
+            height: Some(720),
+            fps: Some(30.0),
+            fourcc: Some(String::from("mjpg")),
+            auto_exposure: Some(false),
+            exposure: Some(-8.0),
+            brightness: Some(128.0),
+            gain: Some(0.0),
+            raw: Some(false),
+            warmup: None,
+        }
+    }
+
+    #[test]
+    fn accepted_settings_are_not_reported() {
+        // 像素格式不区分大小写，帧率允许驱动的小数误差，曝光的取值各后端不同不做比较
+        let actual = CaptureReadback {
+            fps: 30.0003,
+            ..readback()
+        };
+        assert!(rejected_items(&profile(), &actual).is_empty());
+        // 没有设置的项不比较
+        assert!(rejected_items(&CaptureProfile::default(), &CaptureReadback::default()).is_empty());
+    }
+
+    #[test]
+    fn reports_each_rejected_setting() {
+        let actual = CaptureReadback {
+            width: 640,
+            height: 480,
+            fps: 15.0,
+            fourcc: String::from("YUY2"),
+            brightness: 100.0,
+            gain: 12.0,
+            ..readback()
+        };
+        let expected = CaptureProfile {
+            raw: Some(true),
+            ..profile()
+        };
+        assert_eq!(
+            rejected_items(&expected, &actual),
+            vec![
+                "像素格式 mjpg（实际 YUY2）",
+                "分辨率 1280x720（实际 640x480）",
+                "帧率 30（实际 15）",
+                "亮度 128（实际 100）",
+                "增益 0（实际 12）",
+                "原始画面",
+            ]
+        );
+    }
+
+    #[test]
+    fn resolution_is_compared_only_when_both_sides_are_set() {
+        let width_only = CaptureProfile {
+            width: Some(1920),
+            ..CaptureProfile::default()
+        };
+        assert!(rejected_items(&width_only, &readback()).is_empty());
+    }
+
+    #[test]
+    fn converts_fourcc_codes() {
+        let code = fourcc_code("MJPG").unwrap();
+        assert_eq!(code, i32::from_le_bytes(*b"MJPG"));
+        assert_eq!(fourcc_string(code), "MJPG");
+        // 驱动没有返回像素格式时为 0
+        assert_eq!(fourcc_string(0), "");
+        for invalid in ["MJP", "MJPGX", "MJP格"] {
+            assert!(fourcc_code(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn reads_each_camera_separately() {
+        let saved = HashMap::from([(
+            "cameraCapture",
+            r##"{"USB#vid_046d": {"width": 1920, "height": 1080, "fourcc": "MJPG", "warmup": {"enabled": true}},
+                "#0": {}}"##
+                .to_string(),
+        )]);
+        let get = |key: &str| saved.get(key).cloned();
+
+        let usb = CaptureProfile::from_options(get, "USB#vid_046d");
+        assert_eq!((usb.width, usb.height), (Some(1920), Some(1080)));
+        assert_eq!(usb.fps, None);
+        // 预热只写了 enabled，其余使用默认值
+        let warmup = usb.warmup.unwrap();
+        assert!(warmup.enabled);
+        assert_eq!(warmup.timeout_ms, WarmupConfig::default().timeout_ms);
+        assert!(!usb.is_empty());
+
+        assert!(CaptureProfile::from_options(get, "#0").is_empty());
+        assert!(CaptureProfile::from_options(get, "Integrated Camera").is_empty());
+        assert!(CaptureProfile::from_options(|_| Some(String::from("{")), "#0").is_empty());
+    }
+}
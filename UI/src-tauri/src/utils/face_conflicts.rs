@@ -0,0 +1,299 @@
+// 重复、冲突录入检查
+// 同一个人录入到多个 Windows 账户，或把别人的脸录到了自己的账户下，解锁时会进错账户
+// 每个面容的特征取自保存的面容图片（.faceimg）和模板库（.gallery），不依赖 .face 的格式
+use std::{collections::HashMap, fs, path::Path, time::SystemTime};
+
+use opencv::{
+    core::Ptr,
+    objdetect::{FaceDetectorYN, FaceRecognizerSF},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    face_pipeline::{feature_to_vec, image_feature},
+    frame_normalize::{modality_compatible, Modality},
+    template_gallery::{cosine_similarity, validate_face_token, TemplateGallery},
+};
+
+// 默认的冲突阈值，高于 OpenCV 推荐的同一人阈值 0.363，减少误报
+pub const DEFAULT_CONFLICT_THRESHOLD: f32 = 0.45;
+
+// 界面传入的面容信息，数据库在界面一侧
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceRef {
+    pub id: i64,
+    pub face_token: String,
+    pub user_name: String,
+    #[serde(default)]
+    pub alias: Option<String>,
+    #[serde(default)]
+    pub modality: Modality,
+    #[serde(default = "default_detection_threshold")]
+    pub face_detection_threshold: f32,
+}
+
+fn default_detection_threshold() -> f32 {
+    0.9
+}
+
+// 一个面容的全部特征
+pub struct FaceFeatures {
+    pub face: FaceRef,
+    pub features: Vec<(Modality, Vec<f32>)>,
+}
+
+// 两个属于不同账户但很相似的面容
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictPair {
+    pub face_id: i64,
+    pub user_name: String,
+    pub alias: Option<String>,
+    // 对面的面容，新录入检查时为 None
+    pub other_face_id: Option<i64>,
+    pub other_user_name: Option<String>,
+    pub other_alias: Option<String>,
+    pub score: f32,
+}
+
+// 面容图片提取出的特征，图片中没有人脸时为 None
+struct CachedImageFeature {
+    modified: Option<SystemTime>,
+    face_detection_threshold: f32,
+    feature: Option<Vec<f32>>,
+}
+
+// 面容图片特征的缓存，键是 face_token
+// 提取特征要跑一遍检测和识别，每次检查都重新提取太慢；图片被替换或检测阈值变化时重新提取
+// 缓存的特征依赖识别模型，卸载模型时要清空
+#[derive(Default)]
+pub struct FeatureCache {
+    entries: HashMap<String, CachedImageFeature>,
+}
+
+impl FeatureCache {
+    fn get(
+        &self,
+        face_token: &str,
+        modified: Option<SystemTime>,
+        face_detection_threshold: f32,
+    ) -> Option<Option<Vec<f32>>> {
+        self.entries
+            .get(face_token)
+            .filter(|entry| {
+                modified.is_some()
+                    && entry.modified == modified
+                    && entry.face_detection_threshold == face_detection_threshold
+            })
+            .map(|entry| entry.feature.clone())
+    }
+
+    fn insert(
+        &mut self,
+        face_token: &str,
+        modified: Option<SystemTime>,
+        face_detection_threshold: f32,
+        feature: Option<Vec<f32>>,
+    ) {
+        self.entries.insert(
+            face_token.to_string(),
+            CachedImageFeature {
+                modified,
+                face_detection_threshold,
+                feature,
+            },
+        );
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+// 读取面容图片和模板库中的特征，图片读取失败时返回错误，调用方决定是否跳过
+// 图片特征优先从缓存中取，模板库是 json，每次都重新读取
+pub fn load_face_features(
+    detector: &mut Ptr<FaceDetectorYN>,
+    recognizer: &mut Ptr<FaceRecognizerSF>,
+    cache: &mut FeatureCache,
+    faces_dir: &Path,
+    face: &FaceRef,
+) -> Result<FaceFeatures, String> {
+    validate_face_token(&face.face_token)?;
+    let mut features = Vec::new();
+    let image_path = faces_dir.join(format!("{}.faceimg", face.face_token));
+    let modified = fs::metadata(&image_path).and_then(|m| m.modified()).ok();
+    let cached = match cache.get(&face.face_token, modified, face.face_detection_threshold) {
+        Some(feature) => feature,
+        None => {
+            let feature = image_feature(
+                detector,
+                recognizer,
+                image_path.to_str().unwrap_or(""),
+                face.face_detection_threshold,
+            )?
+            .map(|f| feature_to_vec(&f))
+            .transpose()?;
+            cache.insert(
+                &face.face_token,
+                modified,
+                face.face_detection_threshold,
+                feature.clone(),
+            );
+            feature
+        }
+    };
+    if let Some(feature) = cached {
+        features.push((face.modality, feature));
+    }
+
+    let gallery = TemplateGallery::load(faces_dir, &face.face_token)?;
+    features.extend(gallery.templates.into_iter().map(|t| (t.modality, t.feature)));
+    Ok(FaceFeatures {
+        face: face.clone(),
+        features,
+    })
+}
+
+// 两组特征之间的最高相似度，只比较同一成像方式的特征
+pub fn best_similarity(a: &[(Modality, Vec<f32>)], b: &[(Modality, Vec<f32>)]) -> Option<f32> {
+    a.iter()
+        .flat_map(|(ma, fa)| {
+            b.iter()
+                .filter(move |(mb, _)| modality_compatible(*ma, *mb))
+                .map(move |(_, fb)| cosine_similarity(fa, fb))
+        })
+        .fold(None, |best, score| Some(best.map_or(score, |b: f32| b.max(score))))
+}
+
+// 新特征与其他账户的面容比对，返回超过阈值的面容，相似度从高到低
+pub fn find_conflicts(
+    feature: &[f32],
+    modality: Modality,
+    user_name: &str,
+    existing: &[FaceFeatures],
+    threshold: f32,
+) -> Vec<ConflictPair> {
+    let probe = [(modality, feature.to_vec())];
+    let mut conflicts: Vec<ConflictPair> = existing
+        .iter()
+        .filter(|f| !same_account(&f.face.user_name, user_name))
+        .filter_map(|f| {
+            best_similarity(&probe, &f.features)
+                .filter(|score| *score >= threshold)
+                .map(|score| ConflictPair {
+                    face_id: f.face.id,
+                    user_name: f.face.user_name.clone(),
+                    alias: f.face.alias.clone(),
+                    other_face_id: None,
+                    other_user_name: None,
+                    other_alias: None,
+                    score,
+                })
+        })
+        .collect();
+    sort_by_score(&mut conflicts);
+    conflicts
+}
+
+// 已有数据中两两比对，列出属于不同账户但超过阈值的面容
+pub fn consistency_report(faces: &[FaceFeatures], threshold: f32) -> Vec<ConflictPair> {
+    let mut pairs = Vec::new();
+    for (i, a) in faces.iter().enumerate() {
+        for b in faces.iter().skip(i + 1) {
+            if same_account(&a.face.user_name, &b.face.user_name) {
+                continue;
+            }
+            if let Some(score) = best_similarity(&a.features, &b.features).filter(|s| *s >= threshold) {
+                pairs.push(ConflictPair {
+                    face_id: a.face.id,
+                    user_name: a.face.user_name.clone(),
+                    alias: a.face.alias.clone(),
+                    other_face_id: Some(b.face.id),
+                    other_user_name: Some(b.face.user_name.clone()),
+                    other_alias: b.face.alias.clone(),
+                    score,
+                });
+            }
+        }
+    }
+    sort_by_score(&mut pairs);
+    pairs
+}
+
+// Windows 用户名不区分大小写
+fn same_account(a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b)
+}
+
+fn sort_by_score(pairs: &mut [ConflictPair]) {
+    pairs.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn face(id: i64, user_name: &str, features: Vec<(Modality, Vec<f32>)>) -> FaceFeatures {
+        FaceFeatures {
+            face: FaceRef {
+                id,
+                face_token: format!("token{}", id),
+                user_name: user_name.to_string(),
+                alias: None,
+                modality: Modality::Rgb,
+                face_detection_threshold: default_detection_threshold(),
+            },
+            features,
+        }
+    }
+
+    #[test]
+    fn reports_similar_faces_of_different_accounts() {
+        let faces = [
+            face(1, "alice", vec![(Modality::Rgb, vec![1.0, 0.0, 0.0])]),
+            face(2, "bob", vec![(Modality::Rgb, vec![0.9, 0.1, 0.0])]),
+            face(3, "carol", vec![(Modality::Rgb, vec![1.0, 0.05, 0.0])]),
+            face(4, "dave", vec![(Modality::Rgb, vec![0.0, 0.0, 1.0])]),
+        ];
+        let pairs = consistency_report(&faces, DEFAULT_CONFLICT_THRESHOLD);
+        let ids: Vec<_> = pairs.iter().map(|p| (p.face_id, p.other_face_id.unwrap())).collect();
+        // 相似度从高到低，dave 与其他人都不像
+        assert_eq!(ids, vec![(1, 3), (2, 3), (1, 2)]);
+        assert!(pairs.windows(2).all(|w| w[0].score >= w[1].score));
+        assert_eq!(pairs[0].other_user_name.as_deref(), Some("carol"));
+    }
+
+    #[test]
+    fn same_account_is_not_a_conflict() {
+        // Windows 用户名不区分大小写
+        let faces = [
+            face(1, "Alice", vec![(Modality::Rgb, vec![1.0, 0.0])]),
+            face(2, "alice", vec![(Modality::Rgb, vec![1.0, 0.0])]),
+        ];
+        assert!(consistency_report(&faces, DEFAULT_CONFLICT_THRESHOLD).is_empty());
+    }
+
+    #[test]
+    fn only_compares_features_of_the_same_modality() {
+        let faces = [
+            face(1, "alice", vec![(Modality::Rgb, vec![1.0, 0.0])]),
+            face(2, "bob", vec![(Modality::Ir, vec![1.0, 0.0])]),
+        ];
+        assert!(consistency_report(&faces, DEFAULT_CONFLICT_THRESHOLD).is_empty());
+
+        // 模板库中有同为红外的模板时才比对
+        let faces = [
+            face(
+                1,
+                "alice",
+                vec![(Modality::Rgb, vec![1.0, 0.0]), (Modality::Ir, vec![0.0, 1.0])],
+            ),
+            face(2, "bob", vec![(Modality::Ir, vec![0.1, 1.0])]),
+        ];
+        let pairs = consistency_report(&faces, DEFAULT_CONFLICT_THRESHOLD);
+        assert_eq!(pairs.len(), 1);
+        assert!(pairs[0].score > 0.99);
+    }
+}
@@ -0,0 +1,212 @@
+// 连续帧的人脸检测
+// 高分辨率画面先缩小到工作尺寸再检测；找到人脸后只在人脸附近的区域检测，定期回到整帧，避免漏掉新进入画面的人
+use opencv::{
+    core::{Mat, Ptr, Rect},
+    objdetect::FaceDetectorYN,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use super::face_pipeline::{detect_faces_in_roi, detect_faces_scaled, face_values, DETECTOR_INPUT_SIZE};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FinderConfig {
+    // 检测时图片长边的最大像素，0 表示不缩小
+    pub working_size: i32,
+    // 是否只在上一帧的人脸附近检测
+    pub roi_tracking: bool,
+    // 检测区域在人脸框基础上向四周扩展的比例
+    pub roi_margin: f32,
+    // 每隔多少帧做一次整帧检测
+    pub full_frame_interval: u32,
+}
+
+impl Default for FinderConfig {
+    fn default() -> Self {
+        Self {
+            working_size: DETECTOR_INPUT_SIZE,
+            roi_tracking: true,
+            roi_margin: 0.5,
+            full_frame_interval: 10,
+        }
+    }
+}
+
+impl FinderConfig {
+    // 从设置中读取，缺失时使用默认值
+    pub fn from_options(get: impl Fn(&str) -> Option<String>) -> Self {
+        let default = Self::default();
+        Self {
+            working_size: get("detectWorkingSize")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.working_size),
+            roi_tracking: get("detectRoiTracking").as_deref() != Some("false"),
+            ..default
+        }
+    }
+}
+
+pub struct FaceFinder {
+    config: FinderConfig,
+    roi: Option<Rect>,
+    frames_since_full: u32,
+}
+
+impl FaceFinder {
+    pub fn new(config: FinderConfig) -> Self {
+        Self {
+            config,
+            roi: None,
+            frames_since_full: 0,
+        }
+    }
+
+    // 当前的检测区域，整帧检测时为 None
+    pub fn roi(&self) -> Option<Rect> {
+        self.roi
+    }
+
+    // 换了摄像头或重新开始时调用
+    pub fn reset(&mut self) {
+        self.roi = None;
+        self.frames_since_full = 0;
+    }
+
+    // 检测一帧，返回的坐标都是原图坐标
+    pub fn detect(
+        &mut self,
+        detector: &mut Ptr<FaceDetectorYN>,
+        frame: &Mat,
+        face_detection_threshold: f32,
+    ) -> Result<Mat, String> {
+        let use_roi = self.config.roi_tracking && self.frames_since_full < self.config.full_frame_interval;
+        if let (true, Some(roi)) = (use_roi, self.roi) {
+            let faces = detect_faces_in_roi(
+                detector,
+                frame,
+                roi,
+                face_detection_threshold,
+                self.config.working_size,
+            )?;
+            if faces.rows() > 0 {
+                self.frames_since_full += 1;
+                self.update_roi(frame, &faces)?;
+                return Ok(faces);
+            }
+            // 区域内丢失人脸时立即回到整帧
+        }
+
+        let faces = detect_faces_scaled(detector, frame, face_detection_threshold, self.config.working_size)?;
+        self.frames_since_full = 0;
+        self.update_roi(frame, &faces)?;
+        Ok(faces)
+    }
+
+    // 检测区域为所有人脸框的外接矩形再向四周扩展
+    fn update_roi(&mut self, frame: &Mat, faces: &Mat) -> Result<(), String> {
+        if !self.config.roi_tracking || faces.rows() == 0 {
+            self.roi = None;
+            return Ok(());
+        }
+
+        let (mut left, mut top, mut right, mut bottom) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+        for row in 0..faces.rows() {
+            let values = face_values(faces, row)?;
+            left = left.min(values[0]);
+            top = top.min(values[1]);
+            right = right.max(values[0] + values[2]);
+            bottom = bottom.max(values[1] + values[3]);
+        }
+        let margin_x = (right - left) * self.config.roi_margin;
+        let margin_y = (bottom - top) * self.config.roi_margin;
+        let left = ((left - margin_x).max(0.0)) as i32;
+        let top = ((top - margin_y).max(0.0)) as i32;
+        let right = ((right + margin_x) as i32).min(frame.cols());
+        let bottom = ((bottom + margin_y) as i32).min(frame.rows());
+
+        self.roi = if right > left && bottom > top {
+            Some(Rect::new(left, top, right - left, bottom - top))
+        } else {
+            None
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use opencv::core::{Scalar, Size, CV_8UC3};
+
+    use super::super::face_pipeline::FACE_ROW_LEN;
+    use super::*;
+
+    fn frame() -> Mat {
+        Mat::new_size_with_default(Size::new(640, 480), CV_8UC3, Scalar::all(0.0)).unwrap()
+    }
+
+    // 只有人脸框的检测结果，坐标已经是原图坐标
+    fn faces(boxes: &[(f32, f32, f32, f32)]) -> Mat {
+        let rows: Vec<[f32; FACE_ROW_LEN]> = boxes
+            .iter()
+            .map(|&(x, y, w, h)| {
+                let mut row = [0.0; FACE_ROW_LEN];
+                row[..4].copy_from_slice(&[x, y, w, h]);
+                row
+            })
+            .collect();
+        Mat::from_slice_2d(&rows).unwrap()
+    }
+
+    #[test]
+    fn roi_covers_all_faces_with_margin() {
+        let mut finder = FaceFinder::new(FinderConfig::default());
+        finder
+            .update_roi(&frame(), &faces(&[(100.0, 100.0, 100.0, 100.0), (300.0, 120.0, 50.0, 80.0)]))
+            .unwrap();
+        // 外接矩形 (100, 100) - (350, 200)，左右各扩展 125，上下各扩展 50，左边超出画面
+        assert_eq!(finder.roi(), Some(Rect::new(0, 50, 475, 200)));
+    }
+
+    #[test]
+    fn roi_is_clipped_to_the_frame() {
+        let mut finder = FaceFinder::new(FinderConfig::default());
+        finder.update_roi(&frame(), &faces(&[(560.0, 400.0, 80.0, 80.0)])).unwrap();
+        assert_eq!(finder.roi(), Some(Rect::new(520, 360, 120, 120)));
+    }
+
+    #[test]
+    fn roi_is_cleared_without_faces_or_tracking() {
+        let mut finder = FaceFinder::new(FinderConfig::default());
+        finder.update_roi(&frame(), &faces(&[(100.0, 100.0, 100.0, 100.0)])).unwrap();
+        assert!(finder.roi().is_some());
+        // 丢失人脸后回到整帧
+        finder.update_roi(&frame(), &Mat::default()).unwrap();
+        assert_eq!(finder.roi(), None);
+
+        finder.update_roi(&frame(), &faces(&[(100.0, 100.0, 100.0, 100.0)])).unwrap();
+        finder.reset();
+        assert_eq!(finder.roi(), None);
+
+        let mut finder = FaceFinder::new(FinderConfig {
+            roi_tracking: false,
+            ..FinderConfig::default()
+        });
+        finder.update_roi(&frame(), &faces(&[(100.0, 100.0, 100.0, 100.0)])).unwrap();
+        assert_eq!(finder.roi(), None);
+    }
+
+    #[test]
+    fn reads_finder_settings() {
+        let options = HashMap::from([("detectWorkingSize", "960"), ("detectRoiTracking", "false")]);
+        let config = FinderConfig::from_options(|key| options.get(key).map(|v| v.to_string()));
+        assert_eq!(config.working_size, 960);
+        assert!(!config.roi_tracking);
+
+        let options = HashMap::from([("detectWorkingSize", "大")]);
+        let config = FinderConfig::from_options(|key| options.get(key).map(|v| v.to_string()));
+        assert_eq!(config.working_size, DETECTOR_INPUT_SIZE);
+        assert!(config.roi_tracking);
+    }
+}
@@ -0,0 +1,346 @@
+// 人脸流水线的公共函数：检测 → 对齐 → 提取特征 → 比对
+// 只依赖 opencv，界面、解锁服务和离线工具都可以复用
+use std::path::Path;
+
+use opencv::{
+    core::{Mat, MatTrait, MatTraitConst, Ptr, Rect, Size},
+    dnn, imgcodecs, imgproc,
+    objdetect::{
+        FaceDetectorYN, FaceDetectorYNTrait, FaceRecognizerSF, FaceRecognizerSFTrait,
+        FaceRecognizerSFTraitConst, FaceRecognizerSF_DisType,
+    },
+};
+
+// YuNet 每一行的数据：x, y, w, h, 右眼x, 右眼y, 左眼x, 左眼y, 鼻尖x, 鼻尖y, 右嘴角x, 右嘴角y, 左嘴角x, 左嘴角y, 置信度
+pub const FACE_ROW_LEN: usize = 15;
+pub const FACE_SCORE_INDEX: usize = 14;
+
+// resources 目录下的模型文件名
+pub const DETECTOR_MODEL: &str = "face_detection_yunet_2023mar.onnx";
+pub const RECOGNIZER_MODEL: &str = "face_recognition_sface_2021dec.onnx";
+pub const LIVENESS_MODEL: &str = "face_liveness.onnx";
+
+// 创建检测器时的输入尺寸，与默认的检测工作尺寸一致
+// 检测前 detect_faces 都会按实际输入（缩小后的画面或检测区域）重新设置，这里只影响第一次分配的缓冲区
+pub const DETECTOR_INPUT_SIZE: i32 = 640;
+
+// 创建人脸检测器，界面、解锁服务和离线工具使用同一组参数
+pub fn create_detector(resource_dir: &Path) -> Result<Ptr<FaceDetectorYN>, String> {
+    create_detector_from_file(&resource_dir.join(DETECTOR_MODEL))
+}
+
+// 从指定的模型文件创建人脸检测器，用于已经按模型清单校验过的路径
+pub fn create_detector_from_file(model_path: &Path) -> Result<Ptr<FaceDetectorYN>, String> {
+    FaceDetectorYN::create(
+        model_path.to_str().unwrap_or(""),
+        "",
+        Size::new(DETECTOR_INPUT_SIZE, DETECTOR_INPUT_SIZE),
+        0.9,
+        0.3,
+        5000,
+        0,
+        0,
+    )
+    .map_err(|e| format!("初始化检测器模型失败: {:?}", e))
+}
+
+// 创建人脸识别器
+pub fn create_recognizer(resource_dir: &Path) -> Result<Ptr<FaceRecognizerSF>, String> {
+    let model_path = resource_dir.join(RECOGNIZER_MODEL);
+    FaceRecognizerSF::create(model_path.to_str().unwrap_or(""), "", 0, 0)
+        .map_err(|e| format!("初始化识别器模型失败: {:?}", e))
+}
+
+// 加载活体检测模型
+pub fn create_liveness(resource_dir: &Path) -> Result<dnn::Net, String> {
+    let model_path = resource_dir.join(LIVENESS_MODEL);
+    dnn::read_net_from_onnx(model_path.to_str().unwrap_or(""))
+        .map_err(|e| format!("初始化活体检测模型失败: {:?}", e))
+}
+
+// 读取图片，读取失败或为空时返回错误
+pub fn read_image(path: &str) -> Result<Mat, String> {
+    let img = imgcodecs::imread(path, imgcodecs::IMREAD_COLOR)
+        .map_err(|e| format!("读取图片 {} 失败: {:?}", path, e))?;
+    if img.empty() {
+        return Err(format!("图片 {} 为空或格式不支持", path));
+    }
+    Ok(img)
+}
+
+// 解码 base64 图片（不带 data:image 前缀）
+pub fn decode_base64_image(image_base64: &str) -> Result<Mat, String> {
+    use base64::Engine;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(image_base64.trim())
+        .map_err(|e| format!("base64 解码失败: {}", e))?;
+    let buffer = opencv::core::Vector::<u8>::from_slice(&bytes);
+    let img = imgcodecs::imdecode(&buffer, imgcodecs::IMREAD_COLOR)
+        .map_err(|e| format!("图片解码失败: {:?}", e))?;
+    if img.empty() {
+        return Err(String::from("图片为空或格式不支持"));
+    }
+    Ok(img)
+}
+
+// 编码为 jpg 的 base64（不带 data:image 前缀），与 decode_base64_image 对应
+pub fn encode_base64_image(img: &Mat) -> Result<String, String> {
+    use base64::Engine;
+    let mut buffer = opencv::core::Vector::<u8>::new();
+    imgcodecs::imencode(".jpg", img, &mut buffer, &opencv::core::Vector::new())
+        .map_err(|e| format!("图片编码失败: {:?}", e))?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(buffer.as_slice()))
+}
+
+// 检测人脸，返回的 Mat 每一行是一张脸
+pub fn detect_faces(
+    detector: &mut Ptr<FaceDetectorYN>,
+    img: &Mat,
+    face_detection_threshold: f32,
+) -> Result<Mat, String> {
+    let size = img.size().map_err(|e| format!("获取图片尺寸失败: {:?}", e))?;
+    detector
+        .set_input_size(size)
+        .map_err(|e| format!("设置检测器尺寸失败: {:?}", e))?;
+    detector
+        .set_score_threshold(face_detection_threshold)
+        .map_err(|e| format!("设置检测器阈值失败: {:?}", e))?;
+
+    let mut faces = Mat::default();
+    detector
+        .detect(img, &mut faces)
+        .map_err(|e| format!("人脸检测失败: {:?}", e))?;
+    Ok(faces)
+}
+
+// 先把图片缩小到长边不超过 working_size 再检测，返回的坐标已映射回原图
+// working_size 为 0 或图片本来就更小时不缩放
+pub fn detect_faces_scaled(
+    detector: &mut Ptr<FaceDetectorYN>,
+    img: &Mat,
+    face_detection_threshold: f32,
+    working_size: i32,
+) -> Result<Mat, String> {
+    let scale = match working_scale(img.cols(), img.rows(), working_size) {
+        Some(scale) => scale,
+        None => return detect_faces(detector, img, face_detection_threshold),
+    };
+    let target = Size::new(
+        ((img.cols() as f32 * scale).round() as i32).max(1),
+        ((img.rows() as f32 * scale).round() as i32).max(1),
+    );
+    let mut small = Mat::default();
+    imgproc::resize(img, &mut small, target, 0.0, 0.0, imgproc::INTER_AREA)
+        .map_err(|e| format!("缩小图片失败: {:?}", e))?;
+
+    let mut faces = detect_faces(detector, &small, face_detection_threshold)?;
+    map_faces_back(&mut faces, 1.0 / scale, 0.0, 0.0)?;
+    Ok(faces)
+}
+
+// 缩小到工作尺寸的比例，working_size 为 0 或图片本来就更小时为 None
+pub fn working_scale(cols: i32, rows: i32, working_size: i32) -> Option<f32> {
+    let longer = cols.max(rows);
+    if working_size <= 0 || longer <= working_size {
+        return None;
+    }
+    Some(working_size as f32 / longer as f32)
+}
+
+// 把检测结果的框和关键点按 scale 放大并加上偏移，置信度不变
+pub fn map_faces_back(faces: &mut Mat, scale: f32, offset_x: f32, offset_y: f32) -> Result<(), String> {
+    for row in 0..faces.rows() {
+        for col in 0..FACE_SCORE_INDEX {
+            let value = faces
+                .at_2d_mut::<f32>(row, col as i32)
+                .map_err(|e| format!("映射人脸坐标失败: {:?}", e))?;
+            // 0, 1 是框的左上角，2, 3 是宽高不加偏移，之后每两个是一个关键点
+            let offset = match col {
+                2 | 3 => 0.0,
+                c if c % 2 == 0 => offset_x,
+                _ => offset_y,
+            };
+            *value = *value * scale + offset;
+        }
+    }
+    Ok(())
+}
+
+// 在图片的一块区域内检测，返回的坐标是原图坐标
+pub fn detect_faces_in_roi(
+    detector: &mut Ptr<FaceDetectorYN>,
+    img: &Mat,
+    roi: Rect,
+    face_detection_threshold: f32,
+    working_size: i32,
+) -> Result<Mat, String> {
+    let region = Mat::roi(img, roi)
+        .and_then(|r| r.try_clone())
+        .map_err(|e| format!("裁剪检测区域失败: {:?}", e))?;
+    let mut faces = detect_faces_scaled(detector, &region, face_detection_threshold, working_size)?;
+    map_faces_back(&mut faces, 1.0, roi.x as f32, roi.y as f32)?;
+    Ok(faces)
+}
+
+// 读取一行人脸数据
+pub fn face_values(faces: &Mat, row: i32) -> Result<[f32; FACE_ROW_LEN], String> {
+    let mut values = [0f32; FACE_ROW_LEN];
+    for (col, value) in values.iter_mut().enumerate() {
+        *value = *faces
+            .at_2d::<f32>(row, col as i32)
+            .map_err(|e| format!("读取人脸数据失败: {:?}", e))?;
+    }
+    Ok(values)
+}
+
+// 取面积最大的人脸，没有人脸时返回 None
+pub fn largest_face(faces: &Mat) -> Result<Option<Mat>, String> {
+    let mut best: Option<(i32, f32)> = None;
+    for row in 0..faces.rows() {
+        let values = face_values(faces, row)?;
+        let area = values[2] * values[3];
+        if best.is_none_or(|(_, best_area)| area > best_area) {
+            best = Some((row, area));
+        }
+    }
+
+    match best {
+        Some((row, _)) => {
+            let face = faces
+                .row(row)
+                .and_then(|r| r.try_clone())
+                .map_err(|e| format!("复制人脸数据失败: {:?}", e))?;
+            Ok(Some(face))
+        }
+        None => Ok(None),
+    }
+}
+
+// 对齐人脸并提取特征
+pub fn extract_feature(
+    recognizer: &mut Ptr<FaceRecognizerSF>,
+    img: &Mat,
+    face: &Mat,
+) -> Result<Mat, String> {
+    let mut aligned = Mat::default();
+    recognizer
+        .align_crop(img, face, &mut aligned)
+        .map_err(|e| format!("人脸对齐失败: {:?}", e))?;
+
+    let mut feature = Mat::default();
+    recognizer
+        .feature(&aligned, &mut feature)
+        .map_err(|e| format!("提取人脸特征失败: {:?}", e))?;
+
+    // feature 指向识别器内部的缓冲区，必须复制一份
+    feature
+        .try_clone()
+        .map_err(|e| format!("复制人脸特征失败: {:?}", e))
+}
+
+// 从图片文件中提取最大人脸的特征，未检测到人脸时返回 None
+pub fn image_feature(
+    detector: &mut Ptr<FaceDetectorYN>,
+    recognizer: &mut Ptr<FaceRecognizerSF>,
+    path: &str,
+    face_detection_threshold: f32,
+) -> Result<Option<Mat>, String> {
+    let img = read_image(path)?;
+    let faces = detect_faces(detector, &img, face_detection_threshold)?;
+    match largest_face(&faces)? {
+        Some(face) => Ok(Some(extract_feature(recognizer, &img, &face)?)),
+        None => Ok(None),
+    }
+}
+
+// 计算两个特征的余弦相似度
+pub fn match_score(
+    recognizer: &Ptr<FaceRecognizerSF>,
+    feature1: &Mat,
+    feature2: &Mat,
+) -> Result<f64, String> {
+    recognizer
+        .match_(feature1, feature2, FaceRecognizerSF_DisType::FR_COSINE as i32)
+        .map_err(|e| format!("人脸比对失败: {:?}", e))
+}
+
+// 特征转成 Vec<f32>，用于写入模板库
+pub fn feature_to_vec(feature: &Mat) -> Result<Vec<f32>, String> {
+    let feature = feature
+        .reshape(1, 1)
+        .map_err(|e| format!("转换人脸特征失败: {:?}", e))?;
+    feature
+        .data_typed::<f32>()
+        .map(|data| data.to_vec())
+        .map_err(|e| format!("读取人脸特征失败: {:?}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 一张脸的 YuNet 数据，关键点在框内的固定位置
+    fn face_row(x: f32, y: f32, w: f32, h: f32) -> [f32; FACE_ROW_LEN] {
+        let at = |fx: f32, fy: f32| [x + fx * w, y + fy * h];
+        let [rex, rey] = at(0.3, 0.35);
+        let [lex, ley] = at(0.7, 0.35);
+        let [nx, ny] = at(0.5, 0.55);
+        let [rmx, rmy] = at(0.35, 0.75);
+        let [lmx, lmy] = at(0.65, 0.75);
+        [x, y, w, h, rex, rey, lex, ley, nx, ny, rmx, rmy, lmx, lmy, 0.87]
+    }
+
+    fn faces(rows: &[[f32; FACE_ROW_LEN]]) -> Mat {
+        Mat::from_slice_2d(rows).unwrap()
+    }
+
+    fn assert_close(actual: &[f32; FACE_ROW_LEN], expected: &[f32; FACE_ROW_LEN]) {
+        for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+            assert!((a - e).abs() < 1e-3, "第 {} 列：{} != {}", i, a, e);
+        }
+    }
+
+    #[test]
+    fn working_scale_limits_the_longer_side() {
+        assert_eq!(working_scale(1920, 1080, 640), Some(1.0 / 3.0));
+        // 竖屏按高度缩小
+        assert_eq!(working_scale(720, 1280, 640), Some(0.5));
+        assert_eq!(working_scale(640, 480, 640), None);
+        assert_eq!(working_scale(1920, 1080, 0), None);
+    }
+
+    #[test]
+    fn maps_downscaled_faces_to_the_full_frame() {
+        // 1920x1080 缩小到 640 后检测到的人脸
+        let scale = working_scale(1920, 1080, 640).unwrap();
+        let mut detected = faces(&[face_row(100.0, 50.0, 40.0, 40.0)]);
+        map_faces_back(&mut detected, 1.0 / scale, 0.0, 0.0).unwrap();
+        let mapped = face_values(&detected, 0).unwrap();
+        assert_close(&mapped, &face_row(300.0, 150.0, 120.0, 120.0));
+        assert_eq!(mapped[FACE_SCORE_INDEX], 0.87);
+    }
+
+    #[test]
+    fn maps_roi_faces_to_the_full_frame() {
+        // 与 detect_faces_in_roi 相同：600x400 的检测区域先缩小到 300，再加上区域的左上角
+        let roi = Rect::new(800, 300, 600, 400);
+        let scale = working_scale(roi.width, roi.height, 300).unwrap();
+        let mut detected = faces(&[face_row(50.0, 40.0, 60.0, 60.0), face_row(0.0, 0.0, 10.0, 20.0)]);
+        map_faces_back(&mut detected, 1.0 / scale, 0.0, 0.0).unwrap();
+        map_faces_back(&mut detected, 1.0, roi.x as f32, roi.y as f32).unwrap();
+        assert_close(&face_values(&detected, 0).unwrap(), &face_row(900.0, 380.0, 120.0, 120.0));
+        assert_close(&face_values(&detected, 1).unwrap(), &face_row(800.0, 300.0, 20.0, 40.0));
+    }
+
+    #[test]
+    fn picks_the_largest_face() {
+        let detected = faces(&[
+            face_row(0.0, 0.0, 50.0, 50.0),
+            face_row(100.0, 0.0, 80.0, 90.0),
+            face_row(300.0, 0.0, 90.0, 70.0),
+        ]);
+        let largest = largest_face(&detected).unwrap().unwrap();
+        assert_eq!(face_values(&largest, 0).unwrap()[0], 100.0);
+        assert!(largest_face(&Mat::default()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,221 @@
+// 画面中有多张人脸时的解锁策略
+// 只依赖 serde，由调用方完成检测和比对后传入每张人脸的结果
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MultiFacePolicy {
+    // 只比对最大（最近）的人脸，忽略其他人脸
+    Largest,
+    // 画面中每一张人脸都必须是已录入的面容
+    AllEnrolled,
+    // 比对最大的人脸，其他人脸中有能看清的陌生人时拒绝解锁，太小看不清的人脸忽略
+    RejectUnknown,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MultiFaceConfig {
+    pub policy: MultiFacePolicy,
+    // RejectUnknown 时参与判断的人脸框短边最小像素
+    pub min_face_size: f32,
+}
+
+impl Default for MultiFaceConfig {
+    fn default() -> Self {
+        Self {
+            policy: MultiFacePolicy::Largest,
+            min_face_size: 60.0,
+        }
+    }
+}
+
+impl MultiFaceConfig {
+    // 从设置中读取，缺失时使用默认值
+    pub fn from_options(get: impl Fn(&str) -> Option<String>) -> Self {
+        let default = Self::default();
+        Self {
+            policy: match get("multiFacePolicy").as_deref() {
+                Some("allEnrolled") => MultiFacePolicy::AllEnrolled,
+                Some("rejectUnknown") => MultiFacePolicy::RejectUnknown,
+                _ => MultiFacePolicy::Largest,
+            },
+            min_face_size: get("multiFaceMinSize")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.min_face_size),
+        }
+    }
+}
+
+// 一张人脸的比对结果
+#[derive(Debug, Clone, Copy)]
+pub struct FaceCandidate {
+    // 人脸框 [x, y, w, h]
+    pub rect: [f32; 4],
+    // 相似度最高且超过阈值的面容ID，陌生人为 None
+    pub matched_face_id: Option<i64>,
+    // 最高相似度（0 ~ 1）
+    pub best_score: f64,
+}
+
+impl FaceCandidate {
+    fn area(&self) -> f32 {
+        self.rect[2] * self.rect[3]
+    }
+
+    fn size(&self) -> f32 {
+        self.rect[2].min(self.rect[3])
+    }
+}
+
+// 策略的决定，记录在尝试记录（AttemptLog）中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyDecision {
+    pub policy: MultiFacePolicy,
+    pub face_count: usize,
+    // 参与判断的陌生人脸数量
+    pub unknown_count: usize,
+    // 用于解锁的面容ID
+    pub matched_face_id: Option<i64>,
+    pub allowed: bool,
+    pub reason: Option<String>,
+}
+
+// 按策略决定是否解锁以及使用哪个面容
+pub fn apply_policy(config: &MultiFaceConfig, candidates: &[FaceCandidate]) -> PolicyDecision {
+    let mut decision = PolicyDecision {
+        policy: config.policy,
+        face_count: candidates.len(),
+        unknown_count: 0,
+        matched_face_id: None,
+        allowed: false,
+        reason: None,
+    };
+
+    let largest = match candidates
+        .iter()
+        .max_by(|a, b| a.area().partial_cmp(&b.area()).unwrap_or(std::cmp::Ordering::Equal))
+    {
+        Some(largest) => largest,
+        None => {
+            decision.reason = Some(String::from("未检测到人脸"));
+            return decision;
+        }
+    };
+    decision.matched_face_id = largest.matched_face_id;
+
+    let unknown = |c: &&FaceCandidate| c.matched_face_id.is_none();
+    decision.unknown_count = match config.policy {
+        MultiFacePolicy::Largest => largest.matched_face_id.is_none() as usize,
+        MultiFacePolicy::AllEnrolled => candidates.iter().filter(unknown).count(),
+        MultiFacePolicy::RejectUnknown => candidates
+            .iter()
+            .filter(unknown)
+            .filter(|c| c.size() >= config.min_face_size)
+            .count(),
+    };
+
+    if largest.matched_face_id.is_none() {
+        decision.reason = Some(String::from("最近的人脸不是已录入的面容"));
+    } else if decision.unknown_count > 0 {
+        decision.matched_face_id = None;
+        decision.reason = Some(format!("画面中还有 {} 张陌生人脸", decision.unknown_count));
+    } else {
+        decision.allowed = true;
+    }
+    decision
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn face(size: f32, x: f32, matched_face_id: Option<i64>) -> FaceCandidate {
+        FaceCandidate {
+            rect: [x, 0.0, size, size],
+            matched_face_id,
+            best_score: if matched_face_id.is_some() { 0.8 } else { 0.2 },
+        }
+    }
+
+    fn config(policy: MultiFacePolicy) -> MultiFaceConfig {
+        MultiFaceConfig {
+            policy,
+            ..MultiFaceConfig::default()
+        }
+    }
+
+    #[test]
+    fn largest_ignores_other_faces() {
+        let faces = [face(80.0, 300.0, None), face(200.0, 0.0, Some(1))];
+        let decision = apply_policy(&config(MultiFacePolicy::Largest), &faces);
+        assert!(decision.allowed);
+        assert_eq!(decision.matched_face_id, Some(1));
+        assert_eq!(decision.face_count, 2);
+        assert_eq!(decision.unknown_count, 0);
+
+        // 最大的人脸是陌生人时不解锁，即使较小的人脸匹配
+        let faces = [face(200.0, 0.0, None), face(80.0, 300.0, Some(1))];
+        let decision = apply_policy(&config(MultiFacePolicy::Largest), &faces);
+        assert!(!decision.allowed);
+        assert_eq!(decision.matched_face_id, None);
+        assert_eq!(decision.reason.as_deref(), Some("最近的人脸不是已录入的面容"));
+    }
+
+    #[test]
+    fn reject_unknown_on_visible_stranger() {
+        let faces = [face(200.0, 0.0, Some(1)), face(80.0, 300.0, None)];
+        let decision = apply_policy(&config(MultiFacePolicy::RejectUnknown), &faces);
+        assert!(!decision.allowed);
+        assert_eq!(decision.matched_face_id, None);
+        assert_eq!(decision.unknown_count, 1);
+        assert_eq!(decision.reason.as_deref(), Some("画面中还有 1 张陌生人脸"));
+    }
+
+    #[test]
+    fn reject_unknown_ignores_small_faces() {
+        // 背景中短边小于 min_face_size 的人脸看不清，不参与判断
+        let faces = [face(200.0, 0.0, Some(1)), face(40.0, 300.0, None)];
+        let decision = apply_policy(&config(MultiFacePolicy::RejectUnknown), &faces);
+        assert!(decision.allowed);
+        assert_eq!(decision.matched_face_id, Some(1));
+        assert_eq!(decision.unknown_count, 0);
+    }
+
+    #[test]
+    fn all_enrolled_counts_every_face() {
+        let policy = config(MultiFacePolicy::AllEnrolled);
+        let faces = [face(200.0, 0.0, Some(1)), face(80.0, 300.0, Some(2))];
+        let decision = apply_policy(&policy, &faces);
+        assert!(decision.allowed);
+        assert_eq!(decision.matched_face_id, Some(1));
+
+        // 再小的陌生人脸也会拒绝
+        let faces = [face(200.0, 0.0, Some(1)), face(20.0, 300.0, None), face(30.0, 400.0, None)];
+        let decision = apply_policy(&policy, &faces);
+        assert!(!decision.allowed);
+        assert_eq!(decision.unknown_count, 2);
+    }
+
+    #[test]
+    fn no_faces_is_not_allowed() {
+        let decision = apply_policy(&MultiFaceConfig::default(), &[]);
+        assert!(!decision.allowed);
+        assert_eq!(decision.face_count, 0);
+        assert_eq!(decision.reason.as_deref(), Some("未检测到人脸"));
+    }
+
+    #[test]
+    fn reads_policy_options() {
+        let options = |key: &str| match key {
+            "multiFacePolicy" => Some(String::from("rejectUnknown")),
+            "multiFaceMinSize" => Some(String::from("90")),
+            _ => None,
+        };
+        let config = MultiFaceConfig::from_options(options);
+        assert_eq!(config.policy, MultiFacePolicy::RejectUnknown);
+        assert_eq!(config.min_face_size, 90.0);
+        // 未知的策略按最大人脸处理
+        let config = MultiFaceConfig::from_options(|_| Some(String::from("other")));
+        assert_eq!(config.policy, MultiFacePolicy::Largest);
+    }
+}
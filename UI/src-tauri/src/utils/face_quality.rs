@@ -0,0 +1,389 @@
+// 人脸质量评估：模糊、曝光、人脸大小、平面内旋转、偏航、遮挡
+// 不合格时给出界面可以直接显示的原因
+use opencv::{
+    core::{Mat, Rect},
+    imgproc,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use super::face_pipeline::FACE_ROW_LEN;
+
+// 质量门槛，对应 options 表中的 quality* 设置
+// 界面可以只传部分字段，其余使用默认值
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QualityConfig {
+    // 人脸框短边的最小像素
+    pub min_face_size: f32,
+    // 人脸区域拉普拉斯方差的最小值，越小越模糊
+    pub min_sharpness: f32,
+    // 人脸区域平均亮度范围（0 ~ 255）
+    pub min_brightness: f32,
+    pub max_brightness: f32,
+    // 平面内旋转的最大角度
+    pub max_roll: f32,
+    // 偏航的最大值（鼻尖偏移 / 两眼间距）
+    pub max_yaw: f32,
+    // 上下半脸纹理比的最小值，低于此值认为有遮挡
+    pub min_occlusion_ratio: f32,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        Self {
+            min_face_size: 80.0,
+            min_sharpness: 40.0,
+            min_brightness: 60.0,
+            max_brightness: 210.0,
+            max_roll: 20.0,
+            max_yaw: 0.35,
+            min_occlusion_ratio: 0.3,
+        }
+    }
+}
+
+impl QualityConfig {
+    // 从设置中读取，缺失时使用默认值
+    pub fn from_options(get: impl Fn(&str) -> Option<String>) -> Self {
+        let default = Self::default();
+        let read = |key: &str, fallback: f32| get(key).and_then(|v| v.parse().ok()).unwrap_or(fallback);
+        Self {
+            min_face_size: read("qualityMinFaceSize", default.min_face_size),
+            min_sharpness: read("qualityMinSharpness", default.min_sharpness),
+            min_brightness: read("qualityMinBrightness", default.min_brightness),
+            max_brightness: read("qualityMaxBrightness", default.max_brightness),
+            max_roll: read("qualityMaxRoll", default.max_roll),
+            max_yaw: read("qualityMaxYaw", default.max_yaw),
+            min_occlusion_ratio: read("qualityMinOcclusionRatio", default.min_occlusion_ratio),
+        }
+    }
+}
+
+// 不合格的原因，按检查顺序排列，界面只显示第一个
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum QualityIssue {
+    TooDark,
+    TooBright,
+    TooSmall,
+    Blurry,
+    Tilted,
+    NotFrontal,
+    Occluded,
+}
+
+impl QualityIssue {
+    pub fn message(&self) -> &'static str {
+        match self {
+            QualityIssue::TooDark => "画面太暗，请打开灯光或面向光源",
+            QualityIssue::TooBright => "画面过亮，请避开强光",
+            QualityIssue::TooSmall => "离摄像头太远，请靠近一些",
+            QualityIssue::Blurry => "画面模糊，请保持不动",
+            QualityIssue::Tilted => "头部歪斜，请摆正头部",
+            QualityIssue::NotFrontal => "请正对摄像头",
+            QualityIssue::Occluded => "面部有遮挡，请摘下口罩或移开遮挡物",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityReport {
+    // 综合质量分（0 ~ 1），随模板一起保存
+    pub score: f32,
+    pub sharpness: f32,
+    pub brightness: f32,
+    pub face_size: f32,
+    pub roll: f32,
+    pub yaw: f32,
+    pub occlusion_ratio: f32,
+    pub issues: Vec<QualityIssue>,
+    pub passed: bool,
+    // 第一个不合格原因的提示文本
+    pub reason: Option<String>,
+}
+
+// 评估一张人脸的质量
+// img: BGR 原图，values: YuNet 的一行数据
+pub fn assess_face_quality(
+    img: &Mat,
+    values: &[f32; FACE_ROW_LEN],
+    config: &QualityConfig,
+) -> Result<QualityReport, String> {
+    let face_rect = clamp_rect(img, values[0], values[1], values[2], values[3])
+        .ok_or_else(|| String::from("人脸框超出图片范围"))?;
+    let face = Mat::roi(img, face_rect).map_err(|e| format!("裁剪人脸失败: {:?}", e))?;
+    let mut gray = Mat::default();
+    imgproc::cvt_color(&face, &mut gray, imgproc::COLOR_BGR2GRAY, 0)
+        .map_err(|e| format!("转换灰度图失败: {:?}", e))?;
+
+    let (brightness, _) = mean_std(&gray)?;
+    let sharpness = laplacian_variance(&gray)?;
+    let face_size = values[2].min(values[3]);
+
+    let (right_eye, left_eye, nose) = ([values[4], values[5]], [values[6], values[7]], [values[8], values[9]]);
+    let eye_dx = left_eye[0] - right_eye[0];
+    let eye_dy = left_eye[1] - right_eye[1];
+    let roll = eye_dy.atan2(eye_dx).to_degrees();
+    let inter_eye = (eye_dx * eye_dx + eye_dy * eye_dy).sqrt().max(1.0);
+    let yaw = (nose[0] - (right_eye[0] + left_eye[0]) / 2.0) / inter_eye;
+    let occlusion_ratio = occlusion_ratio(&gray)?;
+
+    let mut issues = Vec::new();
+    if brightness < config.min_brightness {
+        issues.push(QualityIssue::TooDark);
+    }
+    if brightness > config.max_brightness {
+        issues.push(QualityIssue::TooBright);
+    }
+    if face_size < config.min_face_size {
+        issues.push(QualityIssue::TooSmall);
+    }
+    if sharpness < config.min_sharpness {
+        issues.push(QualityIssue::Blurry);
+    }
+    if roll.abs() > config.max_roll {
+        issues.push(QualityIssue::Tilted);
+    }
+    if yaw.abs() > config.max_yaw {
+        issues.push(QualityIssue::NotFrontal);
+    }
+    if occlusion_ratio < config.min_occlusion_ratio || !landmarks_plausible(values) {
+        issues.push(QualityIssue::Occluded);
+    }
+
+    // 每一项归一化到 0 ~ 1 后取平均
+    let exposure_score = 1.0 - ((brightness - 128.0).abs() / 128.0).min(1.0);
+    let parts = [
+        (sharpness / (config.min_sharpness * 3.0)).min(1.0),
+        exposure_score,
+        (face_size / (config.min_face_size * 2.0)).min(1.0),
+        1.0 - (roll.abs() / 90.0).min(1.0),
+        1.0 - yaw.abs().min(1.0),
+        occlusion_ratio.min(1.0),
+    ];
+    let score = parts.iter().sum::<f32>() / parts.len() as f32;
+
+    Ok(QualityReport {
+        score,
+        sharpness,
+        brightness,
+        face_size,
+        roll,
+        yaw,
+        occlusion_ratio,
+        passed: issues.is_empty(),
+        reason: issues.first().map(|i| i.message().to_string()),
+        issues,
+    })
+}
+
+// 拉普拉斯方差，常用的清晰度指标
+pub fn laplacian_variance(gray: &Mat) -> Result<f32, String> {
+    let mut laplacian = Mat::default();
+    imgproc::laplacian(gray, &mut laplacian, opencv::core::CV_64F, 1, 1.0, 0.0, opencv::core::BORDER_DEFAULT)
+        .map_err(|e| format!("计算清晰度失败: {:?}", e))?;
+    let (_, stddev) = mean_std(&laplacian)?;
+    Ok(stddev * stddev)
+}
+
+// 灰度均值和标准差
+pub fn mean_std(gray: &Mat) -> Result<(f32, f32), String> {
+    let mut mean = Mat::default();
+    let mut stddev = Mat::default();
+    opencv::core::mean_std_dev(gray, &mut mean, &mut stddev, &opencv::core::no_array())
+        .map_err(|e| format!("计算亮度失败: {:?}", e))?;
+    let mean = *mean.at::<f64>(0).map_err(|e| format!("读取亮度失败: {:?}", e))?;
+    let stddev = *stddev.at::<f64>(0).map_err(|e| format!("读取亮度失败: {:?}", e))?;
+    Ok((mean as f32, stddev as f32))
+}
+
+// 上半脸（眼睛）和下半脸（嘴巴）的纹理比，口罩、手等遮挡会让一半脸的纹理明显减少
+fn occlusion_ratio(gray: &Mat) -> Result<f32, String> {
+    let half = gray.rows() / 2;
+    if half <= 0 {
+        return Ok(0.0);
+    }
+    let upper = Mat::roi(gray, Rect::new(0, 0, gray.cols(), half))
+        .map_err(|e| format!("裁剪上半脸失败: {:?}", e))?;
+    let lower = Mat::roi(gray, Rect::new(0, half, gray.cols(), gray.rows() - half))
+        .map_err(|e| format!("裁剪下半脸失败: {:?}", e))?;
+    let upper = laplacian_variance(&upper)?;
+    let lower = laplacian_variance(&lower)?;
+    let max = upper.max(lower);
+    if max <= 0.0 {
+        return Ok(0.0);
+    }
+    Ok(upper.min(lower) / max)
+}
+
+// 关键点的相对位置是否合理：眼睛在鼻尖上方，嘴角在鼻尖下方，且都在人脸框内
+fn landmarks_plausible(values: &[f32; FACE_ROW_LEN]) -> bool {
+    let (x, y, w, h) = (values[0], values[1], values[2], values[3]);
+    let inside = (0..5).all(|i| {
+        let (px, py) = (values[4 + i * 2], values[5 + i * 2]);
+        px >= x && px <= x + w && py >= y && py <= y + h
+    });
+    let nose_y = values[9];
+    inside && values[5] < nose_y && values[7] < nose_y && values[11] > nose_y && values[13] > nose_y
+}
+
+fn clamp_rect(img: &Mat, x: f32, y: f32, w: f32, h: f32) -> Option<Rect> {
+    let left = (x.max(0.0)) as i32;
+    let top = (y.max(0.0)) as i32;
+    let right = ((x + w) as i32).min(img.cols());
+    let bottom = ((y + h) as i32).min(img.rows());
+    if right <= left || bottom <= top {
+        return None;
+    }
+    Some(Rect::new(left, top, right - left, bottom - top))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use opencv::core::{self, Scalar, Size, CV_8UC3};
+
+    use super::*;
+
+    // 200x200 的画面，像素在 [low, high) 之间随机，纹理足够清晰
+    fn noise(low: f64, high: f64) -> Mat {
+        let mut img = Mat::new_size_with_default(Size::new(200, 200), CV_8UC3, Scalar::all(0.0)).unwrap();
+        core::randu(&mut img, &Scalar::all(low), &Scalar::all(high)).unwrap();
+        img
+    }
+
+    // 正脸的人脸框和关键点，左上角 (x, y)，边长 size
+    fn face(x: f32, y: f32, size: f32) -> [f32; FACE_ROW_LEN] {
+        let at = |fx: f32, fy: f32| [x + fx * size, y + fy * size];
+        let [rex, rey] = at(0.3, 0.35);
+        let [lex, ley] = at(0.7, 0.35);
+        let [nx, ny] = at(0.5, 0.55);
+        let [rmx, rmy] = at(0.35, 0.75);
+        let [lmx, lmy] = at(0.65, 0.75);
+        [x, y, size, size, rex, rey, lex, ley, nx, ny, rmx, rmy, lmx, lmy, 0.9]
+    }
+
+    fn assess(img: &Mat, values: &[f32; FACE_ROW_LEN]) -> QualityReport {
+        assess_face_quality(img, values, &QualityConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn clear_frontal_face_passes() {
+        let report = assess(&noise(0.0, 255.0), &face(40.0, 40.0, 120.0));
+        assert!(report.passed, "{:?}", report.issues);
+        assert_eq!(report.reason, None);
+        assert_eq!(report.face_size, 120.0);
+        assert!(report.roll.abs() < 0.01 && report.yaw.abs() < 0.01);
+        assert!(report.score > 0.5 && report.score <= 1.0);
+    }
+
+    #[test]
+    fn exposure_and_size_have_their_own_reasons() {
+        let dark = assess(&noise(0.0, 40.0), &face(40.0, 40.0, 120.0));
+        assert_eq!(dark.issues, vec![QualityIssue::TooDark]);
+        assert_eq!(dark.reason.as_deref(), Some("画面太暗，请打开灯光或面向光源"));
+
+        let bright = assess(&noise(220.0, 255.0), &face(40.0, 40.0, 120.0));
+        assert_eq!(bright.issues, vec![QualityIssue::TooBright]);
+
+        let small = assess(&noise(0.0, 255.0), &face(40.0, 40.0, 60.0));
+        assert_eq!(small.issues, vec![QualityIssue::TooSmall]);
+        assert_eq!(small.reason.as_deref(), Some("离摄像头太远，请靠近一些"));
+    }
+
+    #[test]
+    fn reason_is_the_first_issue() {
+        // 没有纹理的画面既模糊又无法区分上下半脸，只提示第一个原因
+        let flat = Mat::new_size_with_default(Size::new(200, 200), CV_8UC3, Scalar::all(128.0)).unwrap();
+        let report = assess(&flat, &face(40.0, 40.0, 120.0));
+        assert_eq!(report.issues, vec![QualityIssue::Blurry, QualityIssue::Occluded]);
+        assert_eq!(report.reason.as_deref(), Some(QualityIssue::Blurry.message()));
+        assert!(!report.passed);
+    }
+
+    #[test]
+    fn pose_thresholds() {
+        // 左眼比右眼低 0.17 倍脸宽，约 23 度
+        let mut tilted = face(40.0, 40.0, 120.0);
+        tilted[7] += 0.17 * 120.0;
+        let report = assess(&noise(0.0, 255.0), &tilted);
+        assert_eq!(report.issues, vec![QualityIssue::Tilted]);
+        assert!(report.roll > 20.0);
+
+        // 鼻尖偏向一侧
+        let mut turned = face(40.0, 40.0, 120.0);
+        turned[8] = 40.0 + 0.75 * 120.0;
+        let report = assess(&noise(0.0, 255.0), &turned);
+        assert_eq!(report.issues, vec![QualityIssue::NotFrontal]);
+        assert!(report.yaw > 0.35);
+
+        // 放宽门槛后通过
+        let config = QualityConfig {
+            max_roll: 30.0,
+            ..QualityConfig::default()
+        };
+        assert!(assess_face_quality(&noise(0.0, 255.0), &tilted, &config).unwrap().passed);
+    }
+
+    #[test]
+    fn detects_occlusion() {
+        // 下半脸没有纹理，模拟口罩
+        let mut img = noise(0.0, 255.0);
+        let mut lower = Mat::roi_mut(&mut img, Rect::new(40, 100, 120, 60)).unwrap();
+        lower.set_to(&Scalar::all(128.0), &core::no_array()).unwrap();
+        let report = assess(&img, &face(40.0, 40.0, 120.0));
+        assert_eq!(report.issues, vec![QualityIssue::Occluded]);
+        assert!(report.occlusion_ratio < 0.3);
+
+        // 嘴角在鼻尖上方的关键点不合理，同样按遮挡处理
+        let mut values = face(40.0, 40.0, 120.0);
+        values[11] = values[9] - 10.0;
+        let report = assess(&noise(0.0, 255.0), &values);
+        assert_eq!(report.issues, vec![QualityIssue::Occluded]);
+    }
+
+    #[test]
+    fn rejects_faces_outside_the_image() {
+        let outside = face(300.0, 300.0, 80.0);
+        assert!(assess_face_quality(&noise(0.0, 255.0), &outside, &QualityConfig::default()).is_err());
+    }
+
+    #[test]
+    fn reads_thresholds_from_options() {
+        let options = HashMap::from([
+            ("qualityMinFaceSize", "100"),
+            ("qualityMaxYaw", "0.5"),
+            ("qualityMinSharpness", "abc"),
+        ]);
+        let config = QualityConfig::from_options(|key| options.get(key).map(|v| v.to_string()));
+        let default = QualityConfig::default();
+        assert_eq!(config.min_face_size, 100.0);
+        assert_eq!(config.max_yaw, 0.5);
+        // 无法解析和缺失的设置使用默认值
+        assert_eq!(config.min_sharpness, default.min_sharpness);
+        assert_eq!(config.max_roll, default.max_roll);
+
+        // 界面可以只传部分字段
+        let config: QualityConfig = serde_json::from_str(r#"{"min_brightness": 30}"#).unwrap();
+        assert_eq!(config.min_brightness, 30.0);
+        assert_eq!(config.max_brightness, default.max_brightness);
+    }
+
+    #[test]
+    fn issues_serialize_for_the_ui() {
+        assert_eq!(serde_json::to_string(&QualityIssue::NotFrontal).unwrap(), "\"notFrontal\"");
+        let issues = [
+            QualityIssue::TooDark,
+            QualityIssue::TooBright,
+            QualityIssue::TooSmall,
+            QualityIssue::Blurry,
+            QualityIssue::Tilted,
+            QualityIssue::NotFrontal,
+            QualityIssue::Occluded,
+        ];
+        let messages: std::collections::HashSet<&str> = issues.iter().map(|i| i.message()).collect();
+        assert_eq!(messages.len(), issues.len());
+    }
+}
@@ -0,0 +1,188 @@
+// 简单的人脸跟踪：按相邻两帧人脸框的 IoU 关联，给同一张脸一个固定的 track id
+// 多帧活体投票、关键点轨迹等都按 track id 区分不同的人脸
+
+// IoU 低于此值视为不同的人脸
+const DEFAULT_IOU_THRESHOLD: f32 = 0.3;
+// 连续多少帧没有匹配到就丢弃
+const DEFAULT_MAX_MISSED: u32 = 5;
+
+struct Track {
+    id: u32,
+    // x, y, w, h
+    rect: [f32; 4],
+    missed: u32,
+}
+
+pub struct FaceTracker {
+    tracks: Vec<Track>,
+    next_id: u32,
+    iou_threshold: f32,
+    max_missed: u32,
+}
+
+impl Default for FaceTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_IOU_THRESHOLD, DEFAULT_MAX_MISSED)
+    }
+}
+
+impl FaceTracker {
+    pub fn new(iou_threshold: f32, max_missed: u32) -> Self {
+        Self {
+            tracks: Vec::new(),
+            next_id: 1,
+            iou_threshold,
+            max_missed,
+        }
+    }
+
+    // 传入当前帧的所有人脸框，返回每个框对应的 track id
+    pub fn update(&mut self, rects: &[[f32; 4]]) -> Vec<u32> {
+        let mut ids = vec![0u32; rects.len()];
+        let mut matched = vec![false; self.tracks.len()];
+
+        // 贪心匹配：先按 IoU 从高到低排好所有组合
+        let mut pairs: Vec<(f32, usize, usize)> = Vec::new();
+        for (rect_index, rect) in rects.iter().enumerate() {
+            for (track_index, track) in self.tracks.iter().enumerate() {
+                let overlap = iou(rect, &track.rect);
+                if overlap >= self.iou_threshold {
+                    pairs.push((overlap, rect_index, track_index));
+                }
+            }
+        }
+        pairs.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        for (_, rect_index, track_index) in pairs {
+            if ids[rect_index] != 0 || matched[track_index] {
+                continue;
+            }
+            let track = &mut self.tracks[track_index];
+            track.rect = rects[rect_index];
+            track.missed = 0;
+            ids[rect_index] = track.id;
+            matched[track_index] = true;
+        }
+
+        // 没匹配上的轨迹累计丢失次数
+        for (track, is_matched) in self.tracks.iter_mut().zip(matched.iter()) {
+            if !is_matched {
+                track.missed += 1;
+            }
+        }
+        let max_missed = self.max_missed;
+        self.tracks.retain(|t| t.missed <= max_missed);
+
+        // 没匹配上的人脸新建轨迹
+        for (rect_index, id) in ids.iter_mut().enumerate() {
+            if *id == 0 {
+                *id = self.next_id;
+                self.next_id += 1;
+                self.tracks.push(Track {
+                    id: *id,
+                    rect: rects[rect_index],
+                    missed: 0,
+                });
+            }
+        }
+
+        ids
+    }
+
+    // 当前仍在跟踪的 id
+    pub fn active_ids(&self) -> Vec<u32> {
+        self.tracks.iter().map(|t| t.id).collect()
+    }
+
+    pub fn reset(&mut self) {
+        self.tracks.clear();
+    }
+}
+
+// 两个框的交并比
+pub fn iou(a: &[f32; 4], b: &[f32; 4]) -> f32 {
+    let left = a[0].max(b[0]);
+    let top = a[1].max(b[1]);
+    let right = (a[0] + a[2]).min(b[0] + b[2]);
+    let bottom = (a[1] + a[3]).min(b[1] + b[3]);
+    if right <= left || bottom <= top {
+        return 0.0;
+    }
+
+    let intersection = (right - left) * (bottom - top);
+    let union = a[2] * a[3] + b[2] * b[3] - intersection;
+    if union <= 0.0 {
+        0.0
+    } else {
+        intersection / union
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 按每帧的位移平移人脸框
+    fn shift(rect: [f32; 4], dx: f32, dy: f32) -> [f32; 4] {
+        [rect[0] + dx, rect[1] + dy, rect[2], rect[3]]
+    }
+
+    #[test]
+    fn moving_face_keeps_its_id() {
+        let mut tracker = FaceTracker::default();
+        let mut rect = [100.0, 100.0, 80.0, 80.0];
+        let first = tracker.update(&[rect]);
+        for _ in 0..10 {
+            rect = shift(rect, 5.0, 3.0);
+            assert_eq!(tracker.update(&[rect]), first);
+        }
+        assert_eq!(tracker.active_ids(), first);
+    }
+
+    #[test]
+    fn two_faces_keep_ids_when_order_changes() {
+        let mut tracker = FaceTracker::default();
+        let left = [50.0, 100.0, 80.0, 80.0];
+        let right = [300.0, 100.0, 80.0, 80.0];
+        let ids = tracker.update(&[left, right]);
+        assert_ne!(ids[0], ids[1]);
+        // 检测结果的顺序变了，id 仍然跟着位置走
+        let swapped = tracker.update(&[shift(right, 4.0, 0.0), shift(left, -4.0, 0.0)]);
+        assert_eq!(swapped, vec![ids[1], ids[0]]);
+    }
+
+    #[test]
+    fn jump_starts_a_new_track() {
+        let mut tracker = FaceTracker::default();
+        let first = tracker.update(&[[100.0, 100.0, 80.0, 80.0]]);
+        // 没有重叠，视为另一张脸
+        let second = tracker.update(&[[400.0, 100.0, 80.0, 80.0]]);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn short_gaps_keep_the_track() {
+        let mut tracker = FaceTracker::new(DEFAULT_IOU_THRESHOLD, 2);
+        let rect = [100.0, 100.0, 80.0, 80.0];
+        let first = tracker.update(&[rect]);
+        // 丢失 2 帧以内仍然是同一张脸
+        tracker.update(&[]);
+        tracker.update(&[]);
+        assert_eq!(tracker.update(&[rect]), first);
+        // 丢失超过 2 帧后重新出现，分配新的 id
+        for _ in 0..3 {
+            tracker.update(&[]);
+        }
+        assert!(tracker.active_ids().is_empty());
+        assert_ne!(tracker.update(&[rect]), first);
+    }
+
+    #[test]
+    fn iou_of_boxes() {
+        let a = [0.0, 0.0, 10.0, 10.0];
+        assert_eq!(iou(&a, &a), 1.0);
+        assert_eq!(iou(&a, &[20.0, 20.0, 10.0, 10.0]), 0.0);
+        // 重叠一半：交集 50，并集 150
+        assert!((iou(&a, &[5.0, 0.0, 10.0, 10.0]) - 1.0 / 3.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,225 @@
+// 摄像头画面格式统一
+// 检测和识别都需要 8 位 3 通道 BGR，红外（Windows Hello）摄像头常输出单通道、16 位或 YUYV 画面，先在这里转换
+use opencv::{
+    core::{self, Mat, CV_16UC1, CV_8U, CV_8UC1, CV_8UC2, CV_8UC3, CV_8UC4},
+    imgcodecs, imgproc,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+// 三个通道的平均差异小于此值时认为是灰度画面（部分红外摄像头会输出三个通道相同的 BGR）
+const GRAY_CHANNEL_DIFF: f64 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FrameFormat {
+    Bgr,
+    Bgra,
+    Gray,
+    Gray16,
+    Yuyv,
+}
+
+// 成像方式，红外的探针只和红外模板比对
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Modality {
+    Rgb,
+    Ir,
+}
+
+impl Default for Modality {
+    // 旧模板没有记录成像方式，都是彩色摄像头录入的
+    fn default() -> Self {
+        Modality::Rgb
+    }
+}
+
+impl Modality {
+    pub fn from_options(value: Option<&str>) -> Option<Self> {
+        match value {
+            Some("rgb") => Some(Modality::Rgb),
+            Some("ir") => Some(Modality::Ir),
+            _ => None,
+        }
+    }
+}
+
+pub struct NormalizedFrame {
+    // 8 位 BGR 画面
+    pub bgr: Mat,
+    pub format: FrameFormat,
+    pub modality: Modality,
+}
+
+// 按 Mat 的类型判断画面格式
+pub fn frame_format(frame: &Mat) -> Result<FrameFormat, String> {
+    match frame.typ() {
+        t if t == CV_8UC3 => Ok(FrameFormat::Bgr),
+        t if t == CV_8UC4 => Ok(FrameFormat::Bgra),
+        t if t == CV_8UC1 => Ok(FrameFormat::Gray),
+        t if t == CV_16UC1 => Ok(FrameFormat::Gray16),
+        t if t == CV_8UC2 => Ok(FrameFormat::Yuyv),
+        t => Err(format!("不支持的画面格式（类型 {}）", t)),
+    }
+}
+
+// 转换为 8 位 BGR，并判断成像方式
+// modality_hint: 摄像头设置中指定的成像方式，None 时按画面自动判断
+pub fn normalize_frame(frame: &Mat, modality_hint: Option<Modality>) -> Result<NormalizedFrame, String> {
+    if frame.empty() {
+        return Err(String::from("画面为空"));
+    }
+    let format = frame_format(frame)?;
+
+    let mut bgr = Mat::default();
+    match format {
+        FrameFormat::Bgr => {
+            bgr = frame.try_clone().map_err(|e| format!("复制画面失败: {:?}", e))?;
+        }
+        FrameFormat::Bgra => convert(frame, &mut bgr, imgproc::COLOR_BGRA2BGR)?,
+        FrameFormat::Gray => convert(frame, &mut bgr, imgproc::COLOR_GRAY2BGR)?,
+        FrameFormat::Yuyv => convert(frame, &mut bgr, imgproc::COLOR_YUV2BGR_YUY2)?,
+        FrameFormat::Gray16 => {
+            // 红外画面的有效范围通常很窄，按实际最小/最大值拉伸到 0 ~ 255
+            let mut gray = Mat::default();
+            core::normalize(frame, &mut gray, 0.0, 255.0, core::NORM_MINMAX, CV_8U, &core::no_array())
+                .map_err(|e| format!("转换 16 位画面失败: {:?}", e))?;
+            convert(&gray, &mut bgr, imgproc::COLOR_GRAY2BGR)?;
+        }
+    }
+
+    let modality = match modality_hint {
+        Some(modality) => modality,
+        None => match format {
+            FrameFormat::Gray | FrameFormat::Gray16 => Modality::Ir,
+            _ if is_grayscale(&bgr)? => Modality::Ir,
+            _ => Modality::Rgb,
+        },
+    };
+
+    Ok(NormalizedFrame { bgr, format, modality })
+}
+
+// 按原始格式读取图片文件（保留单通道和 16 位）再统一格式，用于红外样本图片
+pub fn read_normalized(path: &str, modality_hint: Option<Modality>) -> Result<NormalizedFrame, String> {
+    let img = imgcodecs::imread(path, imgcodecs::IMREAD_UNCHANGED)
+        .map_err(|e| format!("读取图片 {} 失败: {:?}", path, e))?;
+    if img.empty() {
+        return Err(format!("图片 {} 为空或格式不支持", path));
+    }
+    normalize_frame(&img, modality_hint)
+}
+
+// 三个通道几乎一致的 BGR 画面
+pub fn is_grayscale(bgr: &Mat) -> Result<bool, String> {
+    let mut channels = core::Vector::<Mat>::new();
+    core::split(bgr, &mut channels).map_err(|e| format!("拆分通道失败: {:?}", e))?;
+    if channels.len() < 3 {
+        return Ok(true);
+    }
+    let mut max_diff = 0f64;
+    for (a, b) in [(0, 1), (1, 2)] {
+        let mut diff = Mat::default();
+        core::absdiff(
+            &channels.get(a).map_err(|e| format!("读取通道失败: {:?}", e))?,
+            &channels.get(b).map_err(|e| format!("读取通道失败: {:?}", e))?,
+            &mut diff,
+        )
+        .map_err(|e| format!("比较通道失败: {:?}", e))?;
+        let mean = core::mean(&diff, &core::no_array()).map_err(|e| format!("比较通道失败: {:?}", e))?;
+        max_diff = max_diff.max(mean[0]);
+    }
+    Ok(max_diff < GRAY_CHANNEL_DIFF)
+}
+
+// 探针能否与模板比对
+pub fn modality_compatible(probe: Modality, template: Modality) -> bool {
+    probe == template
+}
+
+fn convert(src: &Mat, dst: &mut Mat, code: i32) -> Result<(), String> {
+    imgproc::cvt_color(src, dst, code, 0).map_err(|e| format!("转换画面格式失败: {:?}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use opencv::core::{Scalar, Size, Vector};
+
+    use super::*;
+
+    // 按原始格式解码 tests/fixtures/frames 下的图片，与 read_normalized 读取文件时相同
+    fn decode(bytes: &[u8]) -> Mat {
+        imgcodecs::imdecode(&Vector::<u8>::from_slice(bytes), imgcodecs::IMREAD_UNCHANGED).unwrap()
+    }
+
+    // 转回单通道后的最小、最大值，min_max_loc 不支持多通道
+    fn min_max(bgr: &Mat) -> (f64, f64) {
+        let mut gray = Mat::default();
+        convert(bgr, &mut gray, imgproc::COLOR_BGR2GRAY).unwrap();
+        let (mut min, mut max) = (0.0, 0.0);
+        core::min_max_loc(&gray, Some(&mut min), Some(&mut max), None, None, &core::no_array()).unwrap();
+        (min, max)
+    }
+
+    #[test]
+    fn stretches_gray16_ir_frames() {
+        let frame = decode(include_bytes!("../../tests/fixtures/frames/ir_gray16.png"));
+        let normalized = normalize_frame(&frame, None).unwrap();
+        assert_eq!(normalized.format, FrameFormat::Gray16);
+        assert_eq!(normalized.modality, Modality::Ir);
+        assert_eq!(normalized.bgr.typ(), CV_8UC3);
+        // 原图只用到 1000 ~ 1310，拉伸后占满 0 ~ 255
+        assert_eq!(min_max(&normalized.bgr), (0.0, 255.0));
+    }
+
+    #[test]
+    fn single_channel_frames_are_ir() {
+        let frame = decode(include_bytes!("../../tests/fixtures/frames/ir_gray8.png"));
+        let normalized = normalize_frame(&frame, None).unwrap();
+        assert_eq!(normalized.format, FrameFormat::Gray);
+        assert_eq!(normalized.modality, Modality::Ir);
+        assert_eq!(normalized.bgr.typ(), CV_8UC3);
+    }
+
+    #[test]
+    fn bgr_with_identical_channels_is_ir() {
+        let frame = decode(include_bytes!("../../tests/fixtures/frames/ir_bgr.png"));
+        let normalized = normalize_frame(&frame, None).unwrap();
+        assert_eq!(normalized.format, FrameFormat::Bgr);
+        assert_eq!(normalized.modality, Modality::Ir);
+    }
+
+    #[test]
+    fn color_frames_are_rgb() {
+        let frame = decode(include_bytes!("../../tests/fixtures/frames/rgb.png"));
+        let normalized = normalize_frame(&frame, None).unwrap();
+        assert_eq!(normalized.format, FrameFormat::Bgr);
+        assert_eq!(normalized.modality, Modality::Rgb);
+    }
+
+    #[test]
+    fn modality_hint_overrides_detection() {
+        let frame = decode(include_bytes!("../../tests/fixtures/frames/ir_gray8.png"));
+        let normalized = normalize_frame(&frame, Some(Modality::Rgb)).unwrap();
+        assert_eq!(normalized.modality, Modality::Rgb);
+    }
+
+    #[test]
+    fn converts_yuyv_frames() {
+        // 关闭 RGB 转换后 DirectShow 读出的 YUY2 画面为 2 通道
+        let gray = Scalar::new(128.0, 128.0, 0.0, 0.0);
+        let frame = Mat::new_size_with_default(Size::new(32, 24), CV_8UC2, gray).unwrap();
+        let normalized = normalize_frame(&frame, None).unwrap();
+        assert_eq!(normalized.format, FrameFormat::Yuyv);
+        assert_eq!(normalized.bgr.typ(), CV_8UC3);
+        assert_eq!(normalized.bgr.size().unwrap(), Size::new(32, 24));
+    }
+
+    #[test]
+    fn rejects_empty_and_unsupported_frames() {
+        assert!(normalize_frame(&Mat::default(), None).is_err());
+        let float = Mat::new_size_with_default(Size::new(8, 8), core::CV_32FC1, Scalar::all(0.0)).unwrap();
+        assert!(normalize_frame(&float, None).is_err());
+    }
+}
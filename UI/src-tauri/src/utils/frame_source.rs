@@ -0,0 +1,476 @@
+// 画面来源：摄像头、图片目录、视频文件、合成画面、回放文件
+// APP_STATE.camera 保存的是 FrameSource，识别流程不关心画面从哪里来，没有摄像头的机器也能跑完整流程
+// 只依赖 opencv，离线评估工具在 Linux 上也用这里的实现
+//
+// open_camera 的 source 参数：
+//   file:///D:/faces/demo         图片目录，按文件名顺序循环播放
+//   file:///D:/faces/demo.mp4     视频文件，播放完从头开始
+//   file:///D:/faces/001.jpg      单张图片，一直返回同一帧
+//   file:///D:/replays/a.fwreplay 解锁尝试的回放文件，按录制时的间隔播放一遍，见 replay
+//   synthetic://640x480?pattern=bars&image=D:/faces/001.jpg
+//                                 合成画面，pattern 为 bars / noise / gray，image 为叠加在画面中晃动的图片
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use opencv::{
+    core::{self, Mat, Point, Rect, Scalar, Size, CV_8UC3},
+    imgcodecs, imgproc,
+    prelude::*,
+    videoio::{self, VideoCapture, VideoCaptureTrait, VideoCaptureTraitConst},
+};
+use serde::Serialize;
+
+use super::replay::{ReplaySource, REPLAY_EXTENSION};
+
+const IMAGE_EXTENSIONS: [&str; 5] = ["jpg", "jpeg", "png", "bmp", "faceimg"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SourceKind {
+    Camera,
+    Directory,
+    Video,
+    Synthetic,
+    Replay,
+}
+
+// 画面来源，read 的签名与 VideoCapture::read 一致，原来读摄像头的代码不需要修改
+pub trait FrameSource {
+    // 读取下一帧，没有更多画面时返回 false
+    fn read(&mut self, frame: &mut Mat) -> opencv::Result<bool>;
+    fn kind(&self) -> SourceKind;
+    // 写日志和返回给界面用的描述
+    fn describe(&self) -> String;
+    // 摄像头的 VideoCapture，用来修改采集参数，其他来源为 None
+    fn capture_mut(&mut self) -> Option<&mut VideoCapture> {
+        None
+    }
+    // 距离下一帧还要等多久，只有按录制间隔播放的回放文件返回 Some
+    // read 本身不等待，调用方应在不持有 APP_STATE 时先等待再读取
+    fn next_frame_in(&mut self) -> Option<Duration> {
+        None
+    }
+}
+
+// 摄像头
+pub struct CameraSource {
+    capture: VideoCapture,
+    description: String,
+}
+
+impl CameraSource {
+    pub fn new(capture: VideoCapture, description: String) -> Self {
+        Self { capture, description }
+    }
+}
+
+impl FrameSource for CameraSource {
+    fn read(&mut self, frame: &mut Mat) -> opencv::Result<bool> {
+        VideoCaptureTrait::read(&mut self.capture, frame)
+    }
+
+    fn kind(&self) -> SourceKind {
+        SourceKind::Camera
+    }
+
+    fn describe(&self) -> String {
+        self.description.clone()
+    }
+
+    fn capture_mut(&mut self) -> Option<&mut VideoCapture> {
+        Some(&mut self.capture)
+    }
+}
+
+// 图片目录，按文件名排序后循环播放；只有一张图片时一直返回这一张
+pub struct DirectorySource {
+    dir: PathBuf,
+    images: Vec<PathBuf>,
+    next: usize,
+    // 单张图片时缓存解码结果
+    cached: Option<Mat>,
+}
+
+impl DirectorySource {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let images = if path.is_dir() {
+            let mut images: Vec<PathBuf> = fs::read_dir(path)
+                .map_err(|e| format!("读取目录 {} 失败: {}", path.display(), e))?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| p.is_file() && is_image(p))
+                .collect();
+            images.sort();
+            images
+        } else {
+            vec![path.to_path_buf()]
+        };
+        if images.is_empty() {
+            return Err(format!("{} 中没有图片", path.display()));
+        }
+        Ok(Self {
+            dir: path.to_path_buf(),
+            images,
+            next: 0,
+            cached: None,
+        })
+    }
+}
+
+impl FrameSource for DirectorySource {
+    fn read(&mut self, frame: &mut Mat) -> opencv::Result<bool> {
+        if let Some(cached) = &self.cached {
+            *frame = cached.try_clone()?;
+            return Ok(true);
+        }
+        let path = &self.images[self.next];
+        self.next = (self.next + 1) % self.images.len();
+        // 保留单通道、16 位等原始格式，与摄像头一样交给 normalize_frame 处理
+        *frame = imgcodecs::imread(path.to_str().unwrap_or(""), imgcodecs::IMREAD_UNCHANGED)?;
+        if self.images.len() == 1 && !frame.empty() {
+            self.cached = Some(frame.try_clone()?);
+        }
+        Ok(!frame.empty())
+    }
+
+    fn kind(&self) -> SourceKind {
+        SourceKind::Directory
+    }
+
+    fn describe(&self) -> String {
+        format!("图片 {}（{} 张）", self.dir.display(), self.images.len())
+    }
+}
+
+// 视频文件，播放完从头开始
+pub struct VideoFileSource {
+    path: PathBuf,
+    capture: VideoCapture,
+}
+
+impl VideoFileSource {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let capture = VideoCapture::from_file(path.to_str().unwrap_or(""), videoio::CAP_ANY)
+            .map_err(|e| format!("打开视频 {} 失败: {:?}", path.display(), e))?;
+        if !capture.is_opened().unwrap_or(false) {
+            return Err(format!("无法打开视频 {}", path.display()));
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            capture,
+        })
+    }
+}
+
+impl FrameSource for VideoFileSource {
+    fn read(&mut self, frame: &mut Mat) -> opencv::Result<bool> {
+        if VideoCaptureTrait::read(&mut self.capture, frame)? && !frame.empty() {
+            return Ok(true);
+        }
+        self.capture.set(videoio::CAP_PROP_POS_FRAMES, 0.0)?;
+        VideoCaptureTrait::read(&mut self.capture, frame)
+    }
+
+    fn kind(&self) -> SourceKind {
+        SourceKind::Video
+    }
+
+    fn describe(&self) -> String {
+        format!("视频 {}", self.path.display())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyntheticPattern {
+    // 移动的彩条
+    Bars,
+    // 随机噪声
+    Noise,
+    // 纯灰色
+    Gray,
+}
+
+// 合成画面，可以叠加一张图片并让它缓慢晃动，模拟人在镜头前的小幅移动
+pub struct SyntheticSource {
+    size: Size,
+    pattern: SyntheticPattern,
+    overlay: Option<Mat>,
+    frame_index: u64,
+}
+
+impl SyntheticSource {
+    pub fn new(size: Size, pattern: SyntheticPattern, overlay: Option<Mat>) -> Self {
+        Self {
+            size,
+            pattern,
+            overlay,
+            frame_index: 0,
+        }
+    }
+
+    fn background(&self) -> opencv::Result<Mat> {
+        let mut frame = Mat::new_size_with_default(self.size, CV_8UC3, Scalar::all(128.0))?;
+        match self.pattern {
+            SyntheticPattern::Gray => {}
+            SyntheticPattern::Noise => {
+                core::randu(&mut frame, &Scalar::all(0.0), &Scalar::all(255.0))?;
+            }
+            SyntheticPattern::Bars => {
+                let colors = [
+                    Scalar::new(255.0, 255.0, 255.0, 0.0),
+                    Scalar::new(0.0, 255.0, 255.0, 0.0),
+                    Scalar::new(255.0, 255.0, 0.0, 0.0),
+                    Scalar::new(0.0, 255.0, 0.0, 0.0),
+                    Scalar::new(255.0, 0.0, 255.0, 0.0),
+                    Scalar::new(0.0, 0.0, 255.0, 0.0),
+                    Scalar::new(255.0, 0.0, 0.0, 0.0),
+                ];
+                let bar_width = (self.size.width / colors.len() as i32).max(1);
+                let shift = (self.frame_index as i32 * 4) % self.size.width.max(1);
+                for (i, color) in colors.iter().enumerate() {
+                    let x = (i as i32 * bar_width + shift) % self.size.width.max(1);
+                    let width = bar_width.min(self.size.width - x);
+                    imgproc::rectangle(
+                        &mut frame,
+                        Rect::new(x, 0, width, self.size.height),
+                        *color,
+                        imgproc::FILLED,
+                        imgproc::LINE_8,
+                        0,
+                    )?;
+                }
+            }
+        }
+        Ok(frame)
+    }
+}
+
+impl FrameSource for SyntheticSource {
+    fn read(&mut self, frame: &mut Mat) -> opencv::Result<bool> {
+        let mut background = self.background()?;
+        if let Some(overlay) = &self.overlay {
+            // 叠加的图片缩放到画面高度的 60%，沿小圆周晃动
+            let scale = self.size.height as f64 * 0.6 / overlay.rows().max(1) as f64;
+            let mut resized = Mat::default();
+            imgproc::resize(overlay, &mut resized, Size::default(), scale, scale, imgproc::INTER_AREA)?;
+            let width = resized.cols().min(self.size.width);
+            let height = resized.rows().min(self.size.height);
+            let angle = self.frame_index as f64 * 0.1;
+            let center = Point::new(
+                self.size.width / 2 + (angle.cos() * 10.0) as i32,
+                self.size.height / 2 + (angle.sin() * 10.0) as i32,
+            );
+            let x = (center.x - width / 2).clamp(0, self.size.width - width);
+            let y = (center.y - height / 2).clamp(0, self.size.height - height);
+            let src = Mat::roi(&resized, Rect::new(0, 0, width, height))?;
+            let mut dst = Mat::roi_mut(&mut background, Rect::new(x, y, width, height))?;
+            src.copy_to(&mut dst)?;
+        }
+        *frame = background;
+        self.frame_index += 1;
+        Ok(true)
+    }
+
+    fn kind(&self) -> SourceKind {
+        SourceKind::Synthetic
+    }
+
+    fn describe(&self) -> String {
+        format!("合成画面 {}x{} {:?}", self.size.width, self.size.height, self.pattern)
+    }
+}
+
+// 是否为 file:// 或 synthetic:// 形式的画面来源，其余按摄像头处理
+pub fn is_source_uri(source: &str) -> bool {
+    source.starts_with("file://") || source.starts_with("synthetic://")
+}
+
+// 按 URI 打开画面来源
+pub fn open_source_uri(source: &str) -> Result<Box<dyn FrameSource>, String> {
+    if let Some(path) = source.strip_prefix("file://") {
+        let path = file_uri_path(path);
+        if !path.exists() {
+            return Err(format!("{} 不存在", path.display()));
+        }
+        if path.extension().is_some_and(|e| e == REPLAY_EXTENSION) {
+            return Ok(Box::new(ReplaySource::open(&path, true)?));
+        }
+        if path.is_dir() || is_image(&path) {
+            return Ok(Box::new(DirectorySource::open(&path)?));
+        }
+        return Ok(Box::new(VideoFileSource::open(&path)?));
+    }
+
+    if let Some(rest) = source.strip_prefix("synthetic://") {
+        let (size_part, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let size = match size_part.split_once('x') {
+            Some((w, h)) => Size::new(
+                w.parse().map_err(|_| format!("合成画面宽度错误: {}", w))?,
+                h.parse().map_err(|_| format!("合成画面高度错误: {}", h))?,
+            ),
+            None if size_part.is_empty() => Size::new(640, 480),
+            None => return Err(format!("合成画面尺寸格式应为 宽x高: {}", size_part)),
+        };
+        if size.width <= 0 || size.height <= 0 {
+            return Err(String::from("合成画面尺寸必须大于 0"));
+        }
+
+        let mut pattern = SyntheticPattern::Bars;
+        let mut overlay = None;
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match key {
+                "pattern" => {
+                    pattern = match value {
+                        "bars" => SyntheticPattern::Bars,
+                        "noise" => SyntheticPattern::Noise,
+                        "gray" => SyntheticPattern::Gray,
+                        other => return Err(format!("未知的合成画面样式 {}", other)),
+                    }
+                }
+                "image" => {
+                    let img = imgcodecs::imread(value, imgcodecs::IMREAD_COLOR)
+                        .map_err(|e| format!("读取图片 {} 失败: {:?}", value, e))?;
+                    if img.empty() {
+                        return Err(format!("图片 {} 为空或格式不支持", value));
+                    }
+                    overlay = Some(img);
+                }
+                other => return Err(format!("未知的合成画面参数 {}", other)),
+            }
+        }
+        return Ok(Box::new(SyntheticSource::new(size, pattern, overlay)));
+    }
+
+    Err(format!("不支持的画面来源 {}", source))
+}
+
+// file:///D:/a 在 Windows 上是 D:/a，在 Linux 上 file:///home/a 是 /home/a
+// 路径中的 %20、%E4%BA%BA 等按 UTF-8 解码，从资源管理器复制的带空格、中文的路径也能打开
+fn file_uri_path(path: &str) -> PathBuf {
+    let path = percent_decode(path);
+    let bytes = path.as_bytes();
+    if bytes.len() >= 3 && bytes[0] == b'/' && bytes[2] == b':' {
+        PathBuf::from(&path[1..])
+    } else {
+        PathBuf::from(path)
+    }
+}
+
+// 不完整的 % 序列原样保留，解码后不是 UTF-8 时使用原始字符串
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match hex.filter(|_| bytes[i] == b'%').and_then(|h| u8::from_str_radix(h, 16).ok()) {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).unwrap_or_else(|_| text.to_string())
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 每个测试使用单独的目录
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("frame_source_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn recognizes_source_uris() {
+        assert!(is_source_uri("file:///D:/faces"));
+        assert!(is_source_uri("synthetic://640x480"));
+        assert!(!is_source_uri("0"));
+        assert!(!is_source_uri("D:/faces"));
+        assert!(!is_source_uri("http://camera/stream"));
+    }
+
+    #[test]
+    fn maps_file_uris_to_paths() {
+        assert_eq!(file_uri_path("/D:/faces/demo"), PathBuf::from("D:/faces/demo"));
+        assert_eq!(file_uri_path("/home/a/demo"), PathBuf::from("/home/a/demo"));
+        assert_eq!(file_uri_path("/D:/my%20faces/a.jpg"), PathBuf::from("D:/my faces/a.jpg"));
+        assert_eq!(file_uri_path("/D%3A/faces"), PathBuf::from("D:/faces"));
+        assert_eq!(file_uri_path("/home/%E4%BA%BA%E8%84%B8"), PathBuf::from("/home/人脸"));
+    }
+
+    #[test]
+    fn keeps_malformed_percent_sequences() {
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("a%2"), "a%2");
+        assert_eq!(percent_decode("a%zz"), "a%zz");
+        // 解码后不是 UTF-8
+        assert_eq!(percent_decode("a%FF"), "a%FF");
+    }
+
+    #[test]
+    fn opens_percent_encoded_directories() {
+        let dir = test_dir("encoded").join("my faces");
+        fs::create_dir_all(&dir).unwrap();
+        let image = Mat::new_size_with_default(Size::new(4, 3), CV_8UC3, Scalar::all(50.0)).unwrap();
+        imgcodecs::imwrite(dir.join("a.png").to_str().unwrap(), &image, &core::Vector::new()).unwrap();
+        let uri = format!("file://{}", dir.display()).replace(' ', "%20");
+
+        let mut source = open_source_uri(&uri).unwrap();
+        assert_eq!(source.kind(), SourceKind::Directory);
+        let mut frame = Mat::default();
+        assert!(source.read(&mut frame).unwrap());
+        assert_eq!((frame.cols(), frame.rows()), (4, 3));
+    }
+
+    #[test]
+    fn rejects_bad_sources() {
+        let empty = test_dir("empty");
+        fs::write(empty.join("notes.txt"), b"x").unwrap();
+        assert!(DirectorySource::open(&empty).is_err());
+
+        for source in [
+            "rtsp://camera",
+            "file:///no/such/frame_source/dir",
+            "synthetic://640",
+            "synthetic://0x480",
+            "synthetic://640x480?pattern=stripes",
+            "synthetic://640x480?fps=30",
+        ] {
+            assert!(open_source_uri(source).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn synthetic_source_produces_frames() {
+        let mut source = open_source_uri("synthetic://64x48?pattern=noise").unwrap();
+        assert_eq!(source.kind(), SourceKind::Synthetic);
+        assert_eq!(source.next_frame_in(), None);
+        let mut frame = Mat::default();
+        for _ in 0..3 {
+            assert!(source.read(&mut frame).unwrap());
+            assert_eq!((frame.cols(), frame.rows(), frame.typ()), (64, 48, CV_8UC3));
+        }
+        // 不指定尺寸时为 640x480
+        let mut source = open_source_uri("synthetic://").unwrap();
+        assert!(source.read(&mut frame).unwrap());
+        assert_eq!((frame.cols(), frame.rows()), (640, 480));
+    }
+}
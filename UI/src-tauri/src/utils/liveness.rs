@@ -0,0 +1,332 @@
+// 活体检测的公共函数
+// 模型：通义实验室 人脸活体检测模型-RGB，输入为 128x128 的 RGB 人脸，输出 [假体, 活体] 两类
+use std::collections::{HashMap, VecDeque};
+
+use opencv::{
+    core::{Mat, Rect, Scalar, Size, Vector, CV_32F},
+    dnn,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+pub const LIVENESS_INPUT_SIZE: i32 = 128;
+// 人脸框外扩比例，活体模型需要看到一部分背景和边缘
+const FACE_CROP_SCALE: f32 = 1.5;
+
+// 按人脸框外扩后裁剪，超出图片的部分会被截断
+pub fn face_crop(img: &Mat, face: &[f32]) -> Result<Mat, String> {
+    let (x, y, w, h) = (face[0], face[1], face[2], face[3]);
+    let cx = x + w / 2.0;
+    let cy = y + h / 2.0;
+    let side = w.max(h) * FACE_CROP_SCALE;
+
+    let left = ((cx - side / 2.0).max(0.0)) as i32;
+    let top = ((cy - side / 2.0).max(0.0)) as i32;
+    let right = ((cx + side / 2.0) as i32).min(img.cols());
+    let bottom = ((cy + side / 2.0) as i32).min(img.rows());
+    if right <= left || bottom <= top {
+        return Err(String::from("人脸框超出图片范围"));
+    }
+
+    Mat::roi(img, Rect::new(left, top, right - left, bottom - top))
+        .and_then(|roi| roi.try_clone())
+        .map_err(|e| format!("裁剪人脸失败: {:?}", e))
+}
+
+// 计算活体分数（0 ~ 1，越高越可能是真人）
+pub fn liveness_score(net: &mut dnn::Net, face_img: &Mat) -> Result<f32, String> {
+    let blob = dnn::blob_from_image(
+        face_img,
+        1.0 / 255.0,
+        Size::new(LIVENESS_INPUT_SIZE, LIVENESS_INPUT_SIZE),
+        Scalar::default(),
+        true,
+        false,
+        CV_32F,
+    )
+    .map_err(|e| format!("活体检测预处理失败: {:?}", e))?;
+
+    net.set_input(&blob, "", 1.0, Scalar::default())
+        .map_err(|e| format!("设置活体检测输入失败: {:?}", e))?;
+    let mut outputs: Vector<Mat> = Vector::new();
+    let names = net
+        .get_unconnected_out_layers_names()
+        .map_err(|e| format!("获取活体检测输出层失败: {:?}", e))?;
+    net.forward(&mut outputs, &names)
+        .map_err(|e| format!("活体检测推理失败: {:?}", e))?;
+
+    let output = outputs
+        .get(0)
+        .map_err(|e| format!("活体检测没有输出: {:?}", e))?;
+    let fake = *output
+        .at::<f32>(0)
+        .map_err(|e| format!("读取活体检测结果失败: {:?}", e))?;
+    let real = *output
+        .at::<f32>(1)
+        .map_err(|e| format!("读取活体检测结果失败: {:?}", e))?;
+
+    // 有的导出版本没有 softmax 层，这里统一做一次
+    if (fake + real - 1.0).abs() > 1e-3 || fake < 0.0 || real < 0.0 {
+        let max = fake.max(real);
+        let (e_fake, e_real) = ((fake - max).exp(), (real - max).exp());
+        return Ok(e_real / (e_fake + e_real));
+    }
+    Ok(real)
+}
+
+// 多帧投票时的分数聚合方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LivenessAggregation {
+    Mean,
+    Median,
+    // 取最低分，最严格
+    Min,
+}
+
+// 多帧活体投票配置
+// 对应 options 表中的 livenessThreshold / livenessVotingFrames / livenessVotingMethod / livenessVotingPassRatio
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LivenessVotingConfig {
+    // 单帧活体阈值
+    pub threshold: f32,
+    // 滑动窗口的帧数，为 1 时与单帧判断一致
+    pub window_size: usize,
+    pub aggregation: LivenessAggregation,
+    // 窗口内至少多少比例的帧单独通过
+    pub min_pass_ratio: f32,
+}
+
+impl Default for LivenessVotingConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 0.5,
+            window_size: 5,
+            aggregation: LivenessAggregation::Mean,
+            min_pass_ratio: 0.6,
+        }
+    }
+}
+
+impl LivenessVotingConfig {
+    // 从设置中读取，get 传入设置名返回设置值，缺失或格式错误时使用默认值
+    pub fn from_options(get: impl Fn(&str) -> Option<String>) -> Self {
+        let default = Self::default();
+        Self {
+            threshold: get("livenessThreshold")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.threshold),
+            window_size: get("livenessVotingFrames")
+                .and_then(|v| v.parse().ok())
+                .filter(|v: &usize| *v > 0)
+                .unwrap_or(default.window_size),
+            aggregation: match get("livenessVotingMethod").as_deref() {
+                Some("median") => LivenessAggregation::Median,
+                Some("min") => LivenessAggregation::Min,
+                Some("mean") => LivenessAggregation::Mean,
+                _ => default.aggregation,
+            },
+            min_pass_ratio: get("livenessVotingPassRatio")
+                .and_then(|v| v.parse().ok())
+                .map(|v: f32| v.clamp(0.0, 1.0))
+                .unwrap_or(default.min_pass_ratio),
+        }
+    }
+}
+
+// 多帧投票的结果，记录在尝试记录（AttemptLog）中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LivenessVerdict {
+    // 窗口内每一帧的分数，按时间顺序
+    pub frame_scores: Vec<f32>,
+    pub aggregation: LivenessAggregation,
+    pub aggregate_score: f32,
+    // 单独通过阈值的帧占比
+    pub pass_ratio: f32,
+    pub passed: bool,
+}
+
+// 按 track id 维护每张脸最近 window_size 帧的活体分数
+pub struct LivenessVoter {
+    config: LivenessVotingConfig,
+    windows: HashMap<u32, VecDeque<f32>>,
+}
+
+impl LivenessVoter {
+    pub fn new(config: LivenessVotingConfig) -> Self {
+        Self {
+            config,
+            windows: HashMap::new(),
+        }
+    }
+
+    // 加入一帧分数，窗口未满时返回 None，满了之后每帧都返回最新的投票结果
+    pub fn push(&mut self, track_id: u32, score: f32) -> Option<LivenessVerdict> {
+        let window_size = self.config.window_size.max(1);
+        let window = self.windows.entry(track_id).or_default();
+        window.push_back(score);
+        while window.len() > window_size {
+            window.pop_front();
+        }
+
+        if window.len() < window_size {
+            return None;
+        }
+        Some(vote(&self.config, window.iter().copied().collect()))
+    }
+
+    // 当前窗口的结果，窗口未满也返回，用于超时后记录日志
+    pub fn partial(&self, track_id: u32) -> Option<LivenessVerdict> {
+        self.windows
+            .get(&track_id)
+            .filter(|w| !w.is_empty())
+            .map(|w| vote(&self.config, w.iter().copied().collect()))
+    }
+
+    // 丢弃已经不在画面中的人脸
+    pub fn retain(&mut self, active_ids: &[u32]) {
+        self.windows.retain(|id, _| active_ids.contains(id));
+    }
+
+    pub fn reset(&mut self) {
+        self.windows.clear();
+    }
+}
+
+fn vote(config: &LivenessVotingConfig, frame_scores: Vec<f32>) -> LivenessVerdict {
+    let aggregate_score = match config.aggregation {
+        LivenessAggregation::Mean => frame_scores.iter().sum::<f32>() / frame_scores.len() as f32,
+        LivenessAggregation::Median => {
+            let mut sorted = frame_scores.clone();
+            sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            let mid = sorted.len() / 2;
+            if sorted.len().is_multiple_of(2) {
+                (sorted[mid - 1] + sorted[mid]) / 2.0
+            } else {
+                sorted[mid]
+            }
+        }
+        LivenessAggregation::Min => frame_scores.iter().copied().fold(f32::MAX, f32::min),
+    };
+    let pass_ratio = frame_scores
+        .iter()
+        .filter(|s| **s >= config.threshold)
+        .count() as f32
+        / frame_scores.len() as f32;
+
+    LivenessVerdict {
+        aggregation: config.aggregation,
+        aggregate_score,
+        pass_ratio,
+        passed: aggregate_score >= config.threshold && pass_ratio >= config.min_pass_ratio,
+        frame_scores,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(aggregation: LivenessAggregation) -> LivenessVotingConfig {
+        LivenessVotingConfig {
+            aggregation,
+            ..LivenessVotingConfig::default()
+        }
+    }
+
+    // 依次加入分数，返回最后一帧的投票结果
+    fn push_all(voter: &mut LivenessVoter, track_id: u32, scores: &[f32]) -> Option<LivenessVerdict> {
+        scores.iter().fold(None, |_, score| voter.push(track_id, *score))
+    }
+
+    #[test]
+    fn single_lucky_frame_does_not_pass() {
+        let mut voter = LivenessVoter::new(config(LivenessAggregation::Mean));
+        // 窗口未满时没有结果
+        for score in [0.1, 0.1, 0.95, 0.1] {
+            assert!(voter.push(1, score).is_none());
+        }
+        let verdict = voter.push(1, 0.1).unwrap();
+        assert!(!verdict.passed);
+        assert_eq!(verdict.frame_scores, vec![0.1, 0.1, 0.95, 0.1, 0.1]);
+        assert!((verdict.pass_ratio - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn window_slides_to_latest_frames() {
+        let mut voter = LivenessVoter::new(config(LivenessAggregation::Mean));
+        let verdict = push_all(&mut voter, 1, &[0.1, 0.1, 0.1, 0.1, 0.1, 0.9, 0.9, 0.9, 0.9, 0.9]).unwrap();
+        assert_eq!(verdict.frame_scores, vec![0.9; 5]);
+        assert!(verdict.passed);
+    }
+
+    #[test]
+    fn pass_ratio_is_required_besides_aggregate() {
+        // 平均分 0.64 超过阈值，但只有 2/5 的帧单独通过
+        let mut voter = LivenessVoter::new(config(LivenessAggregation::Mean));
+        let verdict = push_all(&mut voter, 1, &[1.0, 1.0, 0.4, 0.4, 0.4]).unwrap();
+        assert!(verdict.aggregate_score >= 0.5);
+        assert!(!verdict.passed);
+    }
+
+    #[test]
+    fn aggregation_methods() {
+        let scores = [0.9, 0.2, 0.8, 0.7, 0.6];
+        let aggregate = |aggregation| {
+            let mut voter = LivenessVoter::new(config(aggregation));
+            push_all(&mut voter, 1, &scores).unwrap()
+        };
+        assert!((aggregate(LivenessAggregation::Mean).aggregate_score - 0.64).abs() < 1e-6);
+        assert_eq!(aggregate(LivenessAggregation::Median).aggregate_score, 0.7);
+        let min = aggregate(LivenessAggregation::Min);
+        assert_eq!(min.aggregate_score, 0.2);
+        assert!(!min.passed);
+    }
+
+    #[test]
+    fn tracks_vote_separately() {
+        let mut voter = LivenessVoter::new(LivenessVotingConfig {
+            window_size: 3,
+            ..LivenessVotingConfig::default()
+        });
+        // 两张脸交替出现，各自的窗口互不影响
+        for _ in 0..2 {
+            assert!(voter.push(1, 0.9).is_none());
+            assert!(voter.push(2, 0.1).is_none());
+        }
+        assert!(voter.push(1, 0.9).unwrap().passed);
+        assert!(!voter.push(2, 0.1).unwrap().passed);
+
+        // 离开画面的人脸丢弃窗口，重新出现时从头开始
+        voter.retain(&[1]);
+        assert!(voter.partial(2).is_none());
+        assert!(voter.push(2, 0.9).is_none());
+    }
+
+    #[test]
+    fn partial_verdict_before_window_fills() {
+        let mut voter = LivenessVoter::new(config(LivenessAggregation::Mean));
+        assert!(voter.partial(1).is_none());
+        push_all(&mut voter, 1, &[0.8, 0.6]);
+        let verdict = voter.partial(1).unwrap();
+        assert_eq!(verdict.frame_scores, vec![0.8, 0.6]);
+        assert!(verdict.passed);
+    }
+
+    #[test]
+    fn reads_voting_options() {
+        let options = |key: &str| match key {
+            "livenessThreshold" => Some(String::from("0.7")),
+            "livenessVotingFrames" => Some(String::from("0")),
+            "livenessVotingMethod" => Some(String::from("median")),
+            "livenessVotingPassRatio" => Some(String::from("1.5")),
+            _ => None,
+        };
+        let config = LivenessVotingConfig::from_options(options);
+        assert_eq!(config.threshold, 0.7);
+        // 帧数为 0 时使用默认值，通过比例限制在 0 ~ 1
+        assert_eq!(config.window_size, LivenessVotingConfig::default().window_size);
+        assert_eq!(config.aggregation, LivenessAggregation::Median);
+        assert_eq!(config.min_pass_ratio, 1.0);
+    }
+}
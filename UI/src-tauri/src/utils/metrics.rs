@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+// 分数统一为余弦相似度（0 ~ 1），与 json_data.threshold / 100 对应
+
+// 直方图的分箱数量
+const HISTOGRAM_BINS: usize = 50;
+// 阈值扫描的步长
+const THRESHOLD_STEP: f64 = 0.01;
+
+// 一个已标注的分数样本
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ScoreSample {
+    pub score: f64,
+    // true 是本人（genuine），false 是他人（impostor）
+    pub genuine: bool,
+}
+
+// 某个阈值下的误识率和拒识率
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RatePoint {
+    pub threshold: f64,
+    pub far: f64,
+    pub frr: f64,
+}
+
+// 分数分布的直方图
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoreHistogram {
+    // 每个分箱的起始分数
+    pub bins: Vec<f64>,
+    pub genuine: Vec<u32>,
+    pub impostor: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CalibrationReport {
+    pub genuine_count: usize,
+    pub impostor_count: usize,
+    pub histogram: ScoreHistogram,
+    // 阈值从 0 到 1 扫描得到的 FAR/FRR 曲线，可直接用作 ROC/DET 图
+    pub curve: Vec<RatePoint>,
+    // 等错误率及其阈值
+    pub eer: f64,
+    pub eer_threshold: f64,
+    pub target_far: f64,
+    // 满足目标误识率时的推荐阈值（百分比，与 json_data.threshold 一致），样本不足时为 None
+    pub recommended_threshold: Option<u32>,
+    pub recommended_far: Option<f64>,
+    pub recommended_frr: Option<f64>,
+}
+
+// 计算给定阈值下的 FAR / FRR
+// 分数 >= 阈值视为通过
+pub fn rates_at(samples: &[ScoreSample], threshold: f64) -> RatePoint {
+    let (mut genuine, mut impostor, mut false_reject, mut false_accept) = (0u32, 0u32, 0u32, 0u32);
+    for sample in samples {
+        if sample.genuine {
+            genuine += 1;
+            if sample.score < threshold {
+                false_reject += 1;
+            }
+        } else {
+            impostor += 1;
+            if sample.score >= threshold {
+                false_accept += 1;
+            }
+        }
+    }
+
+    RatePoint {
+        threshold,
+        far: ratio(false_accept, impostor),
+        frr: ratio(false_reject, genuine),
+    }
+}
+
+// 扫描所有阈值，得到 FAR/FRR 曲线
+pub fn rate_curve(samples: &[ScoreSample]) -> Vec<RatePoint> {
+    let steps = (1.0 / THRESHOLD_STEP).round() as usize;
+    (0..=steps)
+        .map(|i| rates_at(samples, i as f64 * THRESHOLD_STEP))
+        .collect()
+}
+
+// 计算等错误率，返回 (eer, 阈值)
+pub fn equal_error_rate(curve: &[RatePoint]) -> (f64, f64) {
+    curve
+        .iter()
+        .min_by(|a, b| {
+            (a.far - a.frr)
+                .abs()
+                .partial_cmp(&(b.far - b.frr).abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .map(|p| ((p.far + p.frr) / 2.0, p.threshold))
+        .unwrap_or((1.0, 1.0))
+}
+
+// 统计分数直方图
+pub fn histogram(samples: &[ScoreSample]) -> ScoreHistogram {
+    let width = 1.0 / HISTOGRAM_BINS as f64;
+    let mut genuine = vec![0u32; HISTOGRAM_BINS];
+    let mut impostor = vec![0u32; HISTOGRAM_BINS];
+    for sample in samples {
+        let index = ((sample.score.clamp(0.0, 1.0) / width) as usize).min(HISTOGRAM_BINS - 1);
+        if sample.genuine {
+            genuine[index] += 1;
+        } else {
+            impostor[index] += 1;
+        }
+    }
+
+    ScoreHistogram {
+        bins: (0..HISTOGRAM_BINS).map(|i| i as f64 * width).collect(),
+        genuine,
+        impostor,
+    }
+}
+
+// 从一次已标注的解锁尝试中取出某个面容的分数样本
+// subject: 用户在登录日志中标注的尝试者，为该面容时是本人，为其他面容或 None（未录入的人）时是他人
+// 标注来自用户，不使用解锁结果，否则阈值会按现有阈值的判定结果校准自己；没有与该面容比对过的尝试不参与
+pub fn labeled_sample(face_id: i64, subject: Option<i64>, scores: &BTreeMap<i64, f64>) -> Option<ScoreSample> {
+    let score = *scores.get(&face_id)?;
+    Some(ScoreSample {
+        score,
+        genuine: subject == Some(face_id),
+    })
+}
+
+// 生成完整的校准报告
+// 推荐阈值取满足 FAR <= target_far 的最低阈值，这样拒识率最低
+pub fn calibrate(samples: &[ScoreSample], target_far: f64) -> CalibrationReport {
+    let genuine_count = samples.iter().filter(|s| s.genuine).count();
+    let impostor_count = samples.len() - genuine_count;
+    let curve = rate_curve(samples);
+    let (eer, eer_threshold) = equal_error_rate(&curve);
+
+    let recommended = if genuine_count > 0 && impostor_count > 0 {
+        curve.iter().find(|p| p.far <= target_far).copied()
+    } else {
+        None
+    };
+
+    CalibrationReport {
+        genuine_count,
+        impostor_count,
+        histogram: histogram(samples),
+        eer,
+        eer_threshold,
+        target_far,
+        recommended_threshold: recommended.map(|p| (p.threshold * 100.0).round() as u32),
+        recommended_far: recommended.map(|p| p.far),
+        recommended_frr: recommended.map(|p| p.frr),
+        curve,
+    }
+}
+
+fn ratio(count: u32, total: u32) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(genuine: &[f64], impostor: &[f64]) -> Vec<ScoreSample> {
+        genuine
+            .iter()
+            .map(|score| ScoreSample {
+                score: *score,
+                genuine: true,
+            })
+            .chain(impostor.iter().map(|score| ScoreSample {
+                score: *score,
+                genuine: false,
+            }))
+            .collect()
+    }
+
+    #[test]
+    fn labeled_samples_follow_the_label() {
+        let scores = BTreeMap::from([(1, 0.8), (2, 0.3)]);
+        let genuine = labeled_sample(1, Some(1), &scores).unwrap();
+        assert!(genuine.genuine);
+        assert_eq!(genuine.score, 0.8);
+        // 标注为其他面容或未录入的人，这个面容的分数是他人样本
+        let impostor = labeled_sample(2, Some(1), &scores).unwrap();
+        assert!(!impostor.genuine);
+        assert_eq!(impostor.score, 0.3);
+        assert!(!labeled_sample(1, None, &scores).unwrap().genuine);
+        // 没有比对过的面容不参与
+        assert!(labeled_sample(3, Some(3), &scores).is_none());
+    }
+
+    #[test]
+    fn high_scoring_impostor_stays_impostor() {
+        // 被误识解锁的尝试分数超过阈值，标注为他人后仍然是他人样本
+        let scores = BTreeMap::from([(1, 0.9)]);
+        assert!(!labeled_sample(1, None, &scores).unwrap().genuine);
+    }
+
+    #[test]
+    fn recommends_lowest_threshold_within_target_far() {
+        let report = calibrate(&samples(&[0.6, 0.7, 0.8], &[0.1, 0.2, 0.25]), 0.01);
+        assert_eq!(report.genuine_count, 3);
+        assert_eq!(report.impostor_count, 3);
+        assert_eq!(report.curve.len(), 101);
+        // 两类分数完全分开，等错误率为 0
+        assert_eq!(report.eer, 0.0);
+        assert_eq!(report.recommended_threshold, Some(26));
+        assert_eq!(report.recommended_far, Some(0.0));
+        assert_eq!(report.recommended_frr, Some(0.0));
+        assert_eq!(report.histogram.genuine.iter().sum::<u32>(), 3);
+        assert_eq!(report.histogram.impostor.iter().sum::<u32>(), 3);
+    }
+
+    #[test]
+    fn overlapping_scores_trade_far_for_frr() {
+        let report = calibrate(&samples(&[0.5, 0.6, 0.7, 0.8], &[0.1, 0.2, 0.3, 0.55]), 0.0);
+        // 要做到没有误识，阈值要高于 0.55，拒识掉 0.5 这一个本人样本
+        assert_eq!(report.recommended_threshold, Some(56));
+        assert_eq!(report.recommended_frr, Some(0.25));
+        assert!(report.eer > 0.0);
+    }
+
+    #[test]
+    fn no_recommendation_without_both_classes() {
+        let report = calibrate(&samples(&[0.6, 0.7], &[]), 0.01);
+        assert_eq!(report.impostor_count, 0);
+        assert_eq!(report.recommended_threshold, None);
+        assert_eq!(report.recommended_far, None);
+    }
+}
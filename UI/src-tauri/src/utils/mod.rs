@@ -1,3 +1,28 @@
+pub mod active_liveness;
 pub mod api;
+pub mod attempt_log;
+pub mod camera_backend;
+pub mod camera_chain;
+pub mod camera_idle;
+pub mod camera_identity;
+pub mod camera_lease;
+pub mod camera_warmup;
+pub mod capture_profile;
 pub mod custom_result;
-pub mod pipe;
\ No newline at end of file
+pub mod face_conflicts;
+pub mod face_finder;
+pub mod face_pipeline;
+pub mod face_policy;
+pub mod face_quality;
+pub mod face_tracker;
+pub mod frame_normalize;
+pub mod frame_source;
+pub mod liveness;
+pub mod metrics;
+pub mod model_manifest;
+pub mod pipe;
+pub mod preprocess;
+pub mod replay;
+pub mod spoof_heuristics;
+pub mod template_gallery;
+pub mod unlock_attempt;
\ No newline at end of file
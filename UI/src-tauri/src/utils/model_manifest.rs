@@ -0,0 +1,299 @@
+// 模型清单：resources/models.json
+// 加载模型前先按清单检查文件是否存在、SHA-256 是否一致，问题直接报给用户，而不是变成 OpenCV 的报错
+use std::{
+    fmt,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub const MANIFEST_FILE: &str = "models.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ModelRole {
+    Detector,
+    Recognizer,
+    Liveness,
+}
+
+impl ModelRole {
+    pub const ALL: [ModelRole; 3] = [ModelRole::Detector, ModelRole::Recognizer, ModelRole::Liveness];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ModelRole::Detector => "人脸检测模型",
+            ModelRole::Recognizer => "人脸识别模型",
+            ModelRole::Liveness => "活体检测模型",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelEntry {
+    pub name: String,
+    pub role: ModelRole,
+    // resources 目录下的文件名
+    pub file: String,
+    pub version: String,
+    // 小写十六进制，为空时拒绝加载（发布前用 face_eval --write-manifest 填写）
+    #[serde(default)]
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelManifest {
+    pub models: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ModelError {
+    ManifestMissing { path: String },
+    ManifestInvalid { reason: String },
+    NotInManifest { role: ModelRole },
+    FileMissing { role: ModelRole, path: String },
+    Unpinned { role: ModelRole, path: String },
+    ReadFailed { role: ModelRole, path: String, reason: String },
+    HashMismatch { role: ModelRole, path: String, expected: String, actual: String },
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelError::ManifestMissing { path } => write!(f, "模型清单 {} 不存在，请重新安装程序", path),
+            ModelError::ManifestInvalid { reason } => write!(f, "模型清单格式错误: {}", reason),
+            ModelError::NotInManifest { role } => write!(f, "模型清单中没有{}", role.label()),
+            ModelError::FileMissing { role, path } => {
+                write!(f, "{}文件 {} 不存在，请重新安装程序", role.label(), path)
+            }
+            ModelError::Unpinned { role, path } => {
+                write!(f, "模型清单中没有{} {} 的 SHA-256，无法校验，请重新安装程序", role.label(), path)
+            }
+            ModelError::ReadFailed { role, path, reason } => {
+                write!(f, "读取{} {} 失败: {}", role.label(), path, reason)
+            }
+            ModelError::HashMismatch { role, path, expected, actual } => write!(
+                f,
+                "{} {} 校验失败，文件可能已损坏或被替换（期望 {}，实际 {}）",
+                role.label(),
+                path,
+                expected,
+                actual
+            ),
+        }
+    }
+}
+
+// 单个模型的检查结果，供安装程序一次性展示
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelStatus {
+    pub role: ModelRole,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub ok: bool,
+    // 清单中是否填写了 SHA-256
+    pub pinned: bool,
+    pub error: Option<ModelError>,
+    pub message: Option<String>,
+}
+
+impl ModelManifest {
+    pub fn load(resource_dir: &Path) -> Result<Self, ModelError> {
+        let path = resource_dir.join(MANIFEST_FILE);
+        let text = std::fs::read_to_string(&path).map_err(|_| ModelError::ManifestMissing {
+            path: path.display().to_string(),
+        })?;
+        serde_json::from_str(&text).map_err(|e| ModelError::ManifestInvalid {
+            reason: e.to_string(),
+        })
+    }
+
+    pub fn entry(&self, role: ModelRole) -> Result<&ModelEntry, ModelError> {
+        self.models
+            .iter()
+            .find(|m| m.role == role)
+            .ok_or(ModelError::NotInManifest { role })
+    }
+
+    // 检查模型文件，通过后返回文件路径
+    pub fn verify(&self, resource_dir: &Path, role: ModelRole) -> Result<PathBuf, ModelError> {
+        let entry = self.entry(role)?;
+        let path = resource_dir.join(&entry.file);
+        let display = path.display().to_string();
+        if !path.is_file() {
+            return Err(ModelError::FileMissing { role, path: display });
+        }
+
+        if entry.sha256.is_empty() {
+            return Err(ModelError::Unpinned { role, path: display });
+        }
+
+        let actual = sha256_file(&path).map_err(|reason| ModelError::ReadFailed {
+            role,
+            path: display.clone(),
+            reason,
+        })?;
+        if !actual.eq_ignore_ascii_case(&entry.sha256) {
+            return Err(ModelError::HashMismatch {
+                role,
+                path: display,
+                expected: entry.sha256.clone(),
+                actual,
+            });
+        }
+        Ok(path)
+    }
+
+    // 检查清单中的全部模型
+    pub fn verify_all(&self, resource_dir: &Path) -> Vec<ModelStatus> {
+        ModelRole::ALL
+            .iter()
+            .map(|role| {
+                let entry = self.entry(*role).ok();
+                let result = self.verify(resource_dir, *role);
+                ModelStatus {
+                    role: *role,
+                    name: entry.map(|e| e.name.clone()),
+                    version: entry.map(|e| e.version.clone()),
+                    ok: result.is_ok(),
+                    pinned: entry.is_some_and(|e| !e.sha256.is_empty()),
+                    message: result.as_ref().err().map(|e| e.to_string()),
+                    error: result.err(),
+                }
+            })
+            .collect()
+    }
+}
+
+// 按清单检查并返回模型路径，清单本身有问题时也返回 ModelError
+pub fn verified_model_path(resource_dir: &Path, role: ModelRole) -> Result<PathBuf, ModelError> {
+    ModelManifest::load(resource_dir)?.verify(resource_dir, role)
+}
+
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    // "abc" 的 SHA-256
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    // 每个测试使用单独的目录，写入清单和模型文件
+    fn resource_dir(name: &str, manifest: Option<&str>, files: &[(&str, &[u8])]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("model_manifest_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        if let Some(manifest) = manifest {
+            fs::write(dir.join(MANIFEST_FILE), manifest).unwrap();
+        }
+        for (file, content) in files {
+            fs::write(dir.join(file), content).unwrap();
+        }
+        dir
+    }
+
+    fn manifest(sha256: &str) -> String {
+        format!(
+            r#"{{"models": [
+                {{"name": "YuNet", "role": "detector", "file": "detector.onnx", "version": "2023mar"}},
+                {{"name": "SFace", "role": "recognizer", "file": "recognizer.onnx", "version": "2021dec",
+                  "sha256": "{}"}}
+            ]}}"#,
+            sha256
+        )
+    }
+
+    #[test]
+    fn hashes_files() {
+        let dir = resource_dir("hash", None, &[("abc.bin", b"abc")]);
+        assert_eq!(sha256_file(&dir.join("abc.bin")).unwrap(), ABC_SHA256);
+    }
+
+    #[test]
+    fn verifies_pinned_and_rejects_unpinned_models() {
+        let dir = resource_dir(
+            "ok",
+            Some(&manifest(&ABC_SHA256.to_uppercase())),
+            &[("detector.onnx", b"any"), ("recognizer.onnx", b"abc")],
+        );
+        let manifest = ModelManifest::load(&dir).unwrap();
+        // 没有填写 SHA-256 时即使文件存在也不加载，大小写不同的哈希视为一致
+        assert!(matches!(
+            manifest.verify(&dir, ModelRole::Detector),
+            Err(ModelError::Unpinned {
+                role: ModelRole::Detector,
+                ..
+            })
+        ));
+        assert_eq!(
+            manifest.verify(&dir, ModelRole::Recognizer).unwrap(),
+            dir.join("recognizer.onnx")
+        );
+    }
+
+    #[test]
+    fn reports_changed_and_missing_models() {
+        let dir = resource_dir("bad", Some(&manifest(ABC_SHA256)), &[("recognizer.onnx", b"abd")]);
+        let manifest = ModelManifest::load(&dir).unwrap();
+        assert!(matches!(
+            manifest.verify(&dir, ModelRole::Recognizer),
+            Err(ModelError::HashMismatch {
+                role: ModelRole::Recognizer,
+                ..
+            })
+        ));
+        assert!(matches!(
+            manifest.verify(&dir, ModelRole::Detector),
+            Err(ModelError::FileMissing {
+                role: ModelRole::Detector,
+                ..
+            })
+        ));
+        assert!(matches!(
+            manifest.verify(&dir, ModelRole::Liveness),
+            Err(ModelError::NotInManifest {
+                role: ModelRole::Liveness
+            })
+        ));
+
+        let statuses = manifest.verify_all(&dir);
+        assert_eq!(statuses.len(), ModelRole::ALL.len());
+        assert!(statuses.iter().all(|s| !s.ok && s.message.is_some()));
+        assert!(!statuses[0].pinned);
+        assert!(statuses[1].pinned);
+        assert_eq!(statuses[2].name, None);
+    }
+
+    #[test]
+    fn reports_missing_and_invalid_manifests() {
+        let missing = resource_dir("missing", None, &[]);
+        assert!(matches!(
+            verified_model_path(&missing, ModelRole::Detector),
+            Err(ModelError::ManifestMissing { .. })
+        ));
+        let invalid = resource_dir("invalid", Some("{\"models\": 1}"), &[]);
+        assert!(matches!(
+            ModelManifest::load(&invalid),
+            Err(ModelError::ManifestInvalid { .. })
+        ));
+    }
+}
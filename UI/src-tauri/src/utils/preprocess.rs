@@ -0,0 +1,264 @@
+// 逆光、暗光下的画面预处理，在检测和提取特征之前执行
+// 顺序：白平衡 → gamma 校正 → 直方图均衡（只处理亮度，不改变颜色）
+// 每个摄像头单独设置，存在 options 表的 cameraPreprocess 中：{摄像头标识: PreprocessConfig}，键见 CameraDescriptor::profile_key
+use std::collections::BTreeMap;
+
+use opencv::{
+    core::{self, Mat, Size, Vector, CV_8U},
+    imgproc,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Equalization {
+    None,
+    // 限制对比度的自适应直方图均衡，逆光时效果最好
+    Clahe,
+    // 全局直方图均衡
+    Histogram,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PreprocessConfig {
+    pub enabled: bool,
+    pub equalization: Equalization,
+    // CLAHE 的对比度限制和分块数
+    pub clahe_clip_limit: f64,
+    pub clahe_tile_size: i32,
+    // 小于 1 提亮暗部，大于 1 压暗，等于 1 不处理
+    pub gamma: f64,
+    // 灰度世界白平衡，纠正偏黄、偏蓝的灯光
+    pub white_balance: bool,
+}
+
+impl Default for PreprocessConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            equalization: Equalization::Clahe,
+            clahe_clip_limit: 2.0,
+            clahe_tile_size: 8,
+            gamma: 1.0,
+            white_balance: false,
+        }
+    }
+}
+
+impl PreprocessConfig {
+    // 读取 cameraPreprocess 中某个摄像头的设置，camera 为 CameraDescriptor::profile_key，没有设置时不做预处理
+    pub fn from_options(get: impl Fn(&str) -> Option<String>, camera: &str) -> Self {
+        get("cameraPreprocess")
+            .and_then(|v| serde_json::from_str::<BTreeMap<String, PreprocessConfig>>(&v).ok())
+            .and_then(|profiles| profiles.get(camera).copied())
+            .unwrap_or_default()
+    }
+
+    // 是否实际会修改画面
+    pub fn is_active(&self) -> bool {
+        self.enabled
+            && (self.equalization != Equalization::None || self.white_balance || (self.gamma - 1.0).abs() > 1e-3)
+    }
+}
+
+// 按设置处理 8 位 BGR 画面，未开启时原样复制
+pub fn preprocess_frame(bgr: &Mat, config: &PreprocessConfig) -> Result<Mat, String> {
+    let mut img = bgr.try_clone().map_err(|e| format!("复制画面失败: {:?}", e))?;
+    if !config.is_active() {
+        return Ok(img);
+    }
+
+    if config.white_balance {
+        img = gray_world_white_balance(&img)?;
+    }
+    if (config.gamma - 1.0).abs() > 1e-3 {
+        img = gamma_correct(&img, config.gamma)?;
+    }
+    if config.equalization != Equalization::None {
+        img = equalize_luma(&img, config)?;
+    }
+    Ok(img)
+}
+
+// 把三个通道的均值拉到同一水平
+fn gray_world_white_balance(img: &Mat) -> Result<Mat, String> {
+    let mean = core::mean(img, &core::no_array()).map_err(|e| format!("计算白平衡失败: {:?}", e))?;
+    let gray = (mean[0] + mean[1] + mean[2]) / 3.0;
+    let mut channels = Vector::<Mat>::new();
+    core::split(img, &mut channels).map_err(|e| format!("拆分通道失败: {:?}", e))?;
+
+    let mut balanced = Vector::<Mat>::new();
+    for (i, channel) in channels.iter().enumerate() {
+        let scale = if mean[i] > 1.0 { gray / mean[i] } else { 1.0 };
+        let mut scaled = Mat::default();
+        channel
+            .convert_to(&mut scaled, CV_8U, scale, 0.0)
+            .map_err(|e| format!("计算白平衡失败: {:?}", e))?;
+        balanced.push(scaled);
+    }
+    let mut result = Mat::default();
+    core::merge(&balanced, &mut result).map_err(|e| format!("合并通道失败: {:?}", e))?;
+    Ok(result)
+}
+
+// 查表做 gamma 校正
+fn gamma_correct(img: &Mat, gamma: f64) -> Result<Mat, String> {
+    let table: Vec<u8> = (0..256)
+        .map(|i| ((i as f64 / 255.0).powf(gamma) * 255.0).round().clamp(0.0, 255.0) as u8)
+        .collect();
+    let lut = Mat::from_slice(&table).map_err(|e| format!("创建 gamma 表失败: {:?}", e))?;
+    let mut result = Mat::default();
+    core::lut(img, &lut, &mut result).map_err(|e| format!("gamma 校正失败: {:?}", e))?;
+    Ok(result)
+}
+
+// 在 YCrCb 的亮度通道上做均衡
+fn equalize_luma(img: &Mat, config: &PreprocessConfig) -> Result<Mat, String> {
+    let mut ycrcb = Mat::default();
+    imgproc::cvt_color(img, &mut ycrcb, imgproc::COLOR_BGR2YCrCb, 0)
+        .map_err(|e| format!("转换 YCrCb 失败: {:?}", e))?;
+    let mut channels = Vector::<Mat>::new();
+    core::split(&ycrcb, &mut channels).map_err(|e| format!("拆分通道失败: {:?}", e))?;
+    let luma = channels.get(0).map_err(|e| format!("读取亮度通道失败: {:?}", e))?;
+
+    let mut equalized = Mat::default();
+    match config.equalization {
+        Equalization::Clahe => {
+            let tile = config.clahe_tile_size.max(1);
+            let mut clahe = imgproc::create_clahe(config.clahe_clip_limit, Size::new(tile, tile))
+                .map_err(|e| format!("创建 CLAHE 失败: {:?}", e))?;
+            clahe
+                .apply(&luma, &mut equalized)
+                .map_err(|e| format!("CLAHE 处理失败: {:?}", e))?;
+        }
+        Equalization::Histogram => {
+            imgproc::equalize_hist(&luma, &mut equalized).map_err(|e| format!("直方图均衡失败: {:?}", e))?;
+        }
+        Equalization::None => return img.try_clone().map_err(|e| format!("复制画面失败: {:?}", e)),
+    }
+    channels.set(0, equalized).map_err(|e| format!("替换亮度通道失败: {:?}", e))?;
+
+    let mut merged = Mat::default();
+    core::merge(&channels, &mut merged).map_err(|e| format!("合并通道失败: {:?}", e))?;
+    let mut result = Mat::default();
+    imgproc::cvt_color(&merged, &mut result, imgproc::COLOR_YCrCb2BGR, 0)
+        .map_err(|e| format!("转换 BGR 失败: {:?}", e))?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use opencv::core::{Scalar, CV_8UC3};
+
+    use super::*;
+
+    fn options(value: &str) -> HashMap<&'static str, String> {
+        HashMap::from([("cameraPreprocess", value.to_string())])
+    }
+
+    fn solid(b: f64, g: f64, r: f64) -> Mat {
+        Mat::new_size_with_default(Size::new(32, 24), CV_8UC3, Scalar::new(b, g, r, 0.0)).unwrap()
+    }
+
+    fn channel_means(img: &Mat) -> [f64; 3] {
+        let mean = core::mean(img, &core::no_array()).unwrap();
+        [mean[0], mean[1], mean[2]]
+    }
+
+    #[test]
+    fn only_enabled_settings_that_change_the_frame_are_active() {
+        assert!(!PreprocessConfig::default().is_active());
+        let enabled = PreprocessConfig {
+            enabled: true,
+            ..PreprocessConfig::default()
+        };
+        assert!(enabled.is_active());
+        let nothing = PreprocessConfig {
+            equalization: Equalization::None,
+            ..enabled
+        };
+        assert!(!nothing.is_active());
+        assert!(PreprocessConfig { gamma: 0.8, ..nothing }.is_active());
+        assert!(PreprocessConfig {
+            white_balance: true,
+            ..nothing
+        }
+        .is_active());
+    }
+
+    #[test]
+    fn reads_each_camera_separately() {
+        let saved = options(
+            r#"{"USB#vid_046d": {"enabled": true, "equalization": "histogram", "gamma": 0.7},
+                "Integrated Camera": {"enabled": true, "whiteBalance": true, "claheClipLimit": 3.5}}"#,
+        );
+        let get = |key: &str| saved.get(key).cloned();
+
+        let usb = PreprocessConfig::from_options(get, "USB#vid_046d");
+        assert_eq!(usb.equalization, Equalization::Histogram);
+        assert_eq!(usb.gamma, 0.7);
+        // 没有保存的字段使用默认值
+        assert_eq!(usb.clahe_tile_size, 8);
+        assert!(!usb.white_balance);
+
+        let builtin = PreprocessConfig::from_options(get, "Integrated Camera");
+        assert!(builtin.white_balance);
+        assert_eq!(builtin.clahe_clip_limit, 3.5);
+        assert_eq!(builtin.equalization, Equalization::Clahe);
+
+        // 没有设置的摄像头不做预处理
+        assert_eq!(PreprocessConfig::from_options(get, "#0"), PreprocessConfig::default());
+    }
+
+    #[test]
+    fn invalid_settings_disable_preprocessing() {
+        for value in ["", "[]", r##"{"#0": {"equalization": "sharpen"}}"##] {
+            let saved = options(value);
+            let config = PreprocessConfig::from_options(|key| saved.get(key).cloned(), "#0");
+            assert_eq!(config, PreprocessConfig::default(), "{}", value);
+        }
+        assert_eq!(PreprocessConfig::from_options(|_| None, "#0"), PreprocessConfig::default());
+    }
+
+    #[test]
+    fn disabled_config_returns_the_frame_unchanged() {
+        let frame = solid(10.0, 80.0, 200.0);
+        let config = PreprocessConfig {
+            gamma: 0.5,
+            white_balance: true,
+            ..PreprocessConfig::default()
+        };
+        assert_eq!(channel_means(&preprocess_frame(&frame, &config).unwrap()), [10.0, 80.0, 200.0]);
+    }
+
+    #[test]
+    fn gamma_and_white_balance_change_the_frame() {
+        let config = PreprocessConfig {
+            enabled: true,
+            equalization: Equalization::None,
+            ..PreprocessConfig::default()
+        };
+        // gamma 小于 1 提亮暗部
+        let dark = solid(40.0, 40.0, 40.0);
+        let brighter = preprocess_frame(&dark, &PreprocessConfig { gamma: 0.5, ..config }).unwrap();
+        assert!(channel_means(&brighter)[0] > 90.0);
+
+        // 偏蓝的画面三个通道拉到同一水平
+        let bluish = solid(150.0, 100.0, 50.0);
+        let balanced = preprocess_frame(
+            &bluish,
+            &PreprocessConfig {
+                white_balance: true,
+                ..config
+            },
+        )
+        .unwrap();
+        for mean in channel_means(&balanced) {
+            assert!((mean - 100.0).abs() <= 1.0, "{}", mean);
+        }
+    }
+}
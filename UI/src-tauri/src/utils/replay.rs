@@ -0,0 +1,567 @@
+// 解锁尝试的录制与回放
+// 用户反馈"早上没认出我"时，回放文件里有当时的原始画面和每一帧的检测、比对、活体结果，
+// 可以用当前版本的流程重新跑一遍，对比不同版本的差异
+// 只依赖标准库、opencv 和 serde，离线评估工具在 Linux 上直接读取
+//
+// 文件格式（.fwreplay），整数都是小端：
+//   "FWREPLAY" + u32 版本
+//   之后是若干条记录：u8 类型 + u32 长度 + 内容
+//     1 文件头      JSON，ReplayHeader，总是第一条
+//     2 画面        u64 时间戳（毫秒）+ PNG 编码的原始画面，单通道、16 位的红外画面原样保存
+//     3 识别结果    JSON，FrameOutputs，紧跟在对应的画面之后
+//     4 尝试结果    JSON，ReplayOutcome，最后一条，录制中断时没有
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use opencv::{
+    core::{Mat, Vector},
+    imgcodecs,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    attempt_log::AttemptLog,
+    frame_normalize::Modality,
+    frame_source::{FrameSource, SourceKind},
+    spoof_heuristics::FusionResult,
+};
+
+pub const REPLAY_EXTENSION: &str = "fwreplay";
+const MAGIC: &[u8; 8] = b"FWREPLAY";
+const VERSION: u32 = 1;
+// 单条记录的上限，防止读到损坏的长度时分配过大的内存
+const MAX_RECORD_LEN: u32 = 64 * 1024 * 1024;
+
+const RECORD_HEADER: u8 = 1;
+const RECORD_FRAME: u8 = 2;
+const RECORD_OUTPUTS: u8 = 3;
+const RECORD_OUTCOME: u8 = 4;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReplayHeader {
+    // 录制时间，秒
+    pub created_at: u64,
+    // 录制的程序和版本，如 unlock 1.2.0
+    pub recorder: String,
+    // 模型的名称和版本，对比不同版本时使用
+    pub models: Vec<String>,
+    // 画面来源的描述
+    pub source: String,
+    pub face_detection_threshold: f32,
+    // 比对阈值 0~100，与面容的 threshold 一致
+    pub threshold: Option<f64>,
+    // 未开启活体检测时为 None
+    pub liveness_threshold: Option<f32>,
+}
+
+// 一帧的识别结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FrameOutputs {
+    // 对应画面的序号，从 0 开始
+    pub frame: u32,
+    pub modality: Option<Modality>,
+    // 检测器的原始输出，每张脸 15 个值：框、5 个关键点、置信度
+    pub faces: Vec<Vec<f32>>,
+    // 与每个面容的相似度 {面容ID: 0~1}
+    pub scores: BTreeMap<i64, f64>,
+    pub liveness: Option<f32>,
+    // 最大人脸的质量分（0 ~ 1），旧文件没有
+    pub quality: Option<f32>,
+    // 翻拍/打印检查与活体分数的融合结果，未开启时没有
+    pub spoof: Option<FusionResult>,
+    // 处理这一帧的耗时
+    pub ms: f64,
+}
+
+// 整次尝试的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReplayOutcome {
+    pub unlocked: bool,
+    // 通过的面容
+    pub matched: Option<i64>,
+    // 失败原因，如 未检测到人脸、活体检测未通过
+    pub reason: Option<String>,
+    // 整次尝试的详细记录（相似度、多帧活体投票等），与 unlock_log 的 json_data 相同，旧文件没有
+    pub attempt: Option<AttemptLog>,
+}
+
+pub enum ReplayRecord {
+    Frame { timestamp_ms: u64, image: Mat },
+    Outputs(FrameOutputs),
+    Outcome(ReplayOutcome),
+}
+
+pub struct ReplayWriter {
+    writer: BufWriter<File>,
+    frames: u32,
+}
+
+impl ReplayWriter {
+    pub fn create(path: &Path, header: &ReplayHeader) -> Result<Self, String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("创建目录 {} 失败: {}", dir.display(), e))?;
+        }
+        let file = File::create(path).map_err(|e| format!("创建 {} 失败: {}", path.display(), e))?;
+        let mut writer = Self {
+            writer: BufWriter::new(file),
+            frames: 0,
+        };
+        writer.write_bytes(MAGIC)?;
+        writer.write_bytes(&VERSION.to_le_bytes())?;
+        writer.write_json(RECORD_HEADER, header)?;
+        Ok(writer)
+    }
+
+    // 写入一帧原始画面，返回画面的序号
+    pub fn write_frame(&mut self, timestamp_ms: u64, frame: &Mat) -> Result<u32, String> {
+        let mut buffer = Vector::<u8>::new();
+        imgcodecs::imencode(".png", frame, &mut buffer, &Vector::new())
+            .map_err(|e| format!("画面编码失败: {:?}", e))?;
+        let mut payload = timestamp_ms.to_le_bytes().to_vec();
+        payload.extend_from_slice(buffer.as_slice());
+        self.write_record(RECORD_FRAME, &payload)?;
+        self.frames += 1;
+        Ok(self.frames - 1)
+    }
+
+    pub fn write_outputs(&mut self, outputs: &FrameOutputs) -> Result<(), String> {
+        self.write_json(RECORD_OUTPUTS, outputs)
+    }
+
+    // 写入尝试结果并关闭文件
+    pub fn finish(mut self, outcome: &ReplayOutcome) -> Result<u32, String> {
+        self.write_json(RECORD_OUTCOME, outcome)?;
+        self.writer.flush().map_err(|e| format!("写入回放文件失败: {}", e))?;
+        Ok(self.frames)
+    }
+
+    fn write_json<T: Serialize>(&mut self, kind: u8, value: &T) -> Result<(), String> {
+        let json = serde_json::to_vec(value).map_err(|e| format!("序列化回放记录失败: {}", e))?;
+        self.write_record(kind, &json)
+    }
+
+    fn write_record(&mut self, kind: u8, payload: &[u8]) -> Result<(), String> {
+        self.write_bytes(&[kind])?;
+        self.write_bytes(&(payload.len() as u32).to_le_bytes())?;
+        self.write_bytes(payload)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.writer
+            .write_all(bytes)
+            .map_err(|e| format!("写入回放文件失败: {}", e))
+    }
+}
+
+pub struct ReplayReader {
+    reader: BufReader<File>,
+    header: ReplayHeader,
+}
+
+impl ReplayReader {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("打开 {} 失败: {}", path.display(), e))?;
+        let mut reader = BufReader::new(file);
+        let mut magic = [0u8; 8];
+        let mut version = [0u8; 4];
+        reader
+            .read_exact(&mut magic)
+            .and_then(|_| reader.read_exact(&mut version))
+            .map_err(|_| format!("{} 不是回放文件", path.display()))?;
+        if &magic != MAGIC {
+            return Err(format!("{} 不是回放文件", path.display()));
+        }
+        let version = u32::from_le_bytes(version);
+        if version > VERSION {
+            return Err(format!("回放文件版本 {} 高于当前支持的版本 {}", version, VERSION));
+        }
+
+        let header = match read_raw_record(&mut reader)? {
+            Some((RECORD_HEADER, payload)) => {
+                serde_json::from_slice(&payload).map_err(|e| format!("解析回放文件头失败: {}", e))?
+            }
+            _ => return Err(format!("{} 缺少文件头", path.display())),
+        };
+        Ok(Self { reader, header })
+    }
+
+    pub fn header(&self) -> &ReplayHeader {
+        &self.header
+    }
+
+    // 读取下一条记录，文件结束或录制中断导致最后一条不完整时返回 None
+    pub fn next_record(&mut self) -> Result<Option<ReplayRecord>, String> {
+        loop {
+            let (kind, payload) = match read_raw_record(&mut self.reader)? {
+                Some(record) => record,
+                None => return Ok(None),
+            };
+            let record = match kind {
+                RECORD_FRAME if payload.len() > 8 => {
+                    let mut timestamp = [0u8; 8];
+                    timestamp.copy_from_slice(&payload[..8]);
+                    let buffer = Vector::<u8>::from_slice(&payload[8..]);
+                    let image = imgcodecs::imdecode(&buffer, imgcodecs::IMREAD_UNCHANGED)
+                        .map_err(|e| format!("解码回放画面失败: {:?}", e))?;
+                    ReplayRecord::Frame {
+                        timestamp_ms: u64::from_le_bytes(timestamp),
+                        image,
+                    }
+                }
+                RECORD_OUTPUTS => ReplayRecord::Outputs(
+                    serde_json::from_slice(&payload).map_err(|e| format!("解析识别结果失败: {}", e))?,
+                ),
+                RECORD_OUTCOME => ReplayRecord::Outcome(
+                    serde_json::from_slice(&payload).map_err(|e| format!("解析尝试结果失败: {}", e))?,
+                ),
+                // 新版本增加的记录类型跳过
+                _ => continue,
+            };
+            return Ok(Some(record));
+        }
+    }
+}
+
+// 回放文件的概要，列出回放时使用，不解码画面
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaySummary {
+    pub header: ReplayHeader,
+    pub frames: u32,
+    pub outcome: Option<ReplayOutcome>,
+}
+
+pub fn summarize_replay(path: &Path) -> Result<ReplaySummary, String> {
+    let mut reader = ReplayReader::open(path)?;
+    let mut summary = ReplaySummary {
+        header: reader.header.clone(),
+        frames: 0,
+        outcome: None,
+    };
+    while let Some((kind, payload)) = read_raw_record(&mut reader.reader)? {
+        match kind {
+            RECORD_FRAME => summary.frames += 1,
+            RECORD_OUTCOME => summary.outcome = serde_json::from_slice(&payload).ok(),
+            _ => {}
+        }
+    }
+    Ok(summary)
+}
+
+fn read_raw_record(reader: &mut impl Read) -> Result<Option<(u8, Vec<u8>)>, String> {
+    let mut head = [0u8; 5];
+    match reader.read_exact(&mut head) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(format!("读取回放文件失败: {}", e)),
+    }
+    let len = u32::from_le_bytes([head[1], head[2], head[3], head[4]]);
+    if len > MAX_RECORD_LEN {
+        return Err(format!("回放记录长度 {} 异常，文件可能已损坏", len));
+    }
+    let mut payload = vec![0u8; len as usize];
+    match reader.read_exact(&mut payload) {
+        Ok(()) => Ok(Some((head[0], payload))),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(format!("读取回放文件失败: {}", e)),
+    }
+}
+
+// 把回放文件当作画面来源，按录制顺序返回原始画面，播放完后返回 false
+// realtime 为 true 时 next_frame_in 按录制时的间隔给出等待时间，否则不需要等待
+pub struct ReplaySource {
+    path: PathBuf,
+    reader: ReplayReader,
+    realtime: bool,
+    // 第一帧的录制时间和播放开始的时间
+    started: Option<(u64, Instant)>,
+    // next_frame_in 预读的下一帧，None 表示还没有预读
+    pending: Option<Result<Option<(u64, Mat)>, String>>,
+}
+
+impl ReplaySource {
+    pub fn open(path: &Path, realtime: bool) -> Result<Self, String> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            reader: ReplayReader::open(path)?,
+            realtime,
+            started: None,
+            pending: None,
+        })
+    }
+
+    // 读取下一帧及其录制时间，跳过识别结果等其他记录
+    fn next_frame(&mut self) -> Result<Option<(u64, Mat)>, String> {
+        loop {
+            match self.reader.next_record()? {
+                Some(ReplayRecord::Frame { timestamp_ms, image }) => return Ok(Some((timestamp_ms, image))),
+                Some(_) => continue,
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+impl FrameSource for ReplaySource {
+    fn read(&mut self, frame: &mut Mat) -> opencv::Result<bool> {
+        let next = match self.pending.take() {
+            Some(pending) => pending,
+            None => self.next_frame(),
+        };
+        match next.map_err(|e| opencv::Error::new(opencv::core::StsError, e))? {
+            Some((timestamp_ms, image)) => {
+                self.started.get_or_insert((timestamp_ms, Instant::now()));
+                *frame = image;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn next_frame_in(&mut self) -> Option<Duration> {
+        if !self.realtime {
+            return None;
+        }
+        if self.pending.is_none() {
+            self.pending = Some(self.next_frame());
+        }
+        let timestamp_ms = self.pending.as_ref()?.as_ref().ok()?.as_ref()?.0;
+        let (first, start) = self.started?;
+        Duration::from_millis(timestamp_ms.saturating_sub(first)).checked_sub(start.elapsed())
+    }
+
+    fn kind(&self) -> SourceKind {
+        SourceKind::Replay
+    }
+
+    fn describe(&self) -> String {
+        format!("回放 {}（{}）", self.path.display(), self.reader.header().recorder)
+    }
+}
+
+// 一次尝试的回放文件名，按录制时间排序
+pub fn replay_file_name(created_at_ms: u64) -> String {
+    format!("attempt_{}.{}", created_at_ms, REPLAY_EXTENSION)
+}
+
+// 只保留最新的 keep 个回放文件，返回删除的数量
+pub fn prune_replays(dir: &Path, keep: usize) -> Result<usize, String> {
+    let mut files = list_replay_files(dir)?;
+    if files.len() <= keep {
+        return Ok(0);
+    }
+    files.sort();
+    let remove = files.len() - keep;
+    for path in &files[..remove] {
+        fs::remove_file(path).map_err(|e| format!("删除 {} 失败: {}", path.display(), e))?;
+    }
+    Ok(remove)
+}
+
+// 目录下的回放文件，目录不存在时返回空
+pub fn list_replay_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("读取 {} 失败: {}", dir.display(), e)),
+    };
+    Ok(entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().map_or(false, |e| e == REPLAY_EXTENSION))
+        .collect())
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use opencv::core::{self, Scalar, Size, CV_16UC1, CV_8UC3};
+
+    use super::*;
+
+    // 每个测试使用单独的文件，测试并行执行时互不影响
+    fn replay_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("replay_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join(replay_file_name(1))
+    }
+
+    fn header() -> ReplayHeader {
+        ReplayHeader {
+            created_at: 1,
+            recorder: String::from("unlock 1.2.0"),
+            models: vec![String::from("SFace 2021dec")],
+            source: String::from("摄像头 0"),
+            face_detection_threshold: 0.9,
+            threshold: Some(36.0),
+            liveness_threshold: None,
+        }
+    }
+
+    fn outcome() -> ReplayOutcome {
+        ReplayOutcome {
+            unlocked: true,
+            matched: Some(7),
+            reason: None,
+            attempt: Some(AttemptLog {
+                scores: BTreeMap::from([(7, 0.82), (8, 0.21)]),
+                ..Default::default()
+            }),
+        }
+    }
+
+    // 写入一帧 16 位红外画面和一帧彩色画面
+    fn write_replay(path: &Path) {
+        let mut writer = ReplayWriter::create(path, &header()).unwrap();
+        let ir = Mat::new_size_with_default(Size::new(8, 6), CV_16UC1, Scalar::all(1200.0)).unwrap();
+        assert_eq!(writer.write_frame(1000, &ir).unwrap(), 0);
+        writer
+            .write_outputs(&FrameOutputs {
+                frame: 0,
+                modality: Some(Modality::Ir),
+                scores: BTreeMap::from([(7, 0.82)]),
+                ..Default::default()
+            })
+            .unwrap();
+        let rgb = Mat::new_size_with_default(Size::new(8, 6), CV_8UC3, Scalar::new(10.0, 20.0, 30.0, 0.0)).unwrap();
+        assert_eq!(writer.write_frame(1033, &rgb).unwrap(), 1);
+        assert_eq!(writer.finish(&outcome()).unwrap(), 2);
+    }
+
+    #[test]
+    fn round_trips_frames_and_results() {
+        let path = replay_path("round_trip");
+        write_replay(&path);
+
+        let mut reader = ReplayReader::open(&path).unwrap();
+        assert_eq!(reader.header().recorder, "unlock 1.2.0");
+        assert_eq!(reader.header().threshold, Some(36.0));
+
+        // 16 位画面原样保存
+        match reader.next_record().unwrap() {
+            Some(ReplayRecord::Frame { timestamp_ms, image }) => {
+                assert_eq!(timestamp_ms, 1000);
+                assert_eq!(image.typ(), CV_16UC1);
+                assert_eq!(image.size().unwrap(), Size::new(8, 6));
+                let (mut min, mut max) = (0.0, 0.0);
+                core::min_max_loc(&image, Some(&mut min), Some(&mut max), None, None, &core::no_array()).unwrap();
+                assert_eq!((min, max), (1200.0, 1200.0));
+            }
+            _ => panic!("第一条应该是画面"),
+        }
+        match reader.next_record().unwrap() {
+            Some(ReplayRecord::Outputs(outputs)) => {
+                assert_eq!(outputs.modality, Some(Modality::Ir));
+                assert_eq!(outputs.scores.get(&7), Some(&0.82));
+            }
+            _ => panic!("画面之后应该是识别结果"),
+        }
+        match reader.next_record().unwrap() {
+            Some(ReplayRecord::Frame { timestamp_ms, image }) => {
+                assert_eq!(timestamp_ms, 1033);
+                assert_eq!(image.typ(), CV_8UC3);
+            }
+            _ => panic!("第三条应该是画面"),
+        }
+        match reader.next_record().unwrap() {
+            Some(ReplayRecord::Outcome(outcome)) => {
+                assert_eq!(outcome.matched, Some(7));
+                assert_eq!(outcome.attempt.unwrap().scores.get(&8), Some(&0.21));
+            }
+            _ => panic!("最后一条应该是尝试结果"),
+        }
+        assert!(reader.next_record().unwrap().is_none());
+
+        let summary = summarize_replay(&path).unwrap();
+        assert_eq!(summary.frames, 2);
+        assert!(summary.outcome.unwrap().unlocked);
+    }
+
+    #[test]
+    fn interrupted_recordings_are_readable() {
+        let path = replay_path("interrupted");
+        write_replay(&path);
+        // 去掉尝试结果的最后几个字节，模拟录制中断
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+
+        let summary = summarize_replay(&path).unwrap();
+        assert_eq!(summary.frames, 2);
+        assert!(summary.outcome.is_none());
+
+        // 作为画面来源播放两帧后结束
+        let mut source = ReplaySource::open(&path, false).unwrap();
+        let mut frame = Mat::default();
+        assert!(source.read(&mut frame).unwrap());
+        assert!(source.read(&mut frame).unwrap());
+        assert_eq!(frame.typ(), CV_8UC3);
+        assert!(!source.read(&mut frame).unwrap());
+    }
+
+    #[test]
+    fn realtime_source_leaves_waiting_to_the_caller() {
+        let path = replay_path("realtime");
+        write_replay(&path);
+
+        let mut source = ReplaySource::open(&path, true).unwrap();
+        let mut frame = Mat::default();
+        // 第一帧不需要等待
+        assert_eq!(source.next_frame_in(), None);
+        let start = Instant::now();
+        assert!(source.read(&mut frame).unwrap());
+        assert_eq!(frame.typ(), CV_16UC1);
+        // 第二帧比第一帧晚 33 ms，read 不等待，由调用方按 next_frame_in 在锁外等待
+        let wait = source.next_frame_in().unwrap();
+        assert!(wait <= Duration::from_millis(33));
+        assert!(source.read(&mut frame).unwrap());
+        assert!(start.elapsed() < Duration::from_millis(33));
+        assert_eq!(frame.typ(), CV_8UC3);
+        assert_eq!(source.next_frame_in(), None);
+        assert!(!source.read(&mut frame).unwrap());
+
+        // 不按录制间隔播放时不需要等待
+        let mut source = ReplaySource::open(&path, false).unwrap();
+        assert!(source.read(&mut frame).unwrap());
+        assert_eq!(source.next_frame_in(), None);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let path = replay_path("other");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, b"not a replay file").unwrap();
+        assert!(ReplayReader::open(&path).is_err());
+    }
+
+    #[test]
+    fn prunes_oldest_replays() {
+        let dir = replay_path("prune").parent().unwrap().to_path_buf();
+        fs::create_dir_all(&dir).unwrap();
+        for created_at in [1000, 3000, 2000] {
+            fs::write(dir.join(replay_file_name(created_at)), b"").unwrap();
+        }
+        fs::write(dir.join("notes.txt"), b"").unwrap();
+
+        assert_eq!(prune_replays(&dir, 2).unwrap(), 1);
+        let mut left = list_replay_files(&dir).unwrap();
+        left.sort();
+        assert_eq!(
+            left,
+            vec![dir.join(replay_file_name(2000)), dir.join(replay_file_name(3000))]
+        );
+    }
+}
@@ -0,0 +1,524 @@
+// 翻拍、打印攻击的图像特征检查，作为活体模型的补充
+// 每一项输出 0 ~ 1 的攻击可能性，越大越像攻击，再按融合规则与活体模型的分数合并
+// moire: 屏幕像素网格和相机传感器叠加产生的摩尔纹，表现为频谱中高频处孤立的尖峰
+// specular: 屏幕玻璃、相纸表面的大片高光
+// bezel: 人脸周围的手机/平板边框，表现为人脸框外较长的水平、竖直直线
+// gamut: 打印和屏幕的色域较窄，人脸区域的色度分布异常集中；红外画面没有色度，不做这一项
+use opencv::{
+    core::{self, Mat, Point, Rect, Vector, CV_32F},
+    imgproc,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{face_pipeline::FACE_ROW_LEN, frame_normalize::Modality};
+
+// 频谱分析使用的尺寸
+const SPECTRUM_SIZE: i32 = 128;
+// 高频区域从半径的多少开始
+const HIGH_FREQ_START: f32 = 0.25;
+// 高频区域对数幅度的尖峰比平均值高出的比例达到此值时，摩尔纹分数为 1
+// 比较的是 ln(1 + 幅度)，尖峰是平均值的 2.2 倍相当于线性幅度约为平均幅度的 2.2 次方，
+// 自然图像的高频能量平滑衰减，只有屏幕像素网格这类周期性纹理才会出现这样孤立的尖峰
+const MOIRE_PEAK_EXCESS: f32 = 1.2;
+// 高光面积占人脸面积的比例达到此值时，高光分数为 1
+const SPECULAR_FULL_RATIO: f32 = 0.08;
+// 色度标准差低于此值时色域分数为 1，高于 GAMUT_NATURAL_STD 时为 0
+const GAMUT_FLAT_STD: f32 = 3.0;
+const GAMUT_NATURAL_STD: f32 = 9.0;
+// 边框检测区域为人脸框的倍数
+const BEZEL_REGION_SCALE: f32 = 2.5;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct SpoofScores {
+    pub moire: f32,
+    pub specular: f32,
+    pub bezel: f32,
+    pub gamut: f32,
+}
+
+impl SpoofScores {
+    pub fn max(&self) -> f32 {
+        self.moire.max(self.specular).max(self.bezel).max(self.gamut)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FusionRule {
+    // 活体分数与各项 (1 - 攻击分数) 加权平均
+    Weighted,
+    // 以活体模型为准，任意一项攻击分数超过否决阈值时拒绝
+    Veto,
+    // 取活体分数与各项 (1 - 攻击分数) 中的最小值，最严格
+    Min,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FusionConfig {
+    // 是否计算四项检查，关闭时只用活体模型
+    pub enabled: bool,
+    pub rule: FusionRule,
+    // 活体模型和四项检查的权重：[活体, 摩尔纹, 高光, 边框, 色域]
+    pub weights: [f32; 5],
+    // Veto 规则下单项攻击分数的否决阈值
+    pub veto_threshold: f32,
+    // 融合后的分数达到此值才算通过
+    pub threshold: f32,
+}
+
+impl Default for FusionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rule: FusionRule::Veto,
+            weights: [0.6, 0.15, 0.1, 0.1, 0.05],
+            veto_threshold: 0.8,
+            threshold: 0.5,
+        }
+    }
+}
+
+impl FusionConfig {
+    // 从设置中读取，缺失时使用默认值；通过阈值沿用 livenessThreshold
+    pub fn from_options(get: impl Fn(&str) -> Option<String>) -> Self {
+        let default = Self::default();
+        Self {
+            enabled: get("spoofHeuristicsEnabled").as_deref() == Some("true"),
+            rule: match get("spoofFusionRule").as_deref() {
+                Some("weighted") => FusionRule::Weighted,
+                Some("min") => FusionRule::Min,
+                _ => FusionRule::Veto,
+            },
+            weights: default.weights,
+            veto_threshold: get("spoofVetoThreshold")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.veto_threshold),
+            threshold: get("livenessThreshold")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.threshold),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FusionResult {
+    pub rule: FusionRule,
+    pub liveness_score: f32,
+    pub heuristics: SpoofScores,
+    pub score: f32,
+    pub passed: bool,
+}
+
+// 按规则融合活体模型分数（越大越像真人）和攻击分数
+pub fn fuse(liveness_score: f32, heuristics: &SpoofScores, config: &FusionConfig) -> FusionResult {
+    let real = [
+        liveness_score,
+        1.0 - heuristics.moire,
+        1.0 - heuristics.specular,
+        1.0 - heuristics.bezel,
+        1.0 - heuristics.gamut,
+    ];
+    let score = match config.rule {
+        FusionRule::Weighted => {
+            let total: f32 = config.weights.iter().sum();
+            if total <= 0.0 {
+                liveness_score
+            } else {
+                real.iter().zip(config.weights.iter()).map(|(s, w)| s * w).sum::<f32>() / total
+            }
+        }
+        FusionRule::Veto => {
+            if heuristics.max() >= config.veto_threshold {
+                0.0
+            } else {
+                liveness_score
+            }
+        }
+        FusionRule::Min => real.iter().cloned().fold(f32::MAX, f32::min),
+    };
+
+    FusionResult {
+        rule: config.rule,
+        liveness_score,
+        heuristics: *heuristics,
+        score,
+        passed: score >= config.threshold,
+    }
+}
+
+// 计算四项攻击分数
+// img: BGR 原图，values: YuNet 的一行数据
+// modality: 画面的成像方式，红外画面转成的 BGR 三个通道相同，色域分数固定为 0
+pub fn spoof_scores(img: &Mat, values: &[f32; FACE_ROW_LEN], modality: Modality) -> Result<SpoofScores, String> {
+    let face_rect = scaled_rect(img, values, 1.0).ok_or_else(|| String::from("人脸框超出图片范围"))?;
+    let face = Mat::roi(img, face_rect).map_err(|e| format!("裁剪人脸失败: {:?}", e))?;
+    let mut gray = Mat::default();
+    imgproc::cvt_color(&face, &mut gray, imgproc::COLOR_BGR2GRAY, 0)
+        .map_err(|e| format!("转换灰度图失败: {:?}", e))?;
+
+    Ok(SpoofScores {
+        moire: moire_score(&gray)?,
+        specular: specular_score(&face)?,
+        bezel: bezel_score(img, values, face_rect)?,
+        gamut: match modality {
+            Modality::Rgb => gamut_score(&face)?,
+            Modality::Ir => 0.0,
+        },
+    })
+}
+
+// 频谱高频区域中最强尖峰与平均能量之比
+pub fn moire_score(gray: &Mat) -> Result<f32, String> {
+    let mut resized = Mat::default();
+    imgproc::resize(
+        gray,
+        &mut resized,
+        core::Size::new(SPECTRUM_SIZE, SPECTRUM_SIZE),
+        0.0,
+        0.0,
+        imgproc::INTER_AREA,
+    )
+    .map_err(|e| format!("缩放人脸失败: {:?}", e))?;
+    let mut float = Mat::default();
+    resized
+        .convert_to(&mut float, CV_32F, 1.0, 0.0)
+        .map_err(|e| format!("转换浮点图失败: {:?}", e))?;
+
+    let mut complex = Mat::default();
+    core::dft(&float, &mut complex, core::DFT_COMPLEX_OUTPUT, 0).map_err(|e| format!("计算频谱失败: {:?}", e))?;
+    let mut planes = Vector::<Mat>::new();
+    core::split(&complex, &mut planes).map_err(|e| format!("拆分频谱失败: {:?}", e))?;
+    let mut magnitude = Mat::default();
+    core::magnitude(
+        &planes.get(0).map_err(|e| format!("读取频谱失败: {:?}", e))?,
+        &planes.get(1).map_err(|e| format!("读取频谱失败: {:?}", e))?,
+        &mut magnitude,
+    )
+    .map_err(|e| format!("计算频谱幅度失败: {:?}", e))?;
+
+    // 未做中心化，频率 (u, v) 与 (N-u, N-v) 对称，只需统计每个点到最近角的距离
+    let half = SPECTRUM_SIZE as f32 / 2.0;
+    let (mut sum, mut count, mut peak) = (0f32, 0u32, 0f32);
+    for v in 0..SPECTRUM_SIZE {
+        for u in 0..SPECTRUM_SIZE {
+            let fu = (u as f32).min((SPECTRUM_SIZE - u) as f32);
+            let fv = (v as f32).min((SPECTRUM_SIZE - v) as f32);
+            let radius = (fu * fu + fv * fv).sqrt() / half;
+            if radius < HIGH_FREQ_START || radius > 1.0 {
+                continue;
+            }
+            let value = (1.0 + *magnitude.at_2d::<f32>(v, u).map_err(|e| format!("读取频谱失败: {:?}", e))?).ln();
+            sum += value;
+            count += 1;
+            peak = peak.max(value);
+        }
+    }
+    if count == 0 || sum <= 0.0 {
+        return Ok(0.0);
+    }
+    let mean = sum / count as f32;
+    // 自然图像的比值接近 1
+    let excess = peak / mean - 1.0;
+    Ok((excess / MOIRE_PEAK_EXCESS).clamp(0.0, 1.0))
+}
+
+// 高亮低饱和（接近白色）像素的面积比例
+pub fn specular_score(face: &Mat) -> Result<f32, String> {
+    let mut hsv = Mat::default();
+    imgproc::cvt_color(face, &mut hsv, imgproc::COLOR_BGR2HSV, 0).map_err(|e| format!("转换 HSV 失败: {:?}", e))?;
+    let mut mask = Mat::default();
+    core::in_range(
+        &hsv,
+        &core::Scalar::new(0.0, 0.0, 245.0, 0.0),
+        &core::Scalar::new(180.0, 40.0, 255.0, 0.0),
+        &mut mask,
+    )
+    .map_err(|e| format!("计算高光区域失败: {:?}", e))?;
+    let total = (mask.rows() * mask.cols()).max(1) as f32;
+    let highlight = core::count_non_zero(&mask).map_err(|e| format!("计算高光区域失败: {:?}", e))? as f32;
+    Ok((highlight / total / SPECULAR_FULL_RATIO).clamp(0.0, 1.0))
+}
+
+// 人脸周围区域中，人脸框外的长水平/竖直直线
+pub fn bezel_score(img: &Mat, values: &[f32; FACE_ROW_LEN], face_rect: Rect) -> Result<f32, String> {
+    let region = match scaled_rect(img, values, BEZEL_REGION_SCALE) {
+        Some(region) => region,
+        None => return Ok(0.0),
+    };
+    let area = Mat::roi(img, region).map_err(|e| format!("裁剪边框区域失败: {:?}", e))?;
+    let mut gray = Mat::default();
+    imgproc::cvt_color(&area, &mut gray, imgproc::COLOR_BGR2GRAY, 0)
+        .map_err(|e| format!("转换灰度图失败: {:?}", e))?;
+    let mut edges = Mat::default();
+    imgproc::canny(&gray, &mut edges, 50.0, 150.0, 3, false).map_err(|e| format!("边缘检测失败: {:?}", e))?;
+
+    let min_length = face_rect.width.min(face_rect.height) as f64;
+    let mut lines = Vector::<core::Vec4i>::new();
+    imgproc::hough_lines_p(&edges, &mut lines, 1.0, std::f64::consts::PI / 180.0, 60, min_length, 5.0)
+        .map_err(|e| format!("直线检测失败: {:?}", e))?;
+
+    // 人脸框在检测区域内的坐标
+    let inner = Rect::new(
+        face_rect.x - region.x,
+        face_rect.y - region.y,
+        face_rect.width,
+        face_rect.height,
+    );
+    let mut sides = [false; 4];
+    for line in lines.iter() {
+        let (a, b) = (Point::new(line[0], line[1]), Point::new(line[2], line[3]));
+        let (dx, dy) = ((b.x - a.x).abs(), (b.y - a.y).abs());
+        // 只看接近水平或竖直的直线
+        if dx > 0 && dy > 0 && (dx.min(dy) as f32 / dx.max(dy) as f32) > 0.1 {
+            continue;
+        }
+        let mid = Point::new((a.x + b.x) / 2, (a.y + b.y) / 2);
+        if inner.contains(mid) {
+            continue;
+        }
+        if dy <= dx {
+            sides[if mid.y < inner.y { 0 } else { 1 }] = true;
+        } else {
+            sides[if mid.x < inner.x { 2 } else { 3 }] = true;
+        }
+    }
+    // 人脸四周出现的边越多，越像拿着屏幕或照片
+    Ok(sides.iter().filter(|s| **s).count() as f32 / 4.0)
+}
+
+// 人脸区域 Cr、Cb 通道的标准差过小
+pub fn gamut_score(face: &Mat) -> Result<f32, String> {
+    let mut ycrcb = Mat::default();
+    imgproc::cvt_color(face, &mut ycrcb, imgproc::COLOR_BGR2YCrCb, 0)
+        .map_err(|e| format!("转换 YCrCb 失败: {:?}", e))?;
+    let mut mean = Mat::default();
+    let mut stddev = Mat::default();
+    core::mean_std_dev(&ycrcb, &mut mean, &mut stddev, &core::no_array())
+        .map_err(|e| format!("计算色度失败: {:?}", e))?;
+    let cr = *stddev.at::<f64>(1).map_err(|e| format!("读取色度失败: {:?}", e))? as f32;
+    let cb = *stddev.at::<f64>(2).map_err(|e| format!("读取色度失败: {:?}", e))? as f32;
+    let chroma = (cr + cb) / 2.0;
+    Ok(((GAMUT_NATURAL_STD - chroma) / (GAMUT_NATURAL_STD - GAMUT_FLAT_STD)).clamp(0.0, 1.0))
+}
+
+// 以人脸中心按比例放大的矩形，裁剪到图片范围内
+fn scaled_rect(img: &Mat, values: &[f32; FACE_ROW_LEN], scale: f32) -> Option<Rect> {
+    let (cx, cy) = (values[0] + values[2] / 2.0, values[1] + values[3] / 2.0);
+    let (w, h) = (values[2] * scale, values[3] * scale);
+    let left = ((cx - w / 2.0).max(0.0)) as i32;
+    let top = ((cy - h / 2.0).max(0.0)) as i32;
+    let right = ((cx + w / 2.0) as i32).min(img.cols());
+    let bottom = ((cy + h / 2.0) as i32).min(img.rows());
+    if right <= left || bottom <= top {
+        return None;
+    }
+    Some(Rect::new(left, top, right - left, bottom - top))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use opencv::{
+        core::{Scalar, Size, CV_8UC1, CV_8UC3},
+        imgcodecs,
+    };
+
+    use super::*;
+
+    // 200×200 画面正中的人脸框，关键点和置信度不参与计算
+    fn centered_face() -> [f32; FACE_ROW_LEN] {
+        let mut values = [0.0; FACE_ROW_LEN];
+        values[..4].copy_from_slice(&[50.0, 50.0, 100.0, 100.0]);
+        values
+    }
+
+    fn uniform_frame(value: f64) -> Mat {
+        Mat::new_size_with_default(Size::new(200, 200), CV_8UC3, Scalar::all(value)).unwrap()
+    }
+
+    #[test]
+    fn gamut_is_skipped_for_ir() {
+        // 三个通道相同的画面色度标准差为 0，按彩色画面计算时色域分数为 1
+        let frame = uniform_frame(128.0);
+        let rgb = spoof_scores(&frame, &centered_face(), Modality::Rgb).unwrap();
+        let ir = spoof_scores(&frame, &centered_face(), Modality::Ir).unwrap();
+        assert_eq!(rgb.gamut, 1.0);
+        assert_eq!(ir.gamut, 0.0);
+    }
+
+    #[test]
+    fn specular_counts_bright_unsaturated_pixels() {
+        assert_eq!(specular_score(&uniform_frame(255.0)).unwrap(), 1.0);
+        assert_eq!(specular_score(&uniform_frame(128.0)).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn moire_detects_periodic_grating() {
+        // 竖条纹的频率为半径的 0.625，落在高频区域
+        let mut gray =
+            Mat::new_size_with_default(Size::new(SPECTRUM_SIZE, SPECTRUM_SIZE), CV_8UC1, Scalar::all(0.0)).unwrap();
+        for row in 0..SPECTRUM_SIZE {
+            for col in 0..SPECTRUM_SIZE {
+                let phase = 2.0 * std::f32::consts::PI * 40.0 * col as f32 / SPECTRUM_SIZE as f32;
+                *gray.at_2d_mut::<u8>(row, col).unwrap() = (128.0 + 100.0 * phase.sin()) as u8;
+            }
+        }
+        assert!(moire_score(&gray).unwrap() > 0.9);
+    }
+
+    fn scores(moire: f32, specular: f32, bezel: f32, gamut: f32) -> SpoofScores {
+        SpoofScores {
+            moire,
+            specular,
+            bezel,
+            gamut,
+        }
+    }
+
+    fn rule(rule: FusionRule) -> FusionConfig {
+        FusionConfig {
+            enabled: true,
+            rule,
+            ..FusionConfig::default()
+        }
+    }
+
+    #[test]
+    fn veto_rejects_any_strong_attack_score() {
+        let config = rule(FusionRule::Veto);
+        let clean = fuse(0.9, &scores(0.3, 0.1, 0.0, 0.2), &config);
+        assert_eq!(clean.score, 0.9);
+        assert!(clean.passed);
+        // 只有一项达到否决阈值也拒绝，活体分数再高也没用
+        let vetoed = fuse(0.99, &scores(0.0, 0.0, 0.8, 0.0), &config);
+        assert_eq!(vetoed.score, 0.0);
+        assert!(!vetoed.passed);
+    }
+
+    #[test]
+    fn weighted_averages_liveness_and_heuristics() {
+        let config = rule(FusionRule::Weighted);
+        // 权重 [0.6, 0.15, 0.1, 0.1, 0.05]：0.6 × 0.5 + 0.15 × 1 + 0.1 × 1 + 0.1 × 0 + 0.05 × 1 = 0.6
+        let fused = fuse(0.5, &scores(0.0, 0.0, 1.0, 0.0), &config);
+        assert!((fused.score - 0.6).abs() < 1e-6);
+        assert!(fused.passed);
+
+        // 权重全为 0 时只看活体分数
+        let zero = FusionConfig {
+            weights: [0.0; 5],
+            ..config
+        };
+        assert_eq!(fuse(0.3, &scores(1.0, 1.0, 1.0, 1.0), &zero).score, 0.3);
+    }
+
+    #[test]
+    fn min_takes_the_weakest_signal() {
+        let fused = fuse(0.9, &scores(0.1, 0.7, 0.0, 0.2), &rule(FusionRule::Min));
+        assert!((fused.score - 0.3).abs() < 1e-6);
+        assert!(!fused.passed);
+        assert_eq!(fused.rule, FusionRule::Min);
+    }
+
+    #[test]
+    fn reads_fusion_options() {
+        let options = |key: &str| match key {
+            "spoofHeuristicsEnabled" => Some(String::from("true")),
+            "spoofFusionRule" => Some(String::from("weighted")),
+            "spoofVetoThreshold" => Some(String::from("0.7")),
+            "livenessThreshold" => Some(String::from("0.6")),
+            _ => None,
+        };
+        let config = FusionConfig::from_options(options);
+        assert!(config.enabled);
+        assert_eq!(config.rule, FusionRule::Weighted);
+        assert_eq!(config.veto_threshold, 0.7);
+        assert_eq!(config.threshold, 0.6);
+        // 未知的规则按否决处理
+        assert_eq!(FusionConfig::from_options(|_| None).rule, FusionRule::Veto);
+    }
+
+    // 在 200×200 画面上画出人脸框外的直线
+    fn frame_with_lines(lines: &[(Point, Point)]) -> Mat {
+        let mut frame = uniform_frame(160.0);
+        for (a, b) in lines {
+            imgproc::line(&mut frame, *a, *b, Scalar::all(20.0), 4, imgproc::LINE_8, 0).unwrap();
+        }
+        frame
+    }
+
+    #[test]
+    fn bezel_counts_sides_with_long_lines() {
+        let face = centered_face();
+        let face_rect = Rect::new(50, 50, 100, 100);
+        let score = |frame: &Mat| bezel_score(frame, &face, face_rect).unwrap();
+
+        // 没有直线
+        assert_eq!(score(&uniform_frame(160.0)), 0.0);
+        // 只有人脸上方一条边
+        let top = frame_with_lines(&[(Point::new(10, 20), Point::new(190, 20))]);
+        assert_eq!(score(&top), 0.25);
+        // 四周都有边框，像拿着手机或平板
+        let bezel = frame_with_lines(&[
+            (Point::new(10, 20), Point::new(190, 20)),
+            (Point::new(10, 180), Point::new(190, 180)),
+            (Point::new(20, 10), Point::new(20, 190)),
+            (Point::new(180, 10), Point::new(180, 190)),
+        ]);
+        assert_eq!(score(&bezel), 1.0);
+    }
+
+    #[test]
+    fn bezel_ignores_short_and_diagonal_lines() {
+        let face = centered_face();
+        let face_rect = Rect::new(50, 50, 100, 100);
+        // 短于人脸框的直线和斜线不算边框
+        let frame = frame_with_lines(&[
+            (Point::new(10, 20), Point::new(60, 20)),
+            (Point::new(10, 190), Point::new(190, 10)),
+        ]);
+        assert_eq!(bezel_score(&frame, &face, face_rect).unwrap(), 0.0);
+    }
+
+    // tests/fixtures/spoof 下的合成图片，200×200，人脸框固定为 (36, 36, 128, 128)，没有真实人脸，不经过检测
+    // genuine: 彩色渐变加噪声；screen: 叠加屏幕像素网格的周期纹理；print: 只有亮度变化的窄色域；bezel: 人脸四周有深色边框
+    // 文件名第一个 _ 之前为类别，与 face_eval 的 spoof_groups 一致
+    const SPOOF_FIXTURES: [(&str, &[u8]); 5] = [
+        ("genuine_1", include_bytes!("../../tests/fixtures/spoof/genuine_1.png")),
+        ("genuine_2", include_bytes!("../../tests/fixtures/spoof/genuine_2.png")),
+        ("screen_1", include_bytes!("../../tests/fixtures/spoof/screen_1.png")),
+        ("print_1", include_bytes!("../../tests/fixtures/spoof/print_1.png")),
+        ("bezel_1", include_bytes!("../../tests/fixtures/spoof/bezel_1.png")),
+    ];
+
+    #[test]
+    fn fixture_error_rates_per_attack() {
+        let mut face = [0.0; FACE_ROW_LEN];
+        face[..4].copy_from_slice(&[36.0, 36.0, 128.0, 128.0]);
+        // 没有活体模型时活体分数按 1 计，与解锁流程一致，只看四项检查
+        let config = rule(FusionRule::Veto);
+
+        // 类别 → (图片数, 通过数)
+        let mut groups: BTreeMap<&str, (u32, u32)> = BTreeMap::new();
+        for (name, bytes) in SPOOF_FIXTURES {
+            let frame = imgcodecs::imdecode(&Vector::<u8>::from_slice(bytes), imgcodecs::IMREAD_COLOR).unwrap();
+            let heuristics = spoof_scores(&frame, &face, Modality::Rgb).unwrap();
+            let fused = fuse(1.0, &heuristics, &config);
+            println!("{}: {:?} -> {:.2}", name, heuristics, fused.score);
+            let group = groups.entry(name.split('_').next().unwrap()).or_default();
+            group.0 += 1;
+            group.1 += fused.passed as u32;
+        }
+
+        // 真实图片的 BPCER（被拒绝的比例）和每一类攻击的 APCER（被接受的比例）
+        let rate = |(total, passed): (u32, u32), accepted: bool| {
+            let count = if accepted { passed } else { total - passed };
+            count as f32 / total as f32
+        };
+        assert_eq!(rate(groups["genuine"], false), 0.0);
+        for attack in ["screen", "print", "bezel"] {
+            assert_eq!(rate(groups[attack], true), 0.0, "{}", attack);
+        }
+    }
+}
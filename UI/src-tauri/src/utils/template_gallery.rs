@@ -0,0 +1,477 @@
+// 面容模板库
+// 录入时的 .face 仍然是主模板，这里保存同一面容的其他模板（自适应学习到的等），文件为 faces/<face_token>.gallery
+// 界面和解锁服务都可以使用
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::frame_normalize::{modality_compatible, Modality};
+
+// 学习到的模板写入的审计日志
+pub const AUDIT_LOG_FILE: &str = "learned_templates.log";
+const GALLERY_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TemplateSource {
+    // 录入时生成
+    Enrolled,
+    // 解锁成功后自适应学习
+    Learned,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Template {
+    pub id: String,
+    pub source: TemplateSource,
+    // 旧模板没有这个字段，都是彩色摄像头生成的
+    #[serde(default)]
+    pub modality: Modality,
+    pub feature: Vec<f32>,
+    // 秒级时间戳
+    pub created_at: u64,
+    // 作为最佳模板匹配成功的次数，用于淘汰
+    #[serde(default)]
+    pub match_count: u32,
+    #[serde(default)]
+    pub last_matched_at: Option<u64>,
+    #[serde(default)]
+    pub quality: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateGallery {
+    pub version: u32,
+    pub templates: Vec<Template>,
+}
+
+impl Default for TemplateGallery {
+    fn default() -> Self {
+        Self {
+            version: GALLERY_VERSION,
+            templates: Vec::new(),
+        }
+    }
+}
+
+// face_token 来自界面，拼接路径前检查，只允许 faces 目录下的文件
+pub fn validate_face_token(face_token: &str) -> Result<(), String> {
+    if face_token.is_empty() || face_token.contains(['/', '\\']) || face_token.contains("..") {
+        return Err(format!("面容标识错误 {}", face_token));
+    }
+    Ok(())
+}
+
+pub fn gallery_path(faces_dir: &Path, face_token: &str) -> PathBuf {
+    faces_dir.join(format!("{}.gallery", face_token))
+}
+
+impl TemplateGallery {
+    // 读取模板库，文件不存在时返回空库
+    pub fn load(faces_dir: &Path, face_token: &str) -> Result<Self, String> {
+        let path = gallery_path(faces_dir, face_token);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("读取模板库 {} 失败: {}", path.display(), e))?;
+        serde_json::from_str(&text).map_err(|e| format!("解析模板库 {} 失败: {}", path.display(), e))
+    }
+
+    // 先写临时文件再替换，避免写到一半断电导致模板库损坏
+    pub fn save(&self, faces_dir: &Path, face_token: &str) -> Result<(), String> {
+        let path = gallery_path(faces_dir, face_token);
+        let temp_path = path.with_extension("gallery.tmp");
+        let text = serde_json::to_string(self).map_err(|e| format!("序列化模板库失败: {}", e))?;
+        fs::write(&temp_path, text)
+            .map_err(|e| format!("写入模板库 {} 失败: {}", temp_path.display(), e))?;
+        fs::rename(&temp_path, &path)
+            .map_err(|e| format!("替换模板库 {} 失败: {}", path.display(), e))
+    }
+
+    pub fn learned_count(&self) -> usize {
+        self.templates
+            .iter()
+            .filter(|t| t.source == TemplateSource::Learned)
+            .count()
+    }
+
+    // 与库中同一成像方式的模板比对，返回 (模板下标, 最高相似度)
+    pub fn best_match(&self, feature: &[f32], modality: Modality) -> Option<(usize, f32)> {
+        self.templates
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| modality_compatible(modality, t.modality))
+            .map(|(i, t)| (i, cosine_similarity(&t.feature, feature)))
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    // 记录一次匹配成功
+    pub fn record_match(&mut self, index: usize) {
+        if let Some(template) = self.templates.get_mut(index) {
+            template.match_count += 1;
+            template.last_matched_at = Some(now_secs());
+        }
+    }
+
+    // 最没用的学习模板：匹配次数最少，其次最久没有匹配过；录入的模板永远不会被淘汰
+    fn least_useful_learned(&self) -> Option<usize> {
+        self.templates
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.source == TemplateSource::Learned)
+            .min_by_key(|(_, t)| (t.match_count, t.last_matched_at.unwrap_or(t.created_at)))
+            .map(|(i, _)| i)
+    }
+}
+
+// 自适应学习配置，对应 options 表中的 adaptiveTemplate* 设置
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AdaptiveConfig {
+    pub enabled: bool,
+    // 相似度至少超过阈值多少才学习（0 ~ 1）
+    pub min_margin: f32,
+    // 每个面容最多保存的学习模板数量
+    pub max_learned: usize,
+    // 与已有模板过于相似时不学习，没有新的信息
+    pub max_redundancy: f32,
+}
+
+impl Default for AdaptiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_margin: 0.15,
+            max_learned: 5,
+            max_redundancy: 0.92,
+        }
+    }
+}
+
+impl AdaptiveConfig {
+    pub fn from_options(get: impl Fn(&str) -> Option<String>) -> Self {
+        let default = Self::default();
+        Self {
+            enabled: get("adaptiveTemplateEnabled").as_deref() == Some("true"),
+            min_margin: get("adaptiveTemplateMargin")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.min_margin),
+            max_learned: get("adaptiveTemplateMax")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.max_learned),
+            max_redundancy: default.max_redundancy,
+        }
+    }
+}
+
+// 一次解锁的上下文
+#[derive(Debug, Clone, Copy)]
+pub struct UnlockContext {
+    // 本次最高相似度
+    pub score: f32,
+    // 面容的判定阈值（0 ~ 1）
+    pub threshold: f32,
+    // 活体检测结果，未开启活体检测时为 None
+    pub liveness_passed: Option<bool>,
+    // 本次解锁是否走了密码兜底
+    pub password_fallback: bool,
+    pub quality: Option<f32>,
+    pub modality: Modality,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum LearnDecision {
+    Learned {
+        template_id: String,
+        replaced: Option<String>,
+    },
+    Skipped {
+        reason: String,
+    },
+}
+
+// 解锁成功后判断是否学习本次的特征
+// 学习的条件：开启了自适应学习、相似度余量足够、活体检测通过、没有走密码兜底、与已有模板不重复
+// primary: 录入时的主模板（.face），不在模板库中，也要参与重复检查
+pub fn consider_learning(
+    gallery: &mut TemplateGallery,
+    primary: Option<&Template>,
+    feature: &[f32],
+    context: &UnlockContext,
+    config: &AdaptiveConfig,
+) -> LearnDecision {
+    let skip = |reason: &str| LearnDecision::Skipped {
+        reason: reason.to_string(),
+    };
+
+    if !config.enabled || config.max_learned == 0 {
+        return skip("未开启自适应学习");
+    }
+    if context.password_fallback {
+        return skip("本次解锁使用了密码兜底");
+    }
+    if context.liveness_passed != Some(true) {
+        return skip("活体检测未通过或未开启");
+    }
+    if context.score - context.threshold < config.min_margin {
+        return skip("相似度余量不足");
+    }
+    let primary_similarity = primary
+        .filter(|t| modality_compatible(context.modality, t.modality))
+        .map(|t| cosine_similarity(&t.feature, feature));
+    let gallery_similarity = gallery
+        .best_match(feature, context.modality)
+        .map(|(_, s)| s);
+    let redundancy = primary_similarity
+        .into_iter()
+        .chain(gallery_similarity)
+        .fold(f32::MIN, f32::max);
+    if redundancy >= config.max_redundancy {
+        return skip("与已有模板过于相似");
+    }
+
+    let mut replaced = None;
+    if gallery.learned_count() >= config.max_learned {
+        if let Some(index) = gallery.least_useful_learned() {
+            replaced = Some(gallery.templates.remove(index).id);
+        }
+    }
+
+    let template_id = uuid::Uuid::new_v4().to_string();
+    gallery.templates.push(Template {
+        id: template_id.clone(),
+        source: TemplateSource::Learned,
+        modality: context.modality,
+        feature: feature.to_vec(),
+        created_at: now_secs(),
+        match_count: 0,
+        last_matched_at: None,
+        quality: context.quality,
+    });
+
+    LearnDecision::Learned {
+        template_id,
+        replaced,
+    }
+}
+
+// 审计日志的一行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub time: u64,
+    pub face_token: String,
+    // learned 学习，replaced 被淘汰，reset 清空
+    pub action: String,
+    pub template_id: Option<String>,
+    pub score: Option<f32>,
+    pub threshold: Option<f32>,
+    pub liveness_passed: Option<bool>,
+}
+
+// 追加一行审计日志（JSON Lines）
+pub fn append_audit(faces_dir: &Path, entry: &AuditEntry) -> Result<(), String> {
+    let path = faces_dir.join(AUDIT_LOG_FILE);
+    let line = serde_json::to_string(entry).map_err(|e| format!("序列化审计日志失败: {}", e))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("打开审计日志 {} 失败: {}", path.display(), e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("写入审计日志失败: {}", e))
+}
+
+// 记录一次学习决定，跳过的不记录
+pub fn audit_decision(
+    faces_dir: &Path,
+    face_token: &str,
+    decision: &LearnDecision,
+    context: &UnlockContext,
+) -> Result<(), String> {
+    let (template_id, replaced) = match decision {
+        LearnDecision::Learned {
+            template_id,
+            replaced,
+        } => (template_id, replaced),
+        LearnDecision::Skipped { .. } => return Ok(()),
+    };
+
+    let entry = |action: &str, id: &str| AuditEntry {
+        time: now_secs(),
+        face_token: face_token.to_string(),
+        action: action.to_string(),
+        template_id: Some(id.to_string()),
+        score: Some(context.score),
+        threshold: Some(context.threshold),
+        liveness_passed: context.liveness_passed,
+    };
+    if let Some(replaced) = replaced {
+        append_audit(faces_dir, &entry("replaced", replaced))?;
+    }
+    append_audit(faces_dir, &entry("learned", template_id))
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn learning_context(score: f32) -> UnlockContext {
+        UnlockContext {
+            score,
+            threshold: 0.4,
+            liveness_passed: Some(true),
+            password_fallback: false,
+            quality: Some(0.8),
+            modality: Modality::Rgb,
+        }
+    }
+
+    fn enabled() -> AdaptiveConfig {
+        AdaptiveConfig {
+            enabled: true,
+            ..AdaptiveConfig::default()
+        }
+    }
+
+    fn primary(feature: Vec<f32>) -> Template {
+        Template {
+            id: String::from("primary"),
+            source: TemplateSource::Enrolled,
+            modality: Modality::Rgb,
+            feature,
+            created_at: 0,
+            match_count: 0,
+            last_matched_at: None,
+            quality: None,
+        }
+    }
+
+    #[test]
+    fn primary_template_counts_as_redundant() {
+        let mut gallery = TemplateGallery::default();
+        let primary = primary(vec![1.0, 0.0, 0.0]);
+        let decision = consider_learning(
+            &mut gallery,
+            Some(&primary),
+            &[1.0, 0.01, 0.0],
+            &learning_context(0.9),
+            &enabled(),
+        );
+        assert!(matches!(decision, LearnDecision::Skipped { .. }));
+        assert!(gallery.templates.is_empty());
+    }
+
+    #[test]
+    fn learns_when_different_from_primary() {
+        let mut gallery = TemplateGallery::default();
+        let primary = primary(vec![1.0, 0.0, 0.0]);
+        let decision = consider_learning(
+            &mut gallery,
+            Some(&primary),
+            &[0.7, 0.7, 0.0],
+            &learning_context(0.9),
+            &enabled(),
+        );
+        assert!(matches!(
+            decision,
+            LearnDecision::Learned { replaced: None, .. }
+        ));
+        assert_eq!(gallery.learned_count(), 1);
+    }
+
+    #[test]
+    fn rejects_tokens_outside_faces_dir() {
+        assert!(validate_face_token("3f2a9c").is_ok());
+        for token in ["", "../secret", "a/b", "a\\b", ".."] {
+            assert!(validate_face_token(token).is_err(), "{}", token);
+        }
+    }
+
+    fn learned(id: &str, feature: Vec<f32>, match_count: u32) -> Template {
+        Template {
+            id: id.to_string(),
+            source: TemplateSource::Learned,
+            match_count,
+            ..primary(feature)
+        }
+    }
+
+    #[test]
+    fn password_fallback_never_learns() {
+        let mut gallery = TemplateGallery::default();
+        let context = UnlockContext {
+            password_fallback: true,
+            ..learning_context(0.9)
+        };
+        let decision = consider_learning(&mut gallery, None, &[0.7, 0.7, 0.0], &context, &enabled());
+        match decision {
+            LearnDecision::Skipped { reason } => assert_eq!(reason, "本次解锁使用了密码兜底"),
+            other => panic!("{:?}", other),
+        }
+        assert!(gallery.templates.is_empty());
+    }
+
+    #[test]
+    fn margin_must_clear_threshold() {
+        let mut gallery = TemplateGallery::default();
+        // 阈值 0.4、余量 0.15：0.5 不够，0.6 刚好够
+        let decision = consider_learning(&mut gallery, None, &[0.7, 0.7, 0.0], &learning_context(0.5), &enabled());
+        match decision {
+            LearnDecision::Skipped { reason } => assert_eq!(reason, "相似度余量不足"),
+            other => panic!("{:?}", other),
+        }
+        let decision = consider_learning(&mut gallery, None, &[0.7, 0.7, 0.0], &learning_context(0.6), &enabled());
+        assert!(matches!(decision, LearnDecision::Learned { .. }));
+    }
+
+    #[test]
+    fn full_gallery_replaces_least_matched() {
+        let mut gallery = TemplateGallery::default();
+        gallery.templates.push(primary(vec![0.0, 0.0, 1.0]));
+        gallery.templates.push(learned("often", vec![1.0, 0.0, 0.0], 0));
+        gallery.templates.push(learned("rarely", vec![0.0, 1.0, 0.0], 0));
+        // 匹配过的模板保留，没有匹配过的先被淘汰
+        gallery.record_match(1);
+        gallery.record_match(1);
+
+        let config = AdaptiveConfig {
+            max_learned: 2,
+            ..enabled()
+        };
+        let decision = consider_learning(&mut gallery, None, &[0.6, 0.6, 0.5], &learning_context(0.9), &config);
+        match decision {
+            LearnDecision::Learned { replaced, .. } => assert_eq!(replaced.as_deref(), Some("rarely")),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(gallery.learned_count(), 2);
+        // 录入的模板不会被淘汰
+        assert!(gallery.templates.iter().any(|t| t.id == "primary"));
+        assert!(gallery.templates.iter().any(|t| t.id == "often"));
+    }
+}
@@ -0,0 +1,454 @@
+// 一次解锁尝试的识别流程：逐帧检测 → 跟踪 → 质量门槛 → 比对 → 多人脸策略 → 翻拍/打印检查 → 多帧活体投票，结束时给出是否解锁和 AttemptLog
+// 解锁成功后按自适应学习的设置决定是否把本次特征加入模板库
+// 界面录制回放（record_replay）和离线评估工具（face_eval --replay）共用，AttemptLog 与解锁服务写入 unlock_log 的格式一致
+use std::path::Path;
+
+use opencv::{
+    core::{Mat, Ptr},
+    dnn,
+    objdetect::{FaceDetectorYN, FaceRecognizerSF},
+    prelude::*,
+};
+
+use super::{
+    attempt_log::AttemptLog,
+    face_finder::{FaceFinder, FinderConfig},
+    face_pipeline::{extract_feature, face_values, feature_to_vec, image_feature},
+    face_policy::{apply_policy, FaceCandidate, MultiFaceConfig, MultiFacePolicy},
+    face_quality::{assess_face_quality, QualityConfig},
+    face_tracker::FaceTracker,
+    frame_normalize::{modality_compatible, Modality},
+    liveness::{face_crop, liveness_score, LivenessVerdict, LivenessVoter, LivenessVotingConfig},
+    replay::{FrameOutputs, ReplayOutcome},
+    spoof_heuristics::{fuse, spoof_scores, FusionConfig},
+    template_gallery::{
+        audit_decision, consider_learning, cosine_similarity, now_secs, AdaptiveConfig, LearnDecision, Template,
+        TemplateGallery, TemplateSource, UnlockContext,
+    },
+};
+
+// 面容没有设置 faceDetectionThreshold 时的检测阈值
+const DEFAULT_FACE_DETECTION_THRESHOLD: f32 = 0.9;
+// 主模板不在模板库中，用固定的 id 区分
+pub const PRIMARY_TEMPLATE_ID: &str = "primary";
+
+#[derive(Debug, Clone, Copy)]
+pub struct AttemptConfig {
+    pub face_detection_threshold: f32,
+    // 检测的工作尺寸和区域跟踪
+    pub finder: FinderConfig,
+    // 多帧活体投票，未开启活体检测时为 None
+    pub liveness: Option<LivenessVotingConfig>,
+    // 翻拍/打印检查，开启时与活体分数融合后再投票
+    pub fusion: FusionConfig,
+    // 质量不合格的帧不参与解锁，与录入时使用同一套门槛
+    pub quality: QualityConfig,
+    pub adaptive: AdaptiveConfig,
+    // 画面中有多张人脸时是否允许解锁
+    pub multi_face: MultiFaceConfig,
+}
+
+impl AttemptConfig {
+    // 从设置中读取，livenessEnabled 不为 true 时不做活体检测
+    // 检测阈值保存在面容中，调用方按需覆盖 face_detection_threshold
+    pub fn from_options(get: impl Fn(&str) -> Option<String>) -> Self {
+        let liveness_enabled = get("livenessEnabled").as_deref() == Some("true");
+        Self {
+            face_detection_threshold: DEFAULT_FACE_DETECTION_THRESHOLD,
+            finder: FinderConfig::from_options(&get),
+            liveness: liveness_enabled.then(|| LivenessVotingConfig::from_options(&get)),
+            fusion: FusionConfig::from_options(&get),
+            quality: QualityConfig::from_options(&get),
+            adaptive: AdaptiveConfig::from_options(&get),
+            multi_face: MultiFaceConfig::from_options(&get),
+        }
+    }
+}
+
+// 参与比对的面容
+pub struct EnrolledFace {
+    pub id: i64,
+    pub face_token: String,
+    // 面容的比对阈值（0 ~ 1）
+    pub threshold: f32,
+    // 录入时的主模板，取自 faces/<face_token>.faceimg，图片中检测不到人脸时为 None
+    pub primary: Option<Template>,
+    pub gallery: TemplateGallery,
+}
+
+impl EnrolledFace {
+    // 与主模板和模板库中同一成像方式的模板比对，返回最高相似度
+    fn best_score(&self, feature: &[f32], modality: Modality) -> Option<f32> {
+        let primary = self
+            .primary
+            .as_ref()
+            .filter(|t| modality_compatible(modality, t.modality))
+            .map(|t| cosine_similarity(&t.feature, feature));
+        let gallery = self.gallery.best_match(feature, modality).map(|(_, score)| score);
+        match (primary, gallery) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    // 模板库中比主模板更接近的模板下标，解锁时记为这个模板匹配成功，淘汰学习模板时参考
+    fn matched_gallery_template(&self, feature: &[f32], modality: Modality) -> Option<usize> {
+        let primary = self
+            .primary
+            .as_ref()
+            .filter(|t| modality_compatible(modality, t.modality))
+            .map(|t| cosine_similarity(&t.feature, feature));
+        self.gallery
+            .best_match(feature, modality)
+            .filter(|(_, score)| primary.is_none_or(|p| *score > p))
+            .map(|(index, _)| index)
+    }
+}
+
+// 从面容图片提取主模板，与解锁服务读取 .face 得到的特征一致
+pub fn load_primary_template(
+    detector: &mut Ptr<FaceDetectorYN>,
+    recognizer: &mut Ptr<FaceRecognizerSF>,
+    faces_dir: &Path,
+    face_token: &str,
+    modality: Modality,
+    face_detection_threshold: f32,
+) -> Result<Option<Template>, String> {
+    let image_path = faces_dir.join(format!("{}.faceimg", face_token));
+    let feature = match image_feature(
+        detector,
+        recognizer,
+        image_path.to_str().unwrap_or(""),
+        face_detection_threshold,
+    )? {
+        Some(feature) => feature_to_vec(&feature)?,
+        None => return Ok(None),
+    };
+    Ok(Some(Template {
+        id: PRIMARY_TEMPLATE_ID.to_string(),
+        source: TemplateSource::Enrolled,
+        modality,
+        feature,
+        created_at: now_secs(),
+        match_count: 0,
+        last_matched_at: None,
+        quality: None,
+    }))
+}
+
+pub struct AttemptModels<'a> {
+    pub detector: &'a mut Ptr<FaceDetectorYN>,
+    pub recognizer: &'a mut Ptr<FaceRecognizerSF>,
+    // 未加载活体模型时为 None，此时配置了活体检测的尝试不会解锁
+    pub liveness: Option<&'a mut dnn::Net>,
+}
+
+// 整次尝试的结果
+#[derive(Debug, Clone)]
+pub struct AttemptResult {
+    pub unlocked: bool,
+    pub matched: Option<i64>,
+    pub reason: Option<String>,
+    pub log: AttemptLog,
+}
+
+impl AttemptResult {
+    // 写入回放文件的尝试结果
+    pub fn to_outcome(&self) -> ReplayOutcome {
+        ReplayOutcome {
+            unlocked: self.unlocked,
+            matched: self.matched,
+            reason: self.reason.clone(),
+            attempt: Some(self.log.clone()),
+        }
+    }
+}
+
+// 解锁时的那一帧，用于自适应学习
+struct UnlockMatch {
+    // faces 中的下标
+    face: usize,
+    feature: Vec<f32>,
+    score: f32,
+    quality: f32,
+    modality: Modality,
+}
+
+pub struct UnlockAttempt {
+    config: AttemptConfig,
+    faces: Vec<EnrolledFace>,
+    finder: FaceFinder,
+    tracker: FaceTracker,
+    voter: Option<LivenessVoter>,
+    log: AttemptLog,
+    // 检测到人脸的帧数
+    detected: u32,
+    // 质量合格的帧数
+    quality_passed: u32,
+    // 最近一次质量不合格的原因
+    quality_reason: Option<String>,
+    // 质量合格、匹配到面容且多人脸策略允许的帧数
+    matched_frames: u32,
+    // 最近一次匹配到面容但被多人脸策略拒绝的原因
+    policy_reason: Option<String>,
+    // 最近一次参与判断的人脸，结束时用它的活体窗口写日志
+    last_track: Option<u32>,
+    // 已经解锁时的匹配，之后的帧只记录不再改变结果
+    unlocked: Option<UnlockMatch>,
+}
+
+impl UnlockAttempt {
+    pub fn new(config: AttemptConfig, faces: Vec<EnrolledFace>) -> Self {
+        Self {
+            voter: config.liveness.map(LivenessVoter::new),
+            finder: FaceFinder::new(config.finder),
+            config,
+            faces,
+            tracker: FaceTracker::default(),
+            log: AttemptLog::default(),
+            detected: 0,
+            quality_passed: 0,
+            quality_reason: None,
+            matched_frames: 0,
+            policy_reason: None,
+            last_track: None,
+            unlocked: None,
+        }
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.unlocked.is_some()
+    }
+
+    // 处理一帧，img 为归一化（和预处理）之后的 BGR 画面
+    // 返回这一帧的识别结果，frame 和 ms 由调用方填写
+    pub fn observe(
+        &mut self,
+        models: &mut AttemptModels,
+        img: &Mat,
+        modality: Modality,
+    ) -> Result<FrameOutputs, String> {
+        let mut outputs = FrameOutputs {
+            modality: Some(modality),
+            ..Default::default()
+        };
+        self.log.modality = Some(modality);
+
+        let all_faces = self
+            .finder
+            .detect(models.detector, img, self.config.face_detection_threshold)?;
+        let mut rows = Vec::new();
+        for row in 0..all_faces.rows() {
+            let values = face_values(&all_faces, row)?;
+            outputs.faces.push(values.to_vec());
+            rows.push(values);
+        }
+        let rects: Vec<[f32; 4]> = rows.iter().map(|v| [v[0], v[1], v[2], v[3]]).collect();
+        let track_ids = self.tracker.update(&rects);
+        if let Some(voter) = self.voter.as_mut() {
+            voter.retain(&self.tracker.active_ids());
+        }
+
+        // 与 largest_face 一致，取面积最大的人脸
+        let area = |i: &usize| rows[*i][2] * rows[*i][3];
+        let chosen = match (0..rows.len()).max_by(|a, b| area(a).total_cmp(&area(b))) {
+            Some(chosen) => chosen,
+            None => return Ok(outputs),
+        };
+        self.detected += 1;
+        let values = rows[chosen];
+        let track_id = track_ids[chosen];
+        self.last_track = Some(track_id);
+
+        // 不合格的帧照常比对和计算活体分数便于排查，但不会解锁
+        let quality = assess_face_quality(img, &values, &self.config.quality)?;
+        outputs.quality = Some(quality.score);
+        if quality.passed {
+            self.quality_passed += 1;
+        } else {
+            self.quality_reason = quality.reason;
+        }
+
+        // 最大人脸匹配到的 (面容下标, 相似度)
+        let mut matched: Option<(usize, f32)> = None;
+        let mut feature = Vec::new();
+        let mut candidates = Vec::new();
+        if !self.faces.is_empty() {
+            for (row, values) in rows.iter().enumerate() {
+                // 只看最大人脸的策略不需要其他人脸的特征
+                if row != chosen && self.config.multi_face.policy == MultiFacePolicy::Largest {
+                    continue;
+                }
+                let face = all_faces
+                    .row(row as i32)
+                    .and_then(|r| r.try_clone())
+                    .map_err(|e| format!("复制人脸数据失败: {:?}", e))?;
+                let face_feature = feature_to_vec(&extract_feature(models.recognizer, img, &face)?)?;
+                let scores: Vec<(usize, f32)> = self
+                    .faces
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, enrolled)| enrolled.best_score(&face_feature, modality).map(|s| (index, s)))
+                    .collect();
+                let best = scores
+                    .iter()
+                    .copied()
+                    .filter(|(index, score)| *score >= self.faces[*index].threshold)
+                    .max_by(|a, b| a.1.total_cmp(&b.1));
+                candidates.push(FaceCandidate {
+                    rect: [values[0], values[1], values[2], values[3]],
+                    matched_face_id: best.map(|(index, _)| self.faces[index].id),
+                    best_score: scores.iter().map(|(_, score)| *score as f64).fold(0.0, f64::max),
+                });
+                if row != chosen {
+                    continue;
+                }
+                for (index, score) in scores {
+                    let id = self.faces[index].id;
+                    outputs.scores.insert(id, score as f64);
+                    let best = self.log.scores.entry(id).or_insert(score as f64);
+                    *best = best.max(score as f64);
+                }
+                matched = best;
+                feature = face_feature;
+            }
+        }
+        let policy = (!candidates.is_empty()).then(|| apply_policy(&self.config.multi_face, &candidates));
+
+        let mut liveness = None;
+        if let (Some(_), Some(net)) = (&self.voter, models.liveness.as_mut()) {
+            let score = liveness_score(net, &face_crop(img, &values)?)?;
+            outputs.liveness = Some(score);
+            liveness = Some(score);
+        }
+        // 开启翻拍/打印检查时用融合后的分数投票，未开启活体检测时活体分数按 1 计、只看这一帧的融合结果
+        if self.config.fusion.enabled {
+            let heuristics = spoof_scores(img, &values, modality)?;
+            outputs.spoof = Some(fuse(liveness.unwrap_or(1.0), &heuristics, &self.config.fusion));
+        }
+        let mut verdict: Option<LivenessVerdict> = None;
+        if let (Some(voter), Some(score)) = (self.voter.as_mut(), liveness) {
+            let score = outputs.spoof.as_ref().map_or(score, |fused| fused.score);
+            verdict = voter.push(track_id, score);
+        }
+
+        if self.unlocked.is_some() {
+            return Ok(outputs);
+        }
+        // 窗口满了之后每帧都有投票结果，日志记录最新的一次
+        let liveness_passed = match (&self.voter, &verdict) {
+            (None, _) => outputs.spoof.as_ref().map_or(true, |fused| fused.passed),
+            (Some(_), Some(verdict)) => verdict.passed,
+            (Some(_), None) => false,
+        };
+        if verdict.is_some() {
+            self.log.liveness = verdict;
+        }
+        if outputs.spoof.is_some() {
+            self.log.spoof = outputs.spoof.clone();
+        }
+        let policy_allowed = policy.as_ref().map_or(false, |p| p.allowed);
+        if let (true, Some(_), Some(policy)) = (quality.passed, matched, &policy) {
+            if !policy.allowed {
+                self.policy_reason = policy.reason.clone();
+            }
+        }
+        // 日志记录最新的一次决定，解锁后不再更新
+        if policy.is_some() {
+            self.log.multi_face = policy;
+        }
+        if let (true, true, Some((face, score))) = (quality.passed, policy_allowed, matched) {
+            self.matched_frames += 1;
+            if liveness_passed {
+                self.unlocked = Some(UnlockMatch {
+                    face,
+                    feature,
+                    score,
+                    quality: quality.score,
+                    modality,
+                });
+            }
+        }
+        Ok(outputs)
+    }
+
+    // 尝试结束，没有解锁时给出原因
+    // faces_dir: 学习到新模板时保存模板库并写审计日志的目录，为 None 时只在日志中记录决定
+    pub fn finish(mut self, faces_dir: Option<&Path>) -> AttemptResult {
+        if self.log.liveness.is_none() {
+            if let (Some(voter), Some(track_id)) = (&self.voter, self.last_track) {
+                // 窗口未满时记录已有的几帧
+                self.log.liveness = voter.partial(track_id);
+            }
+        }
+
+        if let Some(unlock) = self.unlocked.take() {
+            self.log.learned = Some(self.learn(&unlock, faces_dir));
+            self.unlocked = Some(unlock);
+        }
+
+        let reason = if self.unlocked.is_some() {
+            None
+        } else if self.detected == 0 {
+            Some(String::from("未检测到人脸"))
+        } else if self.quality_passed == 0 {
+            // 每一帧的质量都不合格时给出最近的原因，界面可以直接显示
+            self.quality_reason.or_else(|| Some(String::from("人脸质量不合格")))
+        } else if self.matched_frames == 0 {
+            // 匹配到了面容但画面中还有其他人脸时给出策略的原因
+            self.policy_reason.or_else(|| Some(String::from("没有匹配的面容")))
+        } else if self.voter.is_none() {
+            // 没有活体投票时只有翻拍/打印检查会拒绝
+            Some(String::from("翻拍/打印检查未通过"))
+        } else if self.log.liveness.is_none() {
+            Some(String::from("活体检测没有结果"))
+        } else {
+            Some(String::from("活体检测未通过"))
+        };
+        AttemptResult {
+            unlocked: self.unlocked.is_some(),
+            matched: self.unlocked.map(|u| self.faces[u.face].id),
+            reason,
+            log: self.log,
+        }
+    }
+
+    // 记录匹配成功的模板，再按自适应学习的设置决定是否学习解锁时的特征，重复检查包含主模板
+    fn learn(&mut self, unlock: &UnlockMatch, faces_dir: Option<&Path>) -> LearnDecision {
+        let face = &mut self.faces[unlock.face];
+        let matched_template = face.matched_gallery_template(&unlock.feature, unlock.modality);
+        if let Some(index) = matched_template {
+            face.gallery.record_match(index);
+        }
+        let context = UnlockContext {
+            score: unlock.score,
+            threshold: face.threshold,
+            liveness_passed: self.log.liveness.as_ref().map(|v| v.passed),
+            // 这里的流程没有密码兜底
+            password_fallback: false,
+            quality: Some(unlock.quality),
+            modality: unlock.modality,
+        };
+        let decision = consider_learning(
+            &mut face.gallery,
+            face.primary.as_ref(),
+            &unlock.feature,
+            &context,
+            &self.config.adaptive,
+        );
+        let learned = matches!(decision, LearnDecision::Learned { .. });
+        if let (Some(faces_dir), true) = (faces_dir, learned || matched_template.is_some()) {
+            // 没有学习时也要保存匹配次数，只更新匹配次数时保存失败不影响本次结果
+            let saved = face
+                .gallery
+                .save(faces_dir, &face.face_token)
+                .and_then(|_| audit_decision(faces_dir, &face.face_token, &decision, &context));
+            if let (Err(e), true) = (saved, learned) {
+                return LearnDecision::Skipped {
+                    reason: format!("保存学习到的模板失败: {}", e),
+                };
+            }
+        }
+        decision
+    }
+}
@@ -0,0 +1,232 @@
+// 从视频录入面容：按间隔抽帧，逐帧做质量和活体检查，再挑出质量好、姿态差异大的几帧作为多个模板
+// 这里只做抽帧和挑选，读取视频、保存模板在 modules::video_enroll 中
+use serde::{Deserialize, Serialize};
+
+use super::{
+    face_pipeline::FACE_ROW_LEN, face_quality::QualityReport, template_gallery::cosine_similarity,
+};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct VideoEnrollConfig {
+    // 每秒抽取的帧数
+    pub sample_fps: f32,
+    // 最多处理的帧数，太长的视频只看开头
+    pub max_samples: usize,
+    // 最多生成的模板数（含主模板）
+    pub max_templates: usize,
+    // 两个模板的姿态差异至少要达到此值
+    pub min_pose_distance: f32,
+    // 与已选模板的相似度超过此值视为重复
+    pub max_redundancy: f32,
+}
+
+impl Default for VideoEnrollConfig {
+    fn default() -> Self {
+        Self {
+            sample_fps: 3.0,
+            max_samples: 60,
+            max_templates: 5,
+            min_pose_distance: 0.08,
+            max_redundancy: 0.92,
+        }
+    }
+}
+
+// 头部姿态的粗略估计，只用于比较帧之间的差异
+// yaw: 鼻尖相对两眼中点的水平偏移 / 两眼间距，与质量评估一致
+// pitch: 鼻尖在两眼和嘴角之间的相对位置，正对时约为 0
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Pose {
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl Pose {
+    pub fn from_landmarks(values: &[f32; FACE_ROW_LEN]) -> Self {
+        let eye_mid = ((values[4] + values[6]) / 2.0, (values[5] + values[7]) / 2.0);
+        let mouth_mid_y = (values[11] + values[13]) / 2.0;
+        let inter_eye = ((values[6] - values[4]).powi(2) + (values[7] - values[5]).powi(2))
+            .sqrt()
+            .max(1.0);
+        let face_height = (mouth_mid_y - eye_mid.1).max(1.0);
+        Self {
+            yaw: (values[8] - eye_mid.0) / inter_eye,
+            // 正脸的鼻尖大约在眼睛到嘴角的 55% 处
+            pitch: (values[9] - eye_mid.1) / face_height - 0.55,
+        }
+    }
+
+    pub fn distance(&self, other: &Pose) -> f32 {
+        ((self.yaw - other.yaw).powi(2) + (self.pitch - other.pitch).powi(2)).sqrt()
+    }
+}
+
+// 一个可用的候选帧
+#[derive(Debug, Clone)]
+pub struct FrameCandidate {
+    // 在视频中的帧号和时间
+    pub frame_index: i64,
+    pub time_ms: f64,
+    pub quality: QualityReport,
+    pub liveness_score: Option<f32>,
+    pub pose: Pose,
+    pub feature: Vec<f32>,
+}
+
+// 被丢弃的帧的原因统计，返回给界面
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RejectStats {
+    pub no_face: usize,
+    pub quality: usize,
+    pub liveness: usize,
+}
+
+// 抽帧进度，通过事件发给界面
+#[derive(Debug, Clone, Serialize)]
+pub struct VideoEnrollProgress {
+    // sampling 抽帧检查中，saving 保存模板中，done 完成
+    pub stage: &'static str,
+    pub processed: usize,
+    pub total: usize,
+    pub accepted: usize,
+}
+
+// 按视频帧率计算抽帧间隔（帧数）
+pub fn sample_step(video_fps: f64, config: &VideoEnrollConfig) -> i64 {
+    if video_fps <= 0.0 || config.sample_fps <= 0.0 {
+        return 1;
+    }
+    ((video_fps / config.sample_fps as f64).round() as i64).max(1)
+}
+
+// 主模板的评分：质量分越高、越接近正脸越好
+// 只取决于候选帧本身，录入时可以边抽帧边记下当前最好的一帧
+pub fn frontal_score(candidate: &FrameCandidate) -> f32 {
+    candidate.quality.score - candidate.pose.distance(&Pose::default()) * 0.5
+}
+
+// 挑选模板，返回候选帧的下标，第一个是主模板
+// 主模板取 frontal_score 最高的一帧（分数相同取靠后的），之后每次取与已选帧姿态差异最大的一帧，直到数量够或没有差异足够的帧
+pub fn select_frames(candidates: &[FrameCandidate], config: &VideoEnrollConfig) -> Vec<usize> {
+    let primary = match candidates
+        .iter()
+        .enumerate()
+        .max_by(|a, b| {
+            frontal_score(a.1)
+                .partial_cmp(&frontal_score(b.1))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .map(|(i, _)| i)
+    {
+        Some(index) => index,
+        None => return Vec::new(),
+    };
+
+    let mut selected = vec![primary];
+    while selected.len() < config.max_templates {
+        let next = candidates
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !selected.contains(i))
+            .filter(|(_, c)| {
+                selected
+                    .iter()
+                    .all(|s| cosine_similarity(&candidates[*s].feature, &c.feature) < config.max_redundancy)
+            })
+            .map(|(i, c)| {
+                let distance = selected
+                    .iter()
+                    .map(|s| candidates[*s].pose.distance(&c.pose))
+                    .fold(f32::MAX, f32::min);
+                (i, distance, c.quality.score)
+            })
+            .filter(|(_, distance, _)| *distance >= config.min_pose_distance)
+            // 姿态差异相同时取质量更好的
+            .max_by(|a, b| {
+                (a.1 + a.2 * 0.1)
+                    .partial_cmp(&(b.1 + b.2 * 0.1))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+        match next {
+            Some((index, _, _)) => selected.push(index),
+            None => break,
+        }
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(quality: f32, yaw: f32, feature: Vec<f32>) -> FrameCandidate {
+        FrameCandidate {
+            frame_index: 0,
+            time_ms: 0.0,
+            quality: QualityReport {
+                score: quality,
+                sharpness: 0.0,
+                brightness: 0.0,
+                face_size: 0.0,
+                roll: 0.0,
+                yaw,
+                occlusion_ratio: 0.0,
+                issues: Vec::new(),
+                passed: true,
+                reason: None,
+            },
+            liveness_score: None,
+            pose: Pose { yaw, pitch: 0.0 },
+            feature,
+        }
+    }
+
+    #[test]
+    fn samples_at_configured_rate() {
+        let config = VideoEnrollConfig::default();
+        assert_eq!(sample_step(30.0, &config), 10);
+        assert_eq!(sample_step(2.0, &config), 1);
+        // 读不到帧率时逐帧处理
+        assert_eq!(sample_step(0.0, &config), 1);
+    }
+
+    #[test]
+    fn primary_is_the_most_frontal_good_frame() {
+        let candidates = [
+            candidate(0.9, 0.3, vec![1.0, 0.0]),
+            candidate(0.8, 0.0, vec![1.0, 0.0]),
+            candidate(0.95, 0.5, vec![1.0, 0.0]),
+        ];
+        assert_eq!(select_frames(&candidates, &VideoEnrollConfig::default())[0], 1);
+        assert!(select_frames(&[], &VideoEnrollConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn primary_ties_pick_the_later_frame() {
+        // modules::video_enroll 抽帧时按相同规则保留主模板的画面
+        let candidates = [candidate(0.9, 0.0, vec![1.0, 0.0]), candidate(0.9, 0.0, vec![0.0, 1.0])];
+        assert_eq!(frontal_score(&candidates[0]), frontal_score(&candidates[1]));
+        assert_eq!(select_frames(&candidates, &VideoEnrollConfig::default())[0], 1);
+    }
+
+    #[test]
+    fn picks_diverse_poses_and_skips_redundant_frames() {
+        let candidates = [
+            candidate(0.9, 0.0, vec![1.0, 0.0, 0.0, 0.0]),
+            // 与正脸姿态差异太小
+            candidate(0.9, 0.03, vec![0.0, 1.0, 0.0, 0.0]),
+            // 与第 4 帧的特征几乎相同
+            candidate(0.8, 0.4, vec![0.0, 0.0, 1.0, 0.0]),
+            candidate(0.8, -0.3, vec![0.0, 0.0, 0.0, 1.0]),
+            candidate(0.9, 0.42, vec![0.0, 0.0, 1.0, 0.01]),
+        ];
+        let config = VideoEnrollConfig::default();
+        assert_eq!(select_frames(&candidates, &config), vec![0, 4, 3]);
+
+        let config = VideoEnrollConfig {
+            max_templates: 2,
+            ..config
+        };
+        assert_eq!(select_frames(&candidates, &config), vec![0, 4]);
+    }
+}
@@ -0,0 +1,120 @@
+import { invoke } from '@tauri-apps/api/core';
+import { info, error as errorLog, warn } from '@tauri-apps/plugin-log';
+import { useFacesStore } from '../stores/faces';
+import { formatObjectString, removeFace } from '../utils/function';
+
+export function useTemplateMigration() {
+    const facesStore = useFacesStore();
+
+    /**
+     * 获取当前识别模型的版本
+     * @returns {Promise<String>} 模型版本
+     */
+    const getRecognizerVersion = () => {
+        return invoke('get_model_versions').then((result) => {
+            const recognizer = result.data.find(item => item.role === 'recognizer');
+            if (!recognizer) {
+                throw '模型清单中没有人脸识别模型';
+            }
+            return recognizer.version;
+        });
+    };
+
+    /**
+     * 找出由旧版本识别模型生成特征的面容
+     * 只有记录了 modelVersion 且与当前版本不同的面容才算过期
+     * @param {String} version 当前识别模型版本
+     * @returns {Array} 需要迁移的面容
+     */
+    const findOutdatedFaces = (version) => {
+        return facesStore.faceList.filter(face => face.json_data.modelVersion && face.json_data.modelVersion !== version);
+    };
+
+    /**
+     * 找出没有记录 modelVersion 的面容
+     * 这些面容在记录版本之前录入，无法判断特征由哪个版本生成，不自动迁移
+     * @returns {Array} 版本未知的面容
+     */
+    const findUnknownFaces = () => {
+        return facesStore.faceList.filter(face => !face.json_data.modelVersion);
+    };
+
+    /**
+     * 用保存的面容图片重新生成面容特征
+     * 先生成新的特征文件并更新数据库，全部成功后才删除旧的特征和图片，任何一步失败都保留旧模板
+     * 自适应学习到的模板无法重新生成，会随旧的面容一起删除
+     * @param {Function} onProgress 进度回调 (已处理数量, 总数)
+     * @returns {Promise<Object>} { version, migrated: [面容ID], failed: [{id, alias, reason}], unknown: 版本未知的面容数量 }
+     */
+    const migrateTemplates = async (onProgress = () => {}) => {
+        // 重新生成特征需要检测和识别模型，先确保模型已加载
+        await invoke('load_opencv_model');
+        const version = await getRecognizerVersion();
+        const outdated = findOutdatedFaces(version);
+        const report = { version, migrated: [], failed: [], unknown: findUnknownFaces().length };
+        if (report.unknown > 0) {
+            warn(`有 ${report.unknown} 个面容没有记录识别模型版本，不会自动迁移`);
+        }
+        const exeDir = localStorage.getItem("exe_dir");
+
+        for (let i = 0; i < outdated.length; i++) {
+            const face = outdated[i];
+            // editFace 会更新列表中的 face_token，先记下旧的
+            const oldToken = face.face_token;
+            const alias = face.json_data.alias || face.user_name;
+            const faceDetectionThreshold = face.json_data.faceDetectionThreshold ?? 0.9;
+            let newToken = "";
+
+            try {
+                const image = await invoke("check_face_from_img", {
+                    imgPath: exeDir + "\\faces\\" + oldToken + ".faceimg",
+                    faceDetectionThreshold
+                });
+                if (image.data.display_base64 === "未检测到人脸" || !image.data.raw_base64) {
+                    throw '面容图片中未检测到人脸';
+                }
+
+                const saved = await invoke("save_face_registration", {
+                    name: face.json_data.alias || '',
+                    referenceBase64: image.data.raw_base64.split(',')[1],
+                    faceDetectionThreshold
+                });
+                newToken = saved.data.file_name;
+
+                await facesStore.editFace({
+                    user_name: face.user_name,
+                    user_pwd: face.user_pwd,
+                    account_type: face.account_type,
+                    face_token: newToken,
+                    json_data: JSON.stringify({ ...face.json_data, modelVersion: version })
+                }, face.id);
+
+                removeFace(oldToken, "删除旧面容");
+                report.migrated.push(face.id);
+                info(`面容 ${alias} 已迁移到识别模型 ${version}`);
+            } catch (error) {
+                // 新生成的文件没有用到，删掉，旧模板保持不变
+                if (newToken) {
+                    removeFace(newToken, "删除迁移失败的面容");
+                }
+                const reason = formatObjectString(error);
+                report.failed.push({ id: face.id, alias, reason });
+                warn(formatObjectString(`面容 ${alias} 迁移失败：`, reason));
+            }
+
+            onProgress(i + 1, outdated.length);
+        }
+
+        if (report.failed.length > 0) {
+            errorLog(`面容模板迁移完成，成功 ${report.migrated.length} 个，失败 ${report.failed.length} 个`);
+        }
+        return report;
+    };
+
+    return {
+        getRecognizerVersion,
+        findOutdatedFaces,
+        findUnknownFaces,
+        migrateTemplates
+    };
+}
@@ -124,11 +124,68 @@ export function useUnlockLog() {
         });
     }
 
+    /**
+     * 标注一次解锁尝试的尝试者，写入 json_data.label
+     * @param {number} id - 日志ID
+     * @param {string|null} jsonData - 原来的 json_data
+     * @param {number|null|undefined} faceId - 尝试者对应的面容ID，null 表示不是已录入面容的本人，undefined 清除标注
+     * @returns {Promise<string>} 新的 json_data
+     */
+    const labelAttempt = (id, jsonData, faceId) => {
+        return new Promise((resolve, reject) => {
+            let detail = {};
+            try {
+                detail = jsonData ? JSON.parse(jsonData) : {};
+            } catch (error) {
+                // 无法解析的旧数据不覆盖
+                const info = formatObjectString(`解锁日志 ${id} 的详细信息无法解析：`, error);
+                warn(info);
+                reject(info);
+                return;
+            }
+            if (faceId === undefined) {
+                delete detail.label;
+            } else {
+                detail.label = { face_id: faceId };
+            }
+
+            const newJsonData = JSON.stringify(detail);
+            update("unlock_log", {json_data: newJsonData}, "id = ?", [id]).then(()=>{
+                resolve(newJsonData);
+            }).catch((error)=>{
+                const info = formatObjectString(`标注解锁日志 ${id} 失败：`, error);
+                errorLog(info);
+                reject(error);
+            });
+        });
+    };
+
+    /**
+     * 查询已标注尝试者的解锁日志，阈值校准（calibrate_threshold 的 attempts 参数）使用
+     * @returns {Promise<Array<string>>} 已标注日志的 json_data
+     */
+    const queryLabeledAttempts = () => {
+        return new Promise((resolve, reject) => {
+            selectCustom(
+                `SELECT json_data FROM unlock_log WHERE json_data LIKE '%"label":%' ORDER BY lastTime DESC`,
+                []
+            ).then((result) => {
+                resolve((result.rows || []).map(row => row.json_data));
+            }).catch((error) => {
+                const info = formatObjectString("查询已标注的解锁日志失败：", error);
+                errorLog(info);
+                reject(error);
+            });
+        });
+    };
+
     return {
         queryAllLogs,
         queryLogsByPage,
         queryTodayLogs,
         queryLogsByDate,
-        deleteBlockImage
+        deleteBlockImage,
+        labelAttempt,
+        queryLabeledAttempts
     };
 }
\ No newline at end of file
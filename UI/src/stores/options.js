@@ -45,6 +45,17 @@ export const useOptionsStore = defineStore('options', {
             const result = this.getOptionByKey(key);
             return result.data ? result.data.val : null;
         },
+        /**
+         * 获取全部设置 {设置名称: 设置值}，传给后端的 from_options 使用
+         * @returns {Object<String, String>}
+         */
+        getOptionsMap(){
+            const result = {};
+            for (const item of this.list) {
+                result[item.key] = item.val;
+            }
+            return result;
+        },
         /**
          * 保存设置，如果库中已存在，则会替换，否则会添加
          * @param {Object} optionObject 要保存的设置对象
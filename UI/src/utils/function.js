@@ -99,7 +99,7 @@ function handleLocalAccount(authForm, add){
  * @param {string} tips 提示信息
  */
 function removeFace(face_name, tips = "删除面容"){
-    const { reomve } = useFile();
+    const { exists, reomve } = useFile();
 
     reomve("faces\\" + face_name + ".faceimg").catch((error)=>{
         const info = formatObjectString(tips + "图片失败：", error);
@@ -112,6 +112,31 @@ function removeFace(face_name, tips = "删除面容"){
         warn(info);
         ElMessage.warning(info);
     });
+
+    // 模板库只在开启自适应学习后才会生成，先确认存在再删除，其他错误照常提示
+    const galleryPath = "faces\\" + face_name + ".gallery";
+    exists(galleryPath).then((found)=>{
+        if(found){
+            return reomve(galleryPath);
+        }
+    }).catch((error)=>{
+        const info = formatObjectString(tips + "模板库失败：", error);
+        warn(info);
+        ElMessage.warning(info);
+    });
+}
+
+/**
+ * 摄像头采集参数、预处理设置的键，与后端 CameraDescriptor::profile_key 一致
+ * 序号会随插拔变化，优先使用设备路径，其次设备名称，都没有时才用序号
+ * @param {Object} descriptor 摄像头的稳定标识 {name, devicePath, index}
+ * @returns {string} 设置中使用的键
+ */
+function cameraProfileKey(descriptor){
+    if(!descriptor){
+        return '';
+    }
+    return descriptor.devicePath || descriptor.name || `#${descriptor.index}`;
 }
 
 /**
@@ -128,5 +153,5 @@ async function hashMessage(message) {
 }
 
 export {
-    formatObjectString, getCurrentDateTime, getFileNameByWindows, handleLocalAccount, removeFace, hashMessage
+    formatObjectString, getCurrentDateTime, getFileNameByWindows, handleLocalAccount, removeFace, cameraProfileKey, hashMessage
 }
\ No newline at end of file
@@ -32,13 +32,16 @@ const databseTable = [
             // windows账户类型
             { name: 'account_type', type: 'TEXT', notNull: true },
             // 人脸特征标识符和图片的存储路径
-            // 特征是 .face 图片是 .faceimg
+            // 特征是 .face 图片是 .faceimg，开启自适应学习后还有模板库 .gallery
             { name: 'face_token', type: 'TEXT', notNull: true },
             // 其余JSON数据，便于随时添加
             // alias 面容别名
             // threshold 置信度
             // view 是否在列表页显示图片缩略图
             // faceDetectionThreshold 人脸的置信度
+            // quality 录入图片的质量分 0~1
+            // modality 录入时的成像方式 rgb / ir，没有时为 rgb
+            // modelVersion 生成特征的识别模型版本
             { name: 'json_data', type: 'TEXT', notNull: true },
             // 创建时间
             { name: 'createTime', type: 'TEXT', defaultValue: "datetime('now', 'localtime')" }
@@ -54,6 +57,14 @@ const databseTable = [
             { name: 'is_unlock', type: 'INTEGER', notNull: true },
             // 解锁失败时的截图
             { name: 'block_img', type: 'TEXT' },
+            // 其余JSON数据，便于随时添加
+            // scores 本次尝试与每个面容的相似度 {面容ID: 0~1}
+            // liveness 多帧活体投票结果 {frame_scores, aggregation, aggregate_score, pass_ratio, passed}
+            // learned 自适应模板学习结果 {action: learned/skipped, template_id, replaced, reason}
+            // modality 本次画面的成像方式 rgb / ir
+            // multi_face 多人脸策略的决定 {policy, face_count, unknown_count, matched_face_id, allowed, reason}
+            // spoof 翻拍/打印检查与活体分数的融合结果 {rule, liveness_score, heuristics: {moire, specular, bezel, gamut}, score, passed}
+            { name: 'json_data', type: 'TEXT' },
             // 上次更新时间
             { name: 'lastTime', type: 'TEXT', defaultValue: "datetime('now', 'localtime')" }
         ]